@@ -0,0 +1,345 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    error::HrdfError, hrdf::Hrdf, models::Version, storage::LoadOptions,
+    utils::create_temporary_file,
+};
+
+#[cfg(feature = "mmap-cache")]
+mod mapped;
+
+#[cfg(feature = "mmap-cache")]
+pub use mapped::{
+    open as open_mapped, write as write_mapped, write_temporary as write_temporary_mapped,
+    MappedHrdf,
+};
+
+// Identifies the cache files, so that any other file is rejected before being deserialized.
+const MAGIC: &[u8; 8] = b"HRDFCACH";
+
+// Must be incremented whenever the layout of the cached data changes.
+const SCHEMA_VERSION: u32 = 15;
+
+// Set in the flags of the cache file if the data is compressed.
+const FLAG_COMPRESSED: u8 = 1;
+
+// The default level of zstd, which compresses fast enough not to slow down the building of the cache.
+#[cfg(feature = "compressed-cache")]
+const COMPRESSION_LEVEL: i32 = 3;
+
+// ------------------------------------------------------------------------------------------------
+// --- CacheHeader
+// ------------------------------------------------------------------------------------------------
+
+/// Describes what the cached data has been built from.<br>
+/// The cache is only used if its header is equal to the one expected for the data being loaded.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct CacheHeader {
+    crate_version: String,
+    // None if the version is detected from the data.
+    version: Option<Version>,
+    // SHA-256 of the HRDF archive, fingerprint of the directory or key provided by the caller (see HrdfLoader::cache_key).
+    source_hash: String,
+    load_options: LoadOptions,
+}
+
+impl CacheHeader {
+    pub fn new(version: Option<Version>, source_hash: String, load_options: LoadOptions) -> Self {
+        Self {
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            version,
+            source_hash,
+            load_options,
+        }
+    }
+
+    // Functions
+
+    /// The caches built with other load options are kept in other files, so that they don't overwrite each other.
+    pub fn file_name(&self) -> String {
+        // unwrap: Serializing a LoadOptions will never fail.
+        let load_options = bincode::serialize(&self.load_options).unwrap();
+        let load_options_hash = format!("{:x}", Sha256::digest(load_options));
+        format!("{}-{}.cache", self.source_hash, &load_options_hash[..8])
+    }
+
+    /// Describes the first difference between the two headers.
+    fn mismatch(&self, expected: &Self) -> Option<String> {
+        if let Some(reason) = self.mismatch_crate_version() {
+            Some(reason)
+        } else if self.version != expected.version {
+            let describe = |version: Option<Version>| {
+                version.map_or("detected".to_string(), |version| version.to_string())
+            };
+            Some(format!(
+                "built for the HRDF version {}, not {}",
+                describe(self.version),
+                describe(expected.version)
+            ))
+        } else if self.source_hash != expected.source_hash {
+            Some("built from other HRDF data".to_string())
+        } else if self.load_options != expected.load_options {
+            Some(format!(
+                "built with the load options {:?}, not {:?}",
+                self.load_options, expected.load_options
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// The cached data can only be read by the crate version that has written it.
+    fn mismatch_crate_version(&self) -> Option<String> {
+        let crate_version = env!("CARGO_PKG_VERSION");
+        (self.crate_version != crate_version).then(|| {
+            format!(
+                "built by version {} of the crate, not {crate_version}",
+                self.crate_version
+            )
+        })
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// --- Functions
+// ------------------------------------------------------------------------------------------------
+
+/// The cache file is made of the magic bytes, the schema version, the flags, the header and the data.<br>
+/// Only the data is compressed, so that the header can be checked without decompressing anything.<br>
+/// The file is written under a temporary name first, so that an interrupted write doesn't leave a truncated cache behind.
+/// Each write uses its own temporary file, so that concurrent loads don't write into the same file.
+pub fn write(
+    path: &Path,
+    header: &CacheHeader,
+    hrdf: &Hrdf,
+    compressed: bool,
+) -> Result<(), HrdfError> {
+    let flags = if compressed { FLAG_COMPRESSED } else { 0 };
+
+    let mut writer = BufWriter::new(create_temporary_file(path)?);
+    writer.write_all(MAGIC)?;
+    writer.write_all(&SCHEMA_VERSION.to_le_bytes())?;
+    writer.write_all(&[flags])?;
+    bincode::serialize_into(&mut writer, header)?;
+    if compressed {
+        write_compressed(&mut writer, hrdf)?;
+    } else {
+        bincode::serialize_into(&mut writer, hrdf)?;
+    }
+    let temporary_file = writer.into_inner().map_err(|e| e.into_error())?;
+    temporary_file.as_file().sync_all()?;
+
+    temporary_file.persist(path).map_err(|e| e.error)?;
+    Ok(())
+}
+
+/// Returns HrdfError::CacheMismatch if the cache doesn't match the expected header.<br>
+/// Both the compressed and the uncompressed caches are read.
+pub fn read(path: &Path, expected: &CacheHeader) -> Result<Hrdf, HrdfError> {
+    let mut reader = BufReader::new(File::open(path)?);
+
+    let mut magic = [0; 8];
+    let mut schema_version = [0; 4];
+    let mut flags = [0; 1];
+    if reader.read_exact(&mut magic).is_err() || &magic != MAGIC {
+        return Err(HrdfError::CacheMismatch(format!(
+            "{} is not a cache file",
+            path.display()
+        )));
+    }
+    reader.read_exact(&mut schema_version)?;
+
+    let schema_version = u32::from_le_bytes(schema_version);
+    if schema_version != SCHEMA_VERSION {
+        return Err(HrdfError::CacheMismatch(format!(
+            "schema version {schema_version} instead of {SCHEMA_VERSION}"
+        )));
+    }
+
+    reader.read_exact(&mut flags)?;
+
+    let header: CacheHeader = bincode::deserialize_from(&mut reader)?;
+    if let Some(reason) = header.mismatch(expected) {
+        return Err(HrdfError::CacheMismatch(reason));
+    }
+
+    if flags[0] & FLAG_COMPRESSED != 0 {
+        read_compressed(&mut reader)
+    } else {
+        Ok(bincode::deserialize_from(&mut reader)?)
+    }
+}
+
+#[cfg(feature = "compressed-cache")]
+fn write_compressed(writer: &mut impl Write, hrdf: &Hrdf) -> Result<(), HrdfError> {
+    let mut encoder = zstd::Encoder::new(writer, COMPRESSION_LEVEL)?;
+    bincode::serialize_into(&mut encoder, hrdf)?;
+    encoder.finish()?;
+    Ok(())
+}
+
+#[cfg(not(feature = "compressed-cache"))]
+fn write_compressed(_writer: &mut impl Write, _hrdf: &Hrdf) -> Result<(), HrdfError> {
+    Err(HrdfError::InvalidData(
+        "Compressing the cache requires the compressed-cache feature.".to_string(),
+    ))
+}
+
+#[cfg(feature = "compressed-cache")]
+fn read_compressed(reader: &mut impl BufRead) -> Result<Hrdf, HrdfError> {
+    let decoder = zstd::Decoder::with_buffer(reader)?;
+    Ok(bincode::deserialize_from(decoder)?)
+}
+
+#[cfg(not(feature = "compressed-cache"))]
+fn read_compressed(_reader: &mut impl BufRead) -> Result<Hrdf, HrdfError> {
+    Err(HrdfError::CacheMismatch(
+        "compressed with zstd, which requires the compressed-cache feature".to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use zip::CompressionMethod;
+
+    use crate::fixtures::MemorySource;
+
+    use super::*;
+
+    fn hrdf() -> Hrdf {
+        let zip = MemorySource::new().to_zip(CompressionMethod::Stored);
+        Hrdf::from_zip_bytes(Version::V_5_40_41_2_0_4, zip).unwrap()
+    }
+
+    fn header(version: Version, source_hash: &str) -> CacheHeader {
+        CacheHeader::new(
+            Some(version),
+            source_hash.to_string(),
+            LoadOptions::default(),
+        )
+    }
+
+    #[test]
+    fn cache_is_read_with_the_same_header() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("data.cache");
+        let header = header(Version::V_5_40_41_2_0_4, "a");
+        write(&path, &header, &hrdf(), false).unwrap();
+
+        let hrdf = read(&path, &header).unwrap();
+        assert_eq!(hrdf.version(), Version::V_5_40_41_2_0_4);
+        assert_eq!(hrdf.data_storage().journeys().entries().len(), 2);
+    }
+
+    #[test]
+    fn header_mismatch_is_reported() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("data.cache");
+        write(
+            &path,
+            &header(Version::V_5_40_41_2_0_4, "a"),
+            &hrdf(),
+            false,
+        )
+        .unwrap();
+
+        let result = read(&path, &header(Version::V_5_40_41_2_0_5, "a"));
+        assert!(
+            matches!(result, Err(HrdfError::CacheMismatch(reason)) if reason.contains("HRDF version"))
+        );
+        let result = read(&path, &header(Version::V_5_40_41_2_0_4, "b"));
+        assert!(
+            matches!(result, Err(HrdfError::CacheMismatch(reason)) if reason.contains("other HRDF data"))
+        );
+
+        let expected = CacheHeader::new(
+            Some(Version::V_5_40_41_2_0_4),
+            "a".to_string(),
+            LoadOptions::minimal(),
+        );
+        let result = read(&path, &expected);
+        assert!(
+            matches!(result, Err(HrdfError::CacheMismatch(reason)) if reason.contains("load options"))
+        );
+    }
+
+    #[test]
+    fn other_files_are_rejected() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("data.cache");
+        let header = header(Version::V_5_40_41_2_0_4, "a");
+
+        std::fs::write(&path, "content").unwrap();
+        assert!(matches!(
+            read(&path, &header),
+            Err(HrdfError::CacheMismatch(_))
+        ));
+
+        let mut content = MAGIC.to_vec();
+        content.extend((SCHEMA_VERSION - 1).to_le_bytes());
+        std::fs::write(&path, content).unwrap();
+        assert!(matches!(
+            read(&path, &header),
+            Err(HrdfError::CacheMismatch(reason)) if reason.contains("schema version")
+        ));
+    }
+
+    #[test]
+    fn file_name_depends_on_load_options() {
+        let header = header(Version::V_5_40_41_2_0_4, "a");
+        let minimal_header = CacheHeader::new(
+            Some(Version::V_5_40_41_2_0_4),
+            "a".to_string(),
+            LoadOptions::minimal(),
+        );
+        assert!(header.file_name().starts_with("a-"));
+        assert_ne!(header.file_name(), minimal_header.file_name());
+    }
+
+    #[cfg(feature = "compressed-cache")]
+    #[test]
+    fn compressed_cache_is_read() {
+        let directory = tempfile::tempdir().unwrap();
+        let header = header(Version::V_5_40_41_2_0_4, "a");
+        let path = directory.path().join("data.cache");
+        let compressed_path = directory.path().join("compressed.cache");
+        write(&path, &header, &hrdf(), false).unwrap();
+        write(&compressed_path, &header, &hrdf(), true).unwrap();
+
+        let size = |path| std::fs::metadata(path).unwrap().len();
+        assert!(size(&compressed_path) < size(&path));
+
+        let hrdf = read(&compressed_path, &header).unwrap();
+        assert_eq!(hrdf.data_storage().journeys().entries().len(), 2);
+        assert_eq!(hrdf.data_storage().stops().entries().len(), 3);
+    }
+
+    #[cfg(not(feature = "compressed-cache"))]
+    #[test]
+    fn compressed_cache_requires_feature() {
+        let directory = tempfile::tempdir().unwrap();
+        let header = header(Version::V_5_40_41_2_0_4, "a");
+        let path = directory.path().join("data.cache");
+
+        let result = write(&path, &header, &hrdf(), true);
+        assert!(matches!(result, Err(HrdfError::InvalidData(_))));
+
+        // A cache compressed by a build with the feature is rejected, so that it's rebuilt.
+        let mut content = MAGIC.to_vec();
+        content.extend(SCHEMA_VERSION.to_le_bytes());
+        content.push(FLAG_COMPRESSED);
+        content.extend(bincode::serialize(&header).unwrap());
+        std::fs::write(&path, content).unwrap();
+        assert!(matches!(
+            read(&path, &header),
+            Err(HrdfError::CacheMismatch(reason)) if reason.contains("compressed-cache")
+        ));
+    }
+}
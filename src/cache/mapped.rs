@@ -0,0 +1,360 @@
+use std::{
+    fmt::Display,
+    fs::File,
+    io::{self, BufWriter, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use memmap2::Mmap;
+use rkyv::{
+    ser::{
+        serializers::{AllocScratch, CompositeSerializer, SharedSerializeMap, WriteSerializer},
+        Serializer,
+    },
+    Deserialize,
+};
+
+use crate::{
+    error::HrdfError,
+    hrdf::{ArchivedHrdf, Hrdf},
+    utils::create_temporary_file,
+};
+
+use super::{CacheHeader, SCHEMA_VERSION};
+
+// Identifies the memory-mapped cache files, they can't be read as bincode cache files and vice versa.
+const MAGIC: &[u8; 8] = b"HRDFMMAP";
+
+// The archived data must be aligned in the file, as the file is mapped at the start of a page.
+const ALIGNMENT: usize = 16;
+
+// Positions in the file, after the magic bytes and the schema version.
+const HEADER_LENGTH_START: usize = MAGIC.len() + 4;
+const DATA_LENGTH_START: usize = HEADER_LENGTH_START + 8;
+const HEADER_START: usize = DATA_LENGTH_START + 8;
+
+// ------------------------------------------------------------------------------------------------
+// --- MappedHrdf
+// ------------------------------------------------------------------------------------------------
+
+/// The HRDF data read in place from a memory-mapped cache file, without being deserialized.<br>
+/// The pages of the file are shared between all the processes mapping it, they are only loaded when accessed.
+pub struct MappedHrdf {
+    mmap: Mmap,
+    // Position of the archived data in the file.
+    offset: usize,
+}
+
+impl MappedHrdf {
+    /// Opens a cache file written by HrdfLoader::load_path_mapped, e.g. by another process.<br>
+    /// The file is checked to be a cache of the current crate version, but not to match any HRDF data.
+    /// The whole data is validated, which requires reading the whole file.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, HrdfError> {
+        open(path.as_ref(), None, true)
+    }
+
+    /// Same as open, but only the header and the length of the data are checked, the data itself isn't validated.
+    ///
+    /// # Safety
+    ///
+    /// The file must have been written by HrdfLoader::load_path_mapped with the same crate version and features,
+    /// and must not have been modified since. Otherwise, reading the data is undefined behavior.
+    pub unsafe fn open_unchecked(path: impl AsRef<Path>) -> Result<Self, HrdfError> {
+        open(path.as_ref(), None, false)
+    }
+
+    // Getters/Setters
+
+    pub fn hrdf(&self) -> &ArchivedHrdf {
+        // SAFETY: The archived data has been validated when it was mapped, unless the caller of open_unchecked guaranteed that it's valid.
+        // The mapping is read-only and the cache files are never modified in place, they are replaced by renaming a new file.
+        unsafe { rkyv::archived_root::<Hrdf>(&self.mmap[self.offset..]) }
+    }
+
+    // Functions
+
+    /// Deserializes the whole data, e.g. to use the functions only available on Hrdf.
+    pub fn to_hrdf(&self) -> Result<Hrdf, HrdfError> {
+        self.hrdf()
+            .deserialize(&mut rkyv::de::deserializers::SharedDeserializeMap::new())
+            .map_err(|e| HrdfError::CacheMismatch(format!("the cached data is invalid: {e}")))
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// --- Functions
+// ------------------------------------------------------------------------------------------------
+
+/// The cache file is made of the magic bytes, the schema version, the lengths of the header and of the data, the header, padding and the archived data.<br>
+/// The file is written under a temporary name first, so that an interrupted write doesn't leave a truncated cache behind.
+/// Each write uses its own temporary file, so that concurrent loads don't write into the same file.
+/// The written file is returned mapped, it remains valid once the file has been renamed.
+pub fn write(path: &Path, header: &CacheHeader, hrdf: &Hrdf) -> Result<MappedHrdf, HrdfError> {
+    let mut temporary_file = create_temporary_file(path)?;
+    let mapped_hrdf = write_file(temporary_file.as_file_mut(), path.display(), header, hrdf)?;
+    temporary_file.as_file().sync_all()?;
+
+    temporary_file.persist(path).map_err(|e| e.error)?;
+    Ok(mapped_hrdf)
+}
+
+/// Same as write followed by open, but the file is anonymous, e.g. when the cache is disabled.<br>
+/// The file is removed by the system once it's no longer mapped.
+pub fn write_temporary(
+    directory: &Path,
+    header: &CacheHeader,
+    hrdf: &Hrdf,
+) -> Result<MappedHrdf, HrdfError> {
+    let mut file = tempfile::tempfile_in(directory)?;
+    write_file(&mut file, "the temporary cache", header, hrdf)
+}
+
+/// Returns HrdfError::CacheMismatch if the cache doesn't match the expected header (if any).<br>
+/// The archived data must only be left unvalidated if the file is known to be valid (see MappedHrdf::open_unchecked).
+pub fn open(
+    path: &Path,
+    expected: Option<&CacheHeader>,
+    validate: bool,
+) -> Result<MappedHrdf, HrdfError> {
+    let file = File::open(path)?;
+    map(&file, path.display(), expected, validate)
+}
+
+fn write_file(
+    file: &mut File,
+    name: impl Display,
+    header: &CacheHeader,
+    hrdf: &Hrdf,
+) -> Result<MappedHrdf, HrdfError> {
+    let header = bincode::serialize(header)?;
+    let header_end = HEADER_START + header.len();
+    let padding = header_end.next_multiple_of(ALIGNMENT) - header_end;
+
+    let mut writer = BufWriter::new(&mut *file);
+    writer.write_all(MAGIC)?;
+    writer.write_all(&SCHEMA_VERSION.to_le_bytes())?;
+    writer.write_all(&(header.len() as u64).to_le_bytes())?;
+    // The length of the data is only known once it has been written.
+    writer.write_all(&0u64.to_le_bytes())?;
+    writer.write_all(&header)?;
+    writer.write_all(&[0; ALIGNMENT][..padding])?;
+
+    // The archived data is written directly to the file, as it can be larger than the available memory.
+    let mut serializer = CompositeSerializer::new(
+        WriteSerializer::new(writer),
+        AllocScratch::default(),
+        SharedSerializeMap::default(),
+    );
+    serializer
+        .serialize_value(hrdf)
+        .map_err(|e| io::Error::other(format!("{e:?}")))?;
+    let data_len = serializer.pos() as u64;
+
+    let mut writer = serializer.into_serializer().into_inner();
+    writer.seek(SeekFrom::Start(DATA_LENGTH_START as u64))?;
+    writer.write_all(&data_len.to_le_bytes())?;
+    writer.flush()?;
+    drop(writer);
+
+    // The data is validated once here, the returned mapping can then be read without validating it again.
+    map(file, name, None, true)
+}
+
+fn map(
+    file: &File,
+    name: impl Display,
+    expected: Option<&CacheHeader>,
+    validate: bool,
+) -> Result<MappedHrdf, HrdfError> {
+    // SAFETY: The cache files are never modified in place, they are replaced by renaming a new file.
+    let mmap = unsafe { Mmap::map(file)? };
+
+    let invalid = || HrdfError::CacheMismatch(format!("{name} is not a cache file"));
+
+    if mmap.get(..MAGIC.len()) != Some(MAGIC.as_slice()) {
+        return Err(invalid());
+    }
+    let read_u64 = |start: usize, len: usize| -> Result<u64, HrdfError> {
+        let mut bytes = [0; 8];
+        bytes[..len].copy_from_slice(mmap.get(start..start + len).ok_or_else(invalid)?);
+        Ok(u64::from_le_bytes(bytes))
+    };
+    let read_usize = |start: usize| -> Result<usize, HrdfError> {
+        usize::try_from(read_u64(start, 8)?).map_err(|_| invalid())
+    };
+
+    let schema_version = read_u64(MAGIC.len(), 4)? as u32;
+    if schema_version != SCHEMA_VERSION {
+        return Err(HrdfError::CacheMismatch(format!(
+            "schema version {schema_version} instead of {SCHEMA_VERSION}"
+        )));
+    }
+
+    let header_end = HEADER_START
+        .checked_add(read_usize(HEADER_LENGTH_START)?)
+        .filter(|&header_end| header_end <= mmap.len())
+        .ok_or_else(invalid)?;
+    let header: CacheHeader = bincode::deserialize(&mmap[HEADER_START..header_end])?;
+
+    let reason = match expected {
+        Some(expected) => header.mismatch(expected),
+        None => header.mismatch_crate_version(),
+    };
+    if let Some(reason) = reason {
+        return Err(HrdfError::CacheMismatch(reason));
+    }
+
+    // A truncated file (e.g. if the disk was full) is detected without reading the data.
+    let offset = header_end.next_multiple_of(ALIGNMENT);
+    if offset.checked_add(read_usize(DATA_LENGTH_START)?) != Some(mmap.len()) {
+        return Err(HrdfError::CacheMismatch(format!(
+            "{name} is truncated or has been modified"
+        )));
+    }
+
+    if validate {
+        rkyv::check_archived_root::<Hrdf>(&mmap[offset..])
+            .map_err(|e| HrdfError::CacheMismatch(format!("the cached data is invalid: {e}")))?;
+    }
+
+    Ok(MappedHrdf { mmap, offset })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, OpenOptions};
+
+    use chrono::NaiveDate;
+    use zip::CompressionMethod;
+
+    use crate::{
+        fixtures::MemorySource,
+        models::{Model, Version},
+        storage::LoadOptions,
+    };
+
+    use super::*;
+
+    fn hrdf() -> Hrdf {
+        let zip = MemorySource::new().to_zip(CompressionMethod::Stored);
+        Hrdf::from_zip_bytes(Version::V_5_40_41_2_0_4, zip).unwrap()
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn header(source_hash: &str) -> CacheHeader {
+        CacheHeader::new(
+            Some(Version::V_5_40_41_2_0_4),
+            source_hash.to_string(),
+            LoadOptions::default(),
+        )
+    }
+
+    #[test]
+    fn archived_data_is_equal_to_owned_data() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("data.mmap");
+        let hrdf = hrdf();
+        write(&path, &header("a"), &hrdf).unwrap();
+
+        let mapped_hrdf = open(&path, Some(&header("a")), true).unwrap();
+        let archived_data_storage = mapped_hrdf.hrdf().data_storage();
+        let data_storage = hrdf.data_storage();
+        assert_eq!(mapped_hrdf.hrdf().version(), Version::V_5_40_41_2_0_4);
+        assert_eq!(archived_data_storage.load_options(), LoadOptions::default());
+
+        for stop in data_storage.stops().entries() {
+            let archived_stop = archived_data_storage.stops().find(stop.id()).unwrap();
+            assert_eq!(archived_stop.name(), stop.name());
+            assert_eq!(archived_stop.sloid(), stop.sloid());
+        }
+        assert_eq!(archived_data_storage.journeys().entries().len(), 2);
+        for journey in data_storage.journeys().entries() {
+            let archived_journey = archived_data_storage.journeys().find(journey.id()).unwrap();
+            assert_eq!(archived_journey.administration(), journey.administration());
+            assert_eq!(archived_journey.route().len(), journey.route().len());
+            assert_eq!(archived_journey.bit_field_id(), journey.bit_field_id());
+            assert_eq!(
+                archived_journey.attributes_between(8500010, 8500030),
+                journey.attributes_between(8500010, 8500030)
+            );
+            for date in [date(2023, 12, 10), date(2024, 3, 1)] {
+                assert_eq!(
+                    archived_journey.attributes_between_on(
+                        8500010,
+                        8500030,
+                        date,
+                        archived_data_storage
+                    ),
+                    journey.attributes_between_on(8500010, 8500030, date, data_storage)
+                );
+                assert_eq!(
+                    archived_journey.information_texts_on(
+                        8500010,
+                        8500030,
+                        date,
+                        archived_data_storage
+                    ),
+                    journey.information_texts_on(8500010, 8500030, date, data_storage)
+                );
+            }
+        }
+
+        let hrdf_again = mapped_hrdf.to_hrdf().unwrap();
+        assert_eq!(hrdf_again.data_storage().journeys().entries().len(), 2);
+    }
+
+    #[test]
+    fn header_mismatch_is_reported() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("data.mmap");
+        write(&path, &header("a"), &hrdf()).unwrap();
+
+        let result = open(&path, Some(&header("b")), true);
+        assert!(
+            matches!(result, Err(HrdfError::CacheMismatch(reason)) if reason.contains("other HRDF data"))
+        );
+        // Without an expected header, only the crate version is checked.
+        assert!(MappedHrdf::open(&path).is_ok());
+        // SAFETY: The file has just been written by this test.
+        assert!(unsafe { MappedHrdf::open_unchecked(&path) }.is_ok());
+    }
+
+    #[test]
+    fn truncated_file_is_rejected() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("data.mmap");
+        write(&path, &header("a"), &hrdf()).unwrap();
+
+        let file = OpenOptions::new().write(true).open(&path).unwrap();
+        let length = file.metadata().unwrap().len();
+        file.set_len(length - 1).unwrap();
+        drop(file);
+
+        let result = MappedHrdf::open(&path);
+        assert!(
+            matches!(result, Err(HrdfError::CacheMismatch(reason)) if reason.contains("truncated"))
+        );
+    }
+
+    #[test]
+    fn other_files_are_rejected() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("data.cache");
+
+        // A bincode cache can't be mapped.
+        crate::cache::write(&path, &header("a"), &hrdf(), false).unwrap();
+        let result = MappedHrdf::open(&path);
+        assert!(
+            matches!(result, Err(HrdfError::CacheMismatch(reason)) if reason.contains("not a cache file"))
+        );
+
+        fs::write(&path, "HRDFMMAP").unwrap();
+        assert!(matches!(
+            MappedHrdf::open(&path),
+            Err(HrdfError::CacheMismatch(_))
+        ));
+    }
+}
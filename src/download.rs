@@ -0,0 +1,326 @@
+use std::{
+    fs::{self, File},
+    io::{BufWriter, ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+use reqwest::{
+    header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+    Response, StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    error::HrdfError,
+    observer::{LoadEvent, LoadObserver},
+    utils::create_temporary_file,
+};
+
+// ------------------------------------------------------------------------------------------------
+// --- DownloadMetadata
+// ------------------------------------------------------------------------------------------------
+
+/// Stored next to the downloaded archive, so that the next download can be conditional.
+#[derive(Debug, Serialize, Deserialize)]
+struct DownloadMetadata {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    // SHA-256 of the archive.
+    hash: String,
+}
+
+// ------------------------------------------------------------------------------------------------
+// --- Functions
+// ------------------------------------------------------------------------------------------------
+
+/// Downloads the HRDF archive into the directory and returns its path and its SHA-256.<br>
+/// The archive is only downloaded again if the server reports that it has changed (ETag/Last-Modified).
+/// If an expected SHA-256 is provided, the archive is rejected when it doesn't match.
+/// The archive previously downloaded from the same URL is deleted once it has been replaced.
+pub async fn download(
+    url: &str,
+    directory: &Path,
+    expected_hash: Option<&str>,
+    observer: Option<&dyn LoadObserver>,
+) -> Result<(PathBuf, String), HrdfError> {
+    let url_hash = format!("{:x}", Sha256::digest(url.as_bytes()));
+    let metadata_path = directory.join(format!("{url_hash}.download"));
+
+    let previous_metadata = read_metadata(&metadata_path);
+    // The metadata is ignored if the archive has been deleted in the meantime.
+    let metadata = previous_metadata
+        .as_ref()
+        .filter(|metadata| archive_path(directory, &metadata.hash).exists());
+
+    let mut request = reqwest::Client::new().get(url);
+    if let Some(metadata) = metadata {
+        if let Some(etag) = &metadata.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &metadata.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+
+    log::info!("Downloading HRDF data from {url}...");
+    let response = request.send().await?;
+
+    if response.status() == StatusCode::NOT_MODIFIED {
+        if let Some(metadata) = metadata {
+            log::info!("The HRDF data has not changed since the last download.");
+            verify_hash(&metadata.hash, expected_hash)?;
+            return Ok((
+                archive_path(directory, &metadata.hash),
+                metadata.hash.clone(),
+            ));
+        }
+    }
+
+    let mut response = response.error_for_status()?;
+    if response.status() != StatusCode::OK {
+        return Err(HrdfError::InvalidData(format!(
+            "Unexpected response while downloading {url}: {}.",
+            response.status()
+        )));
+    }
+
+    let header = |name| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(String::from)
+    };
+    let (etag, last_modified) = (header(ETAG), header(LAST_MODIFIED));
+
+    // The archive is written under a temporary name first, so that an interrupted download is never mistaken for a complete one.
+    // Each download uses its own temporary file, which is removed if the download fails.
+    fs::create_dir_all(directory)?;
+    let mut temporary_file = create_temporary_file(&directory.join(format!("{url_hash}.zip")))?;
+
+    let hash = write_response(&mut response, temporary_file.as_file_mut(), observer).await?;
+    verify_hash(&hash, expected_hash)?;
+
+    let path = archive_path(directory, &hash);
+    temporary_file.persist(&path).map_err(|e| e.error)?;
+
+    let metadata = DownloadMetadata {
+        etag,
+        last_modified,
+        hash: hash.clone(),
+    };
+    write_metadata(&metadata_path, &metadata)?;
+
+    // The archive previously downloaded from the URL has been superseded.
+    if let Some(previous_metadata) = previous_metadata.filter(|previous| previous.hash != hash) {
+        let previous_path = archive_path(directory, &previous_metadata.hash);
+        match fs::remove_file(&previous_path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => log::warn!("{} could not be deleted: {e}", previous_path.display()),
+        }
+    }
+
+    Ok((path, hash))
+}
+
+/// Writes the body of the response into the file and returns its SHA-256.
+async fn write_response(
+    response: &mut Response,
+    file: &mut File,
+    observer: Option<&dyn LoadObserver>,
+) -> Result<String, HrdfError> {
+    let mut hasher = Sha256::new();
+    let total_bytes = response.content_length();
+    let mut bytes_received = 0;
+
+    while let Some(chunk) = response.chunk().await? {
+        file.write_all(&chunk)?;
+        hasher.update(&chunk);
+
+        bytes_received += chunk.len() as u64;
+        if let Some(observer) = observer {
+            observer.on_event(&LoadEvent::DownloadProgress {
+                bytes_received,
+                total_bytes,
+            });
+        }
+    }
+    file.sync_all()?;
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn archive_path(directory: &Path, hash: &str) -> PathBuf {
+    directory.join(format!("{hash}.zip"))
+}
+
+fn verify_hash(hash: &str, expected_hash: Option<&str>) -> Result<(), HrdfError> {
+    match expected_hash {
+        Some(expected_hash) if !expected_hash.eq_ignore_ascii_case(hash) => {
+            Err(HrdfError::ChecksumMismatch {
+                expected: expected_hash.to_string(),
+                actual: hash.to_string(),
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Returns None if the metadata doesn't exist or cannot be read, in which case the archive is downloaded again.
+fn read_metadata(path: &Path) -> Option<DownloadMetadata> {
+    let data = fs::read(path).ok()?;
+    bincode::deserialize(&data).ok()
+}
+
+fn write_metadata(path: &Path, metadata: &DownloadMetadata) -> Result<(), HrdfError> {
+    let mut writer = BufWriter::new(create_temporary_file(path)?);
+    bincode::serialize_into(&mut writer, metadata)?;
+    let temporary_file = writer.into_inner().map_err(|e| e.into_error())?;
+
+    temporary_file.persist(path).map_err(|e| e.error)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader},
+        net::TcpListener,
+        thread::{self, JoinHandle},
+    };
+
+    use super::*;
+
+    const LAST_MODIFIED_VALUE: &str = "Sun, 10 Dec 2023 00:00:00 GMT";
+
+    /// Answers the requests with the responses, in order, and returns the headers of the requests.
+    fn serve(responses: Vec<String>) -> (String, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/data.zip", listener.local_addr().unwrap());
+
+        let handle = thread::spawn(move || {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request = String::new();
+                // The headers end with an empty line.
+                while reader.read_line(&mut request).unwrap() > 2 {}
+                stream.write_all(response.as_bytes()).unwrap();
+                requests.push(request.to_lowercase());
+            }
+            requests
+        });
+        (url, handle)
+    }
+
+    fn response(status: &str, headers: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 {status}\r\n{headers}Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        )
+    }
+
+    fn hash(content: &str) -> String {
+        format!("{:x}", Sha256::digest(content))
+    }
+
+    #[tokio::test]
+    async fn error_status_is_rejected() {
+        let directory = tempfile::tempdir().unwrap();
+        let (url, server) = serve(vec![response("404 Not Found", "", "")]);
+
+        let result = download(&url, directory.path(), None, None).await;
+        assert!(matches!(result, Err(HrdfError::Download(_))));
+        server.join().unwrap();
+        assert_eq!(fs::read_dir(directory.path()).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn checksum_mismatch_is_rejected() {
+        let directory = tempfile::tempdir().unwrap();
+        let (url, server) = serve(vec![response("200 OK", "", "content")]);
+
+        let result = download(&url, directory.path(), Some(&"0".repeat(64)), None).await;
+        assert!(matches!(
+            result,
+            Err(HrdfError::ChecksumMismatch { actual, .. }) if actual == hash("content")
+        ));
+        server.join().unwrap();
+        // The temporary file has been removed.
+        assert_eq!(fs::read_dir(directory.path()).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn archive_is_stored_with_metadata() {
+        let directory = tempfile::tempdir().unwrap();
+        let (url, server) = serve(vec![response("200 OK", "", "content")]);
+
+        // The expected SHA-256 is compared regardless of its case.
+        let expected_hash = hash("content").to_uppercase();
+        let (path, source_hash) = download(&url, directory.path(), Some(&expected_hash), None)
+            .await
+            .unwrap();
+        server.join().unwrap();
+        assert_eq!(path, archive_path(directory.path(), &source_hash));
+
+        let mut file_names: Vec<_> = fs::read_dir(directory.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        file_names.sort();
+        let url_hash = format!("{:x}", Sha256::digest(url.as_bytes()));
+        let mut expected_file_names =
+            [format!("{source_hash}.zip"), format!("{url_hash}.download")];
+        expected_file_names.sort();
+        assert_eq!(file_names, expected_file_names);
+    }
+
+    #[tokio::test]
+    async fn superseded_archive_is_deleted() {
+        let directory = tempfile::tempdir().unwrap();
+        let (url, server) = serve(vec![
+            response("200 OK", "ETag: \"v1\"\r\n", "content"),
+            response("200 OK", "ETag: \"v2\"\r\n", "new content"),
+        ]);
+
+        let (path, _) = download(&url, directory.path(), None, None).await.unwrap();
+        let (new_path, new_source_hash) =
+            download(&url, directory.path(), None, None).await.unwrap();
+        server.join().unwrap();
+
+        assert_eq!(new_source_hash, hash("new content"));
+        assert!(!path.exists());
+        assert_eq!(fs::read_to_string(&new_path).unwrap(), "new content");
+    }
+
+    #[tokio::test]
+    async fn not_modified_archive_is_reused() {
+        let directory = tempfile::tempdir().unwrap();
+        let headers = format!("ETag: \"v1\"\r\nLast-Modified: {LAST_MODIFIED_VALUE}\r\n");
+        let (url, server) = serve(vec![
+            response("200 OK", &headers, "content"),
+            response("304 Not Modified", &headers, ""),
+        ]);
+
+        let (path, source_hash) = download(&url, directory.path(), None, None).await.unwrap();
+        assert_eq!(source_hash, hash("content"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "content");
+
+        let (path_again, source_hash_again) =
+            download(&url, directory.path(), Some(&hash("content")), None)
+                .await
+                .unwrap();
+        assert_eq!((path_again, source_hash_again), (path, source_hash));
+
+        let requests = server.join().unwrap();
+        assert!(!requests[0].contains("if-none-match"));
+        assert!(requests[1].contains("if-none-match: \"v1\""));
+        assert!(requests[1].contains(&format!(
+            "if-modified-since: {}",
+            LAST_MODIFIED_VALUE.to_lowercase()
+        )));
+    }
+}
@@ -0,0 +1,390 @@
+use std::{
+    error::Error,
+    fmt, io,
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::observer::{LoadEvent, LoadObserver};
+
+// ------------------------------------------------------------------------------------------------
+// --- HrdfError
+// ------------------------------------------------------------------------------------------------
+
+#[derive(Debug)]
+pub enum HrdfError {
+    /// An I/O operation failed.
+    Io(io::Error),
+    /// The HRDF archive could not be downloaded.
+    #[cfg(feature = "download")]
+    Download(reqwest::Error),
+    /// The SHA-256 of the downloaded HRDF archive isn't the expected one.
+    ChecksumMismatch { expected: String, actual: String },
+    /// The HRDF archive could not be decompressed.
+    Unzip(zip::result::ZipError),
+    /// The cache could not be written or read.
+    Cache(bincode::Error),
+    /// The cache was built by another version of the crate, from other HRDF data or with other options.
+    CacheMismatch(String),
+    /// A file required by the parser is missing from the HRDF data.
+    MissingFile(String),
+    /// A row of an HRDF file could not be parsed.
+    Parse {
+        file: String,
+        // 1-based indexing
+        line: usize,
+        // 1-based indexing, both ends included.
+        columns: Option<(usize, usize)>,
+        row: String,
+        reason: String,
+    },
+    /// A row of an HRDF file references a resource that does not exist.
+    UnknownReference {
+        file: String,
+        // 1-based indexing
+        line: usize,
+        row: String,
+        reference: String,
+    },
+    /// The data is inconsistent, e.g. a mandatory entry is missing.
+    InvalidData(String),
+}
+
+impl fmt::Display for HrdfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "I/O error: {e}"),
+            #[cfg(feature = "download")]
+            Self::Download(e) => write!(f, "Download error: {e}"),
+            Self::ChecksumMismatch { expected, actual } => write!(
+                f,
+                "The SHA-256 of the HRDF archive is {actual} instead of {expected}."
+            ),
+            Self::Unzip(e) => write!(f, "Unzip error: {e}"),
+            Self::Cache(e) => write!(f, "Cache error: {e}"),
+            Self::CacheMismatch(reason) => write!(f, "The cache is outdated: {reason}"),
+            Self::MissingFile(file) => write!(f, "The file {file} is missing."),
+            Self::Parse {
+                file,
+                line,
+                columns,
+                row,
+                reason,
+            } => {
+                write!(f, "{file}, line {line}")?;
+                if let Some((start, stop)) = columns {
+                    write!(f, ", columns {start}-{stop}")?;
+                }
+                write!(f, ": {reason}\n{row}")
+            }
+            Self::UnknownReference {
+                file,
+                line,
+                row,
+                reference,
+            } => write!(f, "{file}, line {line}: unknown {reference}\n{row}"),
+            Self::InvalidData(reason) => write!(f, "Invalid data: {reason}"),
+        }
+    }
+}
+
+impl Error for HrdfError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            #[cfg(feature = "download")]
+            Self::Download(e) => Some(e),
+            Self::Unzip(e) => Some(e),
+            Self::Cache(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for HrdfError {
+    fn from(value: io::Error) -> Self {
+        // The errors of the archives read on the fly (see ZipSource) are reported as such.
+        if value
+            .get_ref()
+            .is_some_and(|e| e.is::<zip::result::ZipError>())
+        {
+            // unwrap: The inner error has just been checked.
+            let e = value.into_inner().unwrap().downcast().unwrap();
+            return Self::Unzip(*e);
+        }
+        Self::Io(value)
+    }
+}
+
+#[cfg(feature = "download")]
+impl From<reqwest::Error> for HrdfError {
+    fn from(value: reqwest::Error) -> Self {
+        Self::Download(value)
+    }
+}
+
+impl From<zip::result::ZipError> for HrdfError {
+    fn from(value: zip::result::ZipError) -> Self {
+        Self::Unzip(value)
+    }
+}
+
+impl From<bincode::Error> for HrdfError {
+    fn from(value: bincode::Error) -> Self {
+        Self::Cache(value)
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// --- RowError
+// ------------------------------------------------------------------------------------------------
+
+/// Error raised while processing a single row.
+/// The file name, line number and row content are attached by the FileParser, turning it into an HrdfError.
+#[derive(Debug)]
+pub(crate) enum RowError {
+    Parse {
+        columns: Option<(usize, usize)>,
+        reason: String,
+    },
+    UnknownReference(String),
+}
+
+impl RowError {
+    pub(crate) fn invalid(reason: &str) -> Self {
+        Self::Parse {
+            columns: None,
+            reason: reason.to_string(),
+        }
+    }
+
+    pub(crate) fn unknown_reference(kind: &str, id: impl fmt::Debug) -> Self {
+        Self::UnknownReference(format!("{kind} {id:?}"))
+    }
+
+    pub(crate) fn locate(self, file: &str, line: usize, row: &str) -> HrdfError {
+        match self {
+            Self::Parse { columns, reason } => HrdfError::Parse {
+                file: file.to_string(),
+                line,
+                columns,
+                row: row.to_string(),
+                reason,
+            },
+            Self::UnknownReference(reference) => HrdfError::UnknownReference {
+                file: file.to_string(),
+                line,
+                row: row.to_string(),
+                reference,
+            },
+        }
+    }
+}
+
+// RowError doesn't implement Error, which allows any error to be converted into it with the ? operator.
+impl<E: Error> From<E> for RowError {
+    fn from(value: E) -> Self {
+        Self::Parse {
+            columns: None,
+            reason: value.to_string(),
+        }
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// --- ParsingMode
+// ------------------------------------------------------------------------------------------------
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ParsingMode {
+    /// The loading is aborted at the first row that cannot be parsed.
+    #[default]
+    Strict,
+    /// The rows that cannot be parsed are skipped and reported as diagnostics.
+    Lenient,
+}
+
+// ------------------------------------------------------------------------------------------------
+// --- Severity
+// ------------------------------------------------------------------------------------------------
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Severity {
+    /// The row is valid, but it has been skipped because the record it belongs to has been skipped.
+    /// Also used when an optional file is missing and a default is used instead.
+    Warning,
+    /// The row is invalid and it has been skipped.
+    Error,
+}
+
+// ------------------------------------------------------------------------------------------------
+// --- Diagnostic
+// ------------------------------------------------------------------------------------------------
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Diagnostic {
+    severity: Severity,
+    file: String,
+    // 1-based indexing, 0 if the diagnostic concerns the whole file.
+    line: usize,
+    reason: String,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, file: String, line: usize, reason: String) -> Self {
+        Self {
+            severity,
+            file,
+            line,
+            reason,
+        }
+    }
+
+    // Getters/Setters
+
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?}: {}, line {}: {}",
+            self.severity, self.file, self.line, self.reason
+        )
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// --- Diagnostics
+// ------------------------------------------------------------------------------------------------
+
+/// Collects the diagnostics reported while parsing the HRDF files, and forwards the progress of the parsing to the observer.
+/// The files may be parsed concurrently (see the "parallel" feature), hence the Mutex.
+pub(crate) struct Diagnostics {
+    parsing_mode: ParsingMode,
+    observer: Option<Arc<dyn LoadObserver>>,
+    entries: Mutex<Vec<Diagnostic>>,
+}
+
+impl Diagnostics {
+    pub(crate) fn new(parsing_mode: ParsingMode, observer: Option<Arc<dyn LoadObserver>>) -> Self {
+        Self {
+            parsing_mode,
+            observer,
+            entries: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub(crate) fn notify(&self, event: LoadEvent) {
+        if let Some(observer) = &self.observer {
+            observer.on_event(&event);
+        }
+    }
+
+    /// In lenient mode, the error is recorded so that the row can be skipped.
+    /// In strict mode, or if the error isn't related to a row, the error is returned.
+    pub(crate) fn report(&self, error: HrdfError) -> Result<(), HrdfError> {
+        if self.parsing_mode == ParsingMode::Strict {
+            return Err(error);
+        }
+
+        let (file, line, reason) = match error {
+            HrdfError::Parse {
+                file,
+                line,
+                columns: Some((start, stop)),
+                reason,
+                ..
+            } => (file, line, format!("columns {start}-{stop}: {reason}")),
+            HrdfError::Parse {
+                file, line, reason, ..
+            } => (file, line, reason),
+            HrdfError::UnknownReference {
+                file,
+                line,
+                reference,
+                ..
+            } => (file, line, format!("unknown {reference}")),
+            _ => return Err(error),
+        };
+
+        log::warn!("{file}, line {line}: {reason} (row skipped)");
+        self.push(Diagnostic::new(Severity::Error, file, line, reason));
+        Ok(())
+    }
+
+    pub(crate) fn warn(&self, file: &str, line: usize, reason: &str) {
+        self.push(Diagnostic::new(
+            Severity::Warning,
+            file.to_string(),
+            line,
+            reason.to_string(),
+        ));
+    }
+
+    /// The diagnostics are sorted by file and line, so that their order doesn't depend on the order in which the files were parsed.
+    pub(crate) fn into_entries(self) -> Vec<Diagnostic> {
+        // unwrap: The Mutex is only poisoned if a thread panicked while holding it, in which case the panic has already been propagated.
+        let mut entries = self.entries.into_inner().unwrap();
+        entries.sort_by(|a, b| (&a.file, a.line).cmp(&(&b.file, b.line)));
+        entries
+    }
+
+    fn push(&self, diagnostic: Diagnostic) {
+        // unwrap: The Mutex is only poisoned if a thread panicked while holding it, in which case the panic has already been propagated.
+        self.entries.lock().unwrap().push(diagnostic);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diagnostics_are_sorted_by_file_and_line() {
+        let diagnostics = Diagnostics::new(ParsingMode::Lenient, None);
+        // The files may be parsed in any order if the "parallel" feature is enabled.
+        diagnostics.warn("FPLAN", 2, "reason");
+        diagnostics.warn("BAHNHOF", 7, "reason");
+        diagnostics.warn("FPLAN", 1, "reason");
+
+        let locations: Vec<_> = diagnostics
+            .into_entries()
+            .iter()
+            .map(|diagnostic| (diagnostic.file().to_string(), diagnostic.line()))
+            .collect();
+        assert_eq!(
+            locations,
+            vec![
+                ("BAHNHOF".to_string(), 7),
+                ("FPLAN".to_string(), 1),
+                ("FPLAN".to_string(), 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn errors_are_returned_in_strict_mode() {
+        let diagnostics = Diagnostics::new(ParsingMode::Strict, None);
+        let error = RowError::invalid("Invalid value.").locate("BAHNHOF", 3, "row");
+
+        assert!(diagnostics.report(error).is_err());
+        assert!(diagnostics.into_entries().is_empty());
+    }
+}
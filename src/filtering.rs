@@ -0,0 +1,425 @@
+use std::ops::RangeInclusive;
+
+use chrono::NaiveDate;
+use rustc_hash::FxHashSet;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::HrdfError,
+    models::{
+        BitField, CoordinateSystem, Coordinates, Journey, Model, Stop, TimetableMetadataEntry,
+    },
+    storage::ResourceStorage,
+    utils::{count_days_between_two_dates, timetable_end_date, timetable_start_date},
+};
+
+// ------------------------------------------------------------------------------------------------
+// --- DateFilter
+// ------------------------------------------------------------------------------------------------
+
+/// Keeps the data that is active on at least one day of the date range.<br>
+/// The journeys of the previous operating days are kept as well if they still run in the date range, e.g. after midnight.
+pub struct DateFilter {
+    // Includes 0 if the date range overlaps the timetable period, 0 meaning that the data is active every day.
+    active_bit_field_ids: FxHashSet<i32>,
+}
+
+impl DateFilter {
+    /// max_day_offset is the largest number of days between the operating day of a journey and its arrival (e.g. 1 for 25:30).
+    pub fn new(
+        date_range: &RangeInclusive<NaiveDate>,
+        max_day_offset: i32,
+        bit_fields: &ResourceStorage<BitField>,
+        timetable_metadata: &ResourceStorage<TimetableMetadataEntry>,
+    ) -> Result<Self, HrdfError> {
+        let start_date = timetable_start_date(timetable_metadata)
+            .map_err(|e| HrdfError::InvalidData(e.to_string()))?;
+        let end_date = timetable_end_date(timetable_metadata)
+            .map_err(|e| HrdfError::InvalidData(e.to_string()))?;
+        let num_days = count_days_between_two_dates(start_date, end_date) as i64;
+
+        // Indexes of the first and last days of the date range in the timetable period.
+        // The first day is moved back, as a journey can arrive max_day_offset days after its operating day.
+        let first_day =
+            ((*date_range.start() - start_date).num_days() - i64::from(max_day_offset)).max(0);
+        let last_day = (*date_range.end() - start_date)
+            .num_days()
+            .min(num_days - 1);
+
+        if first_day > last_day {
+            // The date range doesn't overlap the timetable period.
+            return Ok(Self {
+                active_bit_field_ids: FxHashSet::default(),
+            });
+        }
+
+        // unwrap: Both indexes are positive.
+        let (first_day, last_day) = (
+            usize::try_from(first_day).unwrap(),
+            usize::try_from(last_day).unwrap(),
+        );
+
+        let mut active_bit_field_ids: FxHashSet<i32> = bit_fields
+            .entries()
+            .into_iter()
+            .filter(|bit_field| {
+                bit_field
+                    .bits()
+                    .iter()
+                    // The first two bits must be ignored.
+                    .skip(2 + first_day)
+                    .take(last_day - first_day + 1)
+                    .any(|&bit| bit == 1)
+            })
+            .map(|bit_field| bit_field.id())
+            .collect();
+        active_bit_field_ids.insert(0);
+
+        Ok(Self {
+            active_bit_field_ids,
+        })
+    }
+
+    // Functions
+
+    /// If the data has no bit_field_id, it's active every day.
+    pub fn is_active(&self, bit_field_id: Option<i32>) -> bool {
+        self.active_bit_field_ids
+            .contains(&bit_field_id.unwrap_or(0))
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// --- Area
+// ------------------------------------------------------------------------------------------------
+
+/// A geographical area in WGS84 or LV95, the stops are matched with their coordinates in the same coordinate system.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "mmap-cache",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
+pub enum Area {
+    BoundingBox(Coordinates, Coordinates),
+    Polygon(Vec<Coordinates>),
+}
+
+impl Area {
+    /// The corners are opposite corners of the bounding box, its edges are included.<br>
+    /// Panics if the corners are not in the same coordinate system.
+    pub fn bounding_box(corner_1: Coordinates, corner_2: Coordinates) -> Self {
+        assert!(corner_1.coordinate_system() == corner_2.coordinate_system());
+        Self::BoundingBox(corner_1, corner_2)
+    }
+
+    /// Panics if there are less than 3 vertices or if they are not all in the same coordinate system.
+    pub fn polygon(vertices: Vec<Coordinates>) -> Self {
+        assert!(vertices.len() >= 3);
+        assert!(vertices
+            .iter()
+            .all(|vertex| vertex.coordinate_system() == vertices[0].coordinate_system()));
+        Self::Polygon(vertices)
+    }
+
+    // Getters/Setters
+
+    pub fn coordinate_system(&self) -> CoordinateSystem {
+        match self {
+            Self::BoundingBox(corner, _) => corner.coordinate_system(),
+            Self::Polygon(vertices) => vertices[0].coordinate_system(),
+        }
+    }
+
+    // Functions
+
+    /// Stops without coordinates in the coordinate system of the area are never contained.
+    pub fn contains(&self, stop: &Stop) -> bool {
+        let coordinates = match self.coordinate_system() {
+            CoordinateSystem::LV95 => stop.lv95_coordinates(),
+            CoordinateSystem::WGS84 => stop.wgs84_coordinates(),
+        };
+        let Some(coordinates) = coordinates else {
+            return false;
+        };
+        let (x, y) = to_xy(&coordinates);
+
+        match self {
+            Self::BoundingBox(corner_1, corner_2) => {
+                let (x1, y1) = to_xy(corner_1);
+                let (x2, y2) = to_xy(corner_2);
+                x >= x1.min(x2) && x <= x1.max(x2) && y >= y1.min(y2) && y <= y1.max(y2)
+            }
+            Self::Polygon(vertices) => {
+                // Ray casting: the point is inside if a ray starting from it crosses an odd number of edges.
+                let mut is_inside = false;
+                for (i, vertex) in vertices.iter().enumerate() {
+                    let (xi, yi) = to_xy(vertex);
+                    let (xj, yj) = to_xy(&vertices[(i + vertices.len() - 1) % vertices.len()]);
+
+                    if (yi > y) != (yj > y) && x < (xj - xi) * (y - yi) / (yj - yi) + xi {
+                        is_inside = !is_inside;
+                    }
+                }
+                is_inside
+            }
+        }
+    }
+}
+
+fn to_xy(coordinates: &Coordinates) -> (f64, f64) {
+    match coordinates.coordinate_system() {
+        CoordinateSystem::LV95 => (coordinates.easting(), coordinates.northing()),
+        CoordinateSystem::WGS84 => (coordinates.latitude(), coordinates.longitude()),
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// --- RegionFilter
+// ------------------------------------------------------------------------------------------------
+
+/// Keeps the journeys touching a stop of the area and operated by one of the administrations.
+pub struct RegionFilter {
+    // None if all the stops are accepted.
+    stop_ids_in_area: Option<FxHashSet<i32>>,
+    // None if all the administrations are accepted.
+    administrations: Option<FxHashSet<String>>,
+}
+
+impl RegionFilter {
+    pub fn new(
+        area: Option<&Area>,
+        administrations: Option<&[String]>,
+        stops: &ResourceStorage<Stop>,
+    ) -> Self {
+        let stop_ids_in_area = area.map(|area| {
+            stops
+                .entries()
+                .into_iter()
+                .filter(|stop| area.contains(stop))
+                .map(|stop| stop.id())
+                .collect()
+        });
+        let administrations =
+            administrations.map(|administrations| administrations.iter().cloned().collect());
+
+        Self {
+            stop_ids_in_area,
+            administrations,
+        }
+    }
+
+    // Functions
+
+    pub fn is_stop_in_area(&self, stop_id: i32) -> bool {
+        self.stop_ids_in_area
+            .as_ref()
+            .is_none_or(|stop_ids| stop_ids.contains(&stop_id))
+    }
+
+    pub fn is_journey_kept(&self, journey: &Journey) -> bool {
+        let is_administration_kept = self
+            .administrations
+            .as_ref()
+            .is_none_or(|administrations| administrations.contains(journey.administration()));
+
+        is_administration_kept
+            && journey
+                .route()
+                .iter()
+                .any(|route_entry| self.is_stop_in_area(route_entry.stop_id()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveTime;
+
+    use crate::{
+        error::ParsingMode,
+        fixtures::MemorySource,
+        models::Version,
+        storage::{DataStorage, LoadOptions},
+    };
+
+    use super::*;
+
+    fn load(source: &MemorySource, load_options: LoadOptions) -> DataStorage {
+        let (data_storage, _) = DataStorage::from_source(
+            Version::V_5_40_41_2_0_4,
+            source,
+            ParsingMode::Strict,
+            load_options,
+            None,
+        )
+        .unwrap();
+        data_storage
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn date_filter(date_range: RangeInclusive<NaiveDate>) -> DateFilter {
+        let data_storage = load(&MemorySource::new(), LoadOptions::minimal());
+        DateFilter::new(
+            &date_range,
+            0,
+            data_storage.bit_fields(),
+            data_storage.timetable_metadata(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn bit_fields_active_in_date_range() {
+        // The bit field 3 is only active on the first 2 days of the timetable (10.12.2023-14.12.2024).
+        let filter = date_filter(date(2023, 12, 11)..=date(2023, 12, 20));
+        assert!(filter.is_active(None));
+        assert!(filter.is_active(Some(1)));
+        assert!(!filter.is_active(Some(2)));
+        assert!(filter.is_active(Some(3)));
+
+        let filter = date_filter(date(2024, 1, 1)..=date(2024, 1, 31));
+        assert!(filter.is_active(Some(1)));
+        assert!(!filter.is_active(Some(3)));
+    }
+
+    #[test]
+    fn date_range_is_clamped_to_timetable() {
+        let filter = date_filter(date(2023, 11, 1)..=date(2023, 12, 10));
+        assert!(filter.is_active(Some(3)));
+
+        let filter = date_filter(date(2024, 12, 14)..=date(2025, 1, 31));
+        assert!(filter.is_active(Some(1)));
+        assert!(!filter.is_active(Some(3)));
+    }
+
+    #[test]
+    fn journeys_running_past_midnight_are_kept() {
+        // The journey 2 is only active on the first 2 days of the timetable and arrives at 25:30.
+        let fplan = MemorySource::new()
+            .file("FPLAN")
+            .replace(
+                "*A VE 8500030 8500010 000002",
+                "*A VE 8500030 8500010 000003",
+            )
+            .replace("01000", "02330")
+            .replace("01100", "02530");
+        let source = MemorySource::new().with("FPLAN", &fplan);
+
+        let data_storage = load(
+            &source,
+            LoadOptions::default().with_date_range(date(2023, 12, 12)..=date(2023, 12, 20)),
+        );
+        let journey = data_storage.journeys().find(2).unwrap();
+        assert_eq!(
+            journey
+                .route()
+                .last()
+                .unwrap()
+                .arrival_time_and_day_offset(),
+            Some((NaiveTime::from_hms_opt(1, 30, 0).unwrap(), 1))
+        );
+
+        let data_storage = load(
+            &source,
+            LoadOptions::default().with_date_range(date(2023, 12, 13)..=date(2023, 12, 20)),
+        );
+        assert!(data_storage.journeys().find(2).is_none());
+
+        let filter = date_filter(date(2023, 12, 12)..=date(2023, 12, 20));
+        assert!(!filter.is_active(Some(3)));
+    }
+
+    #[test]
+    fn date_range_outside_timetable_keeps_nothing() {
+        // The journeys of 14.12.2024 arrive until 15.12.2024.
+        for date_range in [
+            date(2023, 1, 1)..=date(2023, 12, 9),
+            date(2024, 12, 16)..=date(2025, 1, 31),
+        ] {
+            let filter = date_filter(date_range.clone());
+            assert!(!filter.is_active(None));
+            assert!(!filter.is_active(Some(1)));
+
+            let data_storage = load(
+                &MemorySource::new(),
+                LoadOptions::default().with_date_range(date_range),
+            );
+            assert!(data_storage.journeys().entries().is_empty());
+        }
+    }
+
+    fn wgs84(latitude: f64, longitude: f64) -> Coordinates {
+        Coordinates::new(CoordinateSystem::WGS84, latitude, longitude)
+    }
+
+    fn lv95(easting: f64, northing: f64) -> Coordinates {
+        Coordinates::new(CoordinateSystem::LV95, easting, northing)
+    }
+
+    #[test]
+    fn stops_in_area() {
+        let data_storage = load(&MemorySource::new(), LoadOptions::minimal());
+        let stops = data_storage.stops();
+        let (basel, zurich, bern) = (
+            stops.find(8500010).unwrap(),
+            stops.find(8500020).unwrap(),
+            stops.find(8500030).unwrap(),
+        );
+
+        // The corners may be given in any order.
+        let area = Area::bounding_box(wgs84(47.7, 8.99), wgs84(47.15, 8.35));
+        assert!(area.contains(zurich));
+        assert!(!area.contains(basel));
+        assert!(!area.contains(bern));
+
+        let area = Area::polygon(vec![
+            lv95(2590000.0, 1190000.0),
+            lv95(2610000.0, 1190000.0),
+            lv95(2600000.0, 1210000.0),
+        ]);
+        assert!(area.contains(bern));
+        assert!(!area.contains(basel));
+        assert!(!area.contains(zurich));
+    }
+
+    #[test]
+    fn journeys_are_kept_by_area_and_administration() {
+        // The second journey is operated by another administration.
+        let source = ["FPLAN", "DURCHBI", "UMSTEIGZ"].into_iter().fold(
+            MemorySource::new(),
+            |source, file| {
+                let content = source.file(file).replace("000002 000011", "000002 000801");
+                source.with(file, &content)
+            },
+        );
+        let zurich = Area::bounding_box(wgs84(47.7, 8.99), wgs84(47.15, 8.35));
+
+        // Only the first journey stops in Zurich.
+        let data_storage = load(&source, LoadOptions::default().with_area(zurich.clone()));
+        let journeys = data_storage.journeys().entries();
+        assert_eq!(journeys.len(), 1);
+        assert_eq!(journeys[0].first_stop_id(), 8500010);
+
+        // The stops that are no longer referenced are dropped.
+        let data_storage = load(
+            &source,
+            LoadOptions::default().with_administrations(vec!["000801".to_string()]),
+        );
+        let journeys = data_storage.journeys().entries();
+        assert_eq!(journeys.len(), 1);
+        assert_eq!(journeys[0].first_stop_id(), 8500030);
+        assert!(data_storage.stops().find(8500020).is_none());
+        assert!(data_storage.stops().find(8500030).is_some());
+
+        let data_storage = load(
+            &source,
+            LoadOptions::default()
+                .with_area(zurich)
+                .with_administrations(vec!["000801".to_string()]),
+        );
+        assert!(data_storage.journeys().entries().is_empty());
+    }
+}
@@ -0,0 +1,420 @@
+//! Small HRDF data sets for the tests, kept in memory.
+
+use std::{
+    fs,
+    io::{self, BufRead, Cursor, Write},
+    path::Path,
+};
+
+use rustc_hash::FxHashMap;
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+use crate::{error::HrdfError, source::HrdfSource};
+
+// The rows of FPLAN are padded to this length, as their last columns are optional.
+const FPLAN_ROW_LENGTH: usize = 52;
+
+// ------------------------------------------------------------------------------------------------
+// --- MemorySource
+// ------------------------------------------------------------------------------------------------
+
+/// The HRDF files are read from memory.<br>
+/// By default, it contains a complete HRDF 2.0.4 data set with 3 stops and 2 journeys.
+pub struct MemorySource {
+    files: FxHashMap<String, Vec<u8>>,
+}
+
+impl MemorySource {
+    pub fn new() -> Self {
+        let files = FILES
+            .iter()
+            .map(|(name, content)| (name.to_string(), prepare(name, content)))
+            .collect();
+        Self { files }
+    }
+
+    // Getters/Setters
+
+    /// Panics if the file is missing.
+    pub fn file(&self, file: &str) -> String {
+        String::from_utf8_lossy(&self.files[file]).into_owned()
+    }
+
+    // Functions
+
+    /// Replaces the content of the file, or adds it.
+    pub fn with(mut self, file: &str, content: &str) -> Self {
+        self.files.insert(file.to_string(), prepare(file, content));
+        self
+    }
+
+    pub fn without(mut self, file: &str) -> Self {
+        self.files.remove(file);
+        self
+    }
+
+    /// Writes the files into the directory, e.g. to load them from a path.
+    pub fn write_to(&self, directory: &Path) -> io::Result<()> {
+        fs::create_dir_all(directory)?;
+        for (name, content) in &self.files {
+            fs::write(directory.join(name), content)?;
+        }
+        Ok(())
+    }
+
+    /// Returns a ZIP archive containing the files, compressed or not.
+    pub fn to_zip(&self, compression_method: CompressionMethod) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = SimpleFileOptions::default().compression_method(compression_method);
+        // The files are sorted, so that the archive is always the same.
+        let mut files: Vec<_> = self.files.iter().collect();
+        files.sort();
+
+        for (name, content) in files {
+            // unwrap: Writing into memory will never fail.
+            writer.start_file(name.as_str(), options).unwrap();
+            writer.write_all(content).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+}
+
+impl HrdfSource for MemorySource {
+    fn open(&self, file: &str) -> Result<Box<dyn BufRead + Send + '_>, HrdfError> {
+        let content = self
+            .files
+            .get(file)
+            .ok_or_else(|| HrdfError::MissingFile(file.to_string()))?;
+        Ok(Box::new(Cursor::new(content.as_slice())))
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// --- Functions
+// ------------------------------------------------------------------------------------------------
+
+fn prepare(file: &str, content: &str) -> Vec<u8> {
+    let content = if file == "FPLAN" {
+        pad_rows(content)
+    } else {
+        content.to_string()
+    };
+    to_crlf(&content).into_bytes()
+}
+
+fn pad_rows(content: &str) -> String {
+    content
+        .lines()
+        .map(|line| format!("{line:FPLAN_ROW_LENGTH$}\n"))
+        .collect()
+}
+
+// The HRDF files use Windows line endings.
+fn to_crlf(content: &str) -> String {
+    content.replace("\r\n", "\n").replace('\n', "\r\n")
+}
+
+// ------------------------------------------------------------------------------------------------
+// --- Data
+// ------------------------------------------------------------------------------------------------
+
+const FILES: &[(&str, &str)] = &[
+    (
+        "ATTRIBUT",
+        "\
+VR 0   5 10\n\
+Y  0   1  1\n\
+# VR VR VR\n\
+<text>\n\
+<deu>\n\
+VR Velos: Reservierung obligatorisch\n\
+Y  Zug\n\
+<fra>\n\
+VR Velos: reservation\n\
+Y  Train\n",
+    ),
+    (
+        "ATTRIBUT_DE",
+        "\
+VR 0   5 10\n\
+Y  0   1  1\n\
+# VR VR Y \n\
+# Y  Y  Y \n\
+<text>\n\
+VR Bike reservation required (DE)\n\
+Y  Train (DE)\n",
+    ),
+    (
+        "ATTRIBUT_EN",
+        "\
+VR 0   5 10\n\
+Y  0   1  1\n\
+# VR VR Y \n\
+# Y  Y  Y \n\
+<text>\n\
+VR Bike reservation required (EN)\n\
+Y  Train (EN)\n",
+    ),
+    (
+        "ATTRIBUT_FR",
+        "\
+VR 0   5 10\n\
+Y  0   1  1\n\
+# VR VR Y \n\
+# Y  Y  Y \n\
+<text>\n\
+VR Bike reservation required (FR)\n\
+Y  Train (FR)\n",
+    ),
+    (
+        "ATTRIBUT_IT",
+        "\
+VR 0   5 10\n\
+Y  0   1  1\n\
+# VR VR Y \n\
+# Y  Y  Y \n\
+<text>\n\
+VR Bike reservation required (IT)\n\
+Y  Train (IT)\n",
+    ),
+    (
+        "BAHNHOF",
+        "\
+8500010     Basel SBB$<1>$BS$<3>\n\
+8500020     Zuerich HB$<1>\n\
+8500030     Bern$<1>\n",
+    ),
+    (
+        "BETRIEB_DE",
+        "\
+00001 K \"SBB\" L \"SBB\" V \"Schweizerische Bundesbahnen\"\n\
+00001 : 000011\n",
+    ),
+    (
+        "BETRIEB_EN",
+        "\
+00001 K \"SBB\" L \"SBB\" V \"Schweizerische Bundesbahnen\"\n\
+00001 : 000011\n",
+    ),
+    (
+        "BETRIEB_FR",
+        "\
+00001 K \"SBB\" L \"SBB\" V \"Schweizerische Bundesbahnen\"\n\
+00001 : 000011\n",
+    ),
+    (
+        "BETRIEB_IT",
+        "\
+00001 K \"SBB\" L \"SBB\" V \"Schweizerische Bundesbahnen\"\n\
+00001 : 000011\n",
+    ),
+    (
+        "BFKOORD_LV95",
+        "\
+8500010 2611363.00 1266310.00    277\n\
+8500020 2683211.00 1248075.00    408\n\
+8500030 2600037.00 1199749.00    540\n",
+    ),
+    (
+        "BFKOORD_WGS",
+        "\
+8500010   7.589562  47.547414    277\n\
+8500020   8.540192  47.378177    408\n\
+8500030   7.439122  46.948832    540\n",
+    ),
+    (
+        "BFPRIOS",
+        "\
+8500010 16\n\
+8500020 16\n",
+    ),
+    (
+        "BHFART",
+        "\
+% comment\n\
+8500020 B 02\n\
+8500020 G A ch:1:sloid:20\n",
+    ),
+    (
+        "BHFART_60",
+        "\
+% comment\n\
+8500010 B 03\n\
+8500010 G A ch:1:sloid:10\n\
+8500010 G a ch:1:sloid:10:1\n",
+    ),
+    (
+        "BITFELD",
+        "\
+000001 FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF\n\
+000002 C00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000\n\
+000003 300000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000\n",
+    ),
+    (
+        "DURCHBI",
+        "\
+000001 000011 8500030 000002 000011 000001 8500030\n",
+    ),
+    (
+        "ECKDATEN",
+        "\
+10.12.2023\n\
+14.12.2024\n\
+Fahrplan 2024$28.03.2024 10:04:00$5.40.41$SBB\n",
+    ),
+    (
+        "FEIERTAG",
+        "\
+25.12.2024 Weihnachten<deu>Noel<fra>Natale<ita>Christmas<eng>\n",
+    ),
+    (
+        "FPLAN",
+        "\
+*Z 000001 000011   101\n\
+*G IC  8500010 8500030\n\
+*A VE 8500010 8500030 000001\n\
+*A VR 8500010 8500030\n\
+*A Y  8500020 8500030\n\
+*I JY 8500010 8500030 000001 000000001\n\
+*I XI 8500020 8500030 000003 000000001\n\
+*L #0000001 8500010 8500020\n\
+*L IC9      8500020 8500030\n\
+*R H H000001 8500010 8500030\n\
+*CI 0002 8500010 8500030\n\
+*CO 0003 8500020 8500030\n\
+8500010 Basel SBB                    02330\n\
+8500020 Zuerich HB           -02400  02405\n\
+8500030 Bern                 -02500\n\
+*Z 000002 000011   101\n\
+*G IR  8500030 8500010\n\
+*A VE 8500030 8500010 000002\n\
+*L IR       8500030 8500010\n\
+*R H\n\
+8500030 Bern                         01000\n\
+8500010 Basel SBB             01100\n",
+    ),
+    (
+        "GLEIS",
+        "\
+8500010 000001 000011 #0000001      000001\n\
+8500020 000001 000011 #0000001                 \n\
+8500010 #0000001 G '1' A 'AB'\n\
+8500020 #0000001 G '7'\n",
+    ),
+    (
+        "GLEIS_LV95",
+        "\
+8500010 000001 000011 #0000001      000001\n\
+8500020 000001 000011 #0000001                 \n\
+8500010 #0000001 G '1' A 'AB'\n\
+8500010 #0000001 I A ch:1:sloid:10:1:1\n\
+8500010 #0000001 K 2611363 1266310\n",
+    ),
+    (
+        "GLEIS_WGS",
+        "\
+8500010 000001 000011 #0000001      000001\n\
+8500020 000001 000011 #0000001                 \n\
+8500010 #0000001 G '1' A 'AB'\n\
+8500010 #0000001 I A ch:1:sloid:10:1:1\n\
+8500010 #0000001 K 7.58956 47.5474\n",
+    ),
+    (
+        "GRENZHLT",
+        "\
+8500020 Grenze CH-CH\n",
+    ),
+    (
+        "INFOTEXT_DE",
+        "\
+000000001 Text DE\n",
+    ),
+    (
+        "INFOTEXT_EN",
+        "\
+000000001 Text EN\n",
+    ),
+    (
+        "INFOTEXT_FR",
+        "\
+000000001 Text FR\n",
+    ),
+    (
+        "INFOTEXT_IT",
+        "\
+000000001 Text IT\n",
+    ),
+    (
+        "KMINFO",
+        "\
+8500010 30000\n\
+8500020 30000\n\
+8500030     0\n",
+    ),
+    (
+        "LINIE",
+        "\
+0000001 K IC1\n\
+0000001 N T IC 1\n\
+0000001 F 255 255 255\n\
+0000001 B 000 000 000\n",
+    ),
+    (
+        "METABHF",
+        "\
+8500010 8500020 005\n\
+*A Y \n\
+8500010: 8500020\n",
+    ),
+    (
+        "RICHTUNG",
+        "\
+H000001 Zuerich HB\n\
+H000002 Basel SBB\n",
+    ),
+    (
+        "UMSTEIGB",
+        "\
+9999999 02 02\n\
+8500010 05 03\n",
+    ),
+    (
+        "UMSTEIGL",
+        "\
+8500010 000011 IC  *        * 000011 IR  *        * 003!\n",
+    ),
+    (
+        "UMSTEIGV",
+        "\
+@@@@@@@ 000011 000011 02\n\
+8500010 000011 000011 03\n",
+    ),
+    (
+        "UMSTEIGZ",
+        "\
+8500030 000001 000011 000002 000011 004! 000001\n",
+    ),
+    (
+        "ZEITVS",
+        "\
+% comment\n\
+0000000 +0100 +0200 31032024 0200 27102024 0300 %Europe\n\
+8500000 0000000\n\
+8500030 +0000 +0100 31032024 0100 27102024 0200 %test\n\
+7000000 +0000\n",
+    ),
+    (
+        "ZUGART",
+        "\
+IC   1 A 0 IC       0 N\n\
+IR   2 A 0 IR       0 N\n\
+<text>\n\
+<Deutsch>\n\
+class00 ICE\n\
+option10 Nur Sitzplaetze\n\
+category000 Intercity\n\
+<Franzoesisch>\n\
+class00 ICE\n\
+category000 Intercity\n",
+    ),
+];
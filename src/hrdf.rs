@@ -1,115 +1,1035 @@
 use std::{
-    error::Error,
+    env,
     fs::{self, File},
-    io::{BufReader, Cursor},
-    path::Path,
-    time::Instant,
+    io::{self, BufReader, Cursor, Read, Seek},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Instant, UNIX_EPOCH},
 };
 
-use crate::{models::Version, storage::DataStorage};
+#[cfg(feature = "download")]
+use crate::download;
+#[cfg(feature = "mmap-cache")]
+use crate::{cache::MappedHrdf, storage::ArchivedDataStorage};
+use crate::{
+    cache::{self, CacheHeader},
+    error::{Diagnostic, HrdfError, ParsingMode},
+    models::Version,
+    observer::{LoadEvent, LoadObserver},
+    parsing,
+    source::{DirectorySource, HrdfSource, ZipSource},
+    storage::{DataStorage, LoadOptions},
+};
+use flate2::read::GzDecoder;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+#[cfg(feature = "download")]
 use url::Url;
 use zip::ZipArchive;
 
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "mmap-cache",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
 pub struct Hrdf {
+    version: Version,
     data_storage: DataStorage,
 }
 
 impl Hrdf {
-    /// Loads and parses the data.<br>
-    /// If an URL is provided, the HRDF archive (ZIP file) is downloaded automatically. If a path is provided, it must absolutely point to an HRDF archive (ZIP file).<br>
-    /// The ZIP archive is automatically decompressed into the /tmp folder.
-    pub async fn new(
+    /// Loads and parses the data, with the default options of HrdfLoader.
+    #[cfg(feature = "download")]
+    pub async fn new(version: Version, url_or_path: &str) -> Result<Self, HrdfError> {
+        HrdfLoader::new(version).load(url_or_path).await
+    }
+
+    /// Same as new, but synchronous, as the data is never downloaded.
+    pub fn load_path(version: Version, path: impl AsRef<Path>) -> Result<Self, HrdfError> {
+        HrdfLoader::new(version).load_path(path)
+    }
+
+    /// Loads and parses the data directly from an HRDF archive (ZIP file), without extracting it.<br>
+    /// Nothing is written to the filesystem, in particular no cache is built.
+    pub fn from_reader<R: Read + Seek + Send>(
+        version: Version,
+        reader: R,
+    ) -> Result<Self, HrdfError> {
+        let (hrdf, _) = HrdfLoader::new(version).load_from_reader(reader)?;
+        Ok(hrdf)
+    }
+
+    /// Same as from_reader, but the HRDF archive (ZIP file) is already loaded in memory.
+    pub fn from_zip_bytes(
         version: Version,
+        bytes: impl AsRef<[u8]> + Send,
+    ) -> Result<Self, HrdfError> {
+        Self::from_reader(version, Cursor::new(bytes))
+    }
+
+    // Getters/Setters
+
+    /// The version of the HRDF data, either the one provided or the detected one.
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    pub fn data_storage(&self) -> &DataStorage {
+        &self.data_storage
+    }
+}
+
+#[cfg(feature = "mmap-cache")]
+impl ArchivedHrdf {
+    // Getters/Setters
+
+    pub fn version(&self) -> Version {
+        // unwrap: Deserializing a Version will never fail.
+        rkyv::Deserialize::deserialize(&self.version, &mut rkyv::Infallible).unwrap()
+    }
+
+    pub fn data_storage(&self) -> &ArchivedDataStorage {
+        &self.data_storage
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// --- HrdfLoader
+// ------------------------------------------------------------------------------------------------
+
+/// Builder used to configure how the HRDF data is loaded.
+#[derive(Clone)]
+pub struct HrdfLoader {
+    // None if the version must be detected from the data.
+    version: Option<Version>,
+    cache_dir: PathBuf,
+    data_dir: PathBuf,
+    keep_extracted_files: bool,
+    use_cache: bool,
+    force_rebuild_cache: bool,
+    // Always false without the compressed-cache feature.
+    compress_cache: bool,
+    parsing_mode: ParsingMode,
+    load_options: LoadOptions,
+    #[cfg(feature = "download")]
+    expected_sha256: Option<String>,
+    cache_key: Option<String>,
+    observer: Option<Arc<dyn LoadObserver>>,
+}
+
+impl HrdfLoader {
+    /// By default:
+    /// * the cache is written into the current working directory,
+    /// * the archives are downloaded and extracted into the temporary directory of the system (e.g. /tmp), and the extracted files are kept,
+    /// * the cache is used,
+    /// * the parsing mode is strict.
+    pub fn new(version: Version) -> Self {
+        Self::with_version(Some(version))
+    }
+
+    /// Same as new, but the version is detected from the data (see Hrdf::version).<br>
+    /// The loading fails if the data doesn't match the layout of any supported version.
+    pub fn with_detected_version() -> Self {
+        Self::with_version(None)
+    }
+
+    fn with_version(version: Option<Version>) -> Self {
+        Self {
+            version,
+            cache_dir: PathBuf::from("."),
+            data_dir: env::temp_dir(),
+            keep_extracted_files: true,
+            use_cache: true,
+            force_rebuild_cache: false,
+            compress_cache: false,
+            parsing_mode: ParsingMode::default(),
+            load_options: LoadOptions::default(),
+            #[cfg(feature = "download")]
+            expected_sha256: None,
+            cache_key: None,
+            observer: None,
+        }
+    }
+
+    /// Directory into which the cache is written.
+    pub fn cache_dir(mut self, path: impl Into<PathBuf>) -> Self {
+        self.cache_dir = path.into();
+        self
+    }
+
+    /// Directory into which the HRDF archives are downloaded and extracted.
+    pub fn data_dir(mut self, path: impl Into<PathBuf>) -> Self {
+        self.data_dir = path.into();
+        self
+    }
+
+    /// If false, the extracted files are deleted once the data has been parsed. A directory provided as input is never deleted.
+    pub fn keep_extracted_files(mut self, value: bool) -> Self {
+        self.keep_extracted_files = value;
+        self
+    }
+
+    /// If false, the cache is neither read nor written.
+    pub fn use_cache(mut self, value: bool) -> Self {
+        self.use_cache = value;
+        self
+    }
+
+    /// If true, the cache isn't read, but it's rebuilt from the HRDF data.
+    pub fn force_rebuild_cache(mut self, value: bool) -> Self {
+        self.force_rebuild_cache = value;
+        self
+    }
+
+    /// If true, the cache is compressed with zstd, which makes it several times smaller, but slower to read.<br>
+    /// The compressed and the uncompressed caches are both read, whatever this setting. Ignored by load_path_mapped.
+    #[cfg(feature = "compressed-cache")]
+    pub fn compress_cache(mut self, value: bool) -> Self {
+        self.compress_cache = value;
+        self
+    }
+
+    /// In lenient mode, the rows that cannot be parsed are skipped and returned as diagnostics (see load_with_diagnostics).
+    pub fn parsing_mode(mut self, value: ParsingMode) -> Self {
+        self.parsing_mode = value;
+        self
+    }
+
+    /// SHA-256 (hexadecimal) that the downloaded HRDF archive must have, otherwise the loading fails.
+    #[cfg(feature = "download")]
+    pub fn expected_sha256(mut self, value: impl Into<String>) -> Self {
+        self.expected_sha256 = Some(value.into());
+        self
+    }
+
+    /// Identifies the data given to load_path in the cache, instead of the fingerprint of the directory or the SHA-256 of the archive.<br>
+    /// The key must change whenever the data changes and may only contain ASCII letters, digits, '-', '_' and '.'.
+    pub fn cache_key(mut self, value: impl Into<String>) -> Self {
+        self.cache_key = Some(value.into());
+        self
+    }
+
+    /// Only the subsystems enabled in the options are loaded, by default everything is loaded.
+    pub fn load_options(mut self, value: LoadOptions) -> Self {
+        self.load_options = value;
+        self
+    }
+
+    /// The progress of the loading is reported to the observer.
+    pub fn observer(mut self, observer: impl LoadObserver + 'static) -> Self {
+        self.observer = Some(Arc::new(observer));
+        self
+    }
+
+    /// Loads and parses the data.<br>
+    /// If an URL is provided, the HRDF archive is downloaded automatically into the data directory. Otherwise, see load_path.<br>
+    /// The archive is only downloaded again if the server reports that it has changed (ETag/Last-Modified).
+    #[cfg(feature = "download")]
+    pub async fn load(&self, url_or_path: &str) -> Result<Hrdf, HrdfError> {
+        let (hrdf, _) = self.load_with_diagnostics(url_or_path).await?;
+        Ok(hrdf)
+    }
+
+    /// Same as load, but the diagnostics reported in lenient mode are returned as well.<br>
+    /// The cache is only built if no diagnostics have been reported, so that it always contains the complete data.
+    #[cfg(feature = "download")]
+    pub async fn load_with_diagnostics(
+        &self,
         url_or_path: &str,
-        force_rebuild_cache: bool,
-    ) -> Result<Self, Box<dyn Error>> {
+    ) -> Result<(Hrdf, Vec<Diagnostic>), HrdfError> {
+        if Url::parse(url_or_path).is_err() {
+            return self.load_path_with_diagnostics(url_or_path);
+        }
+
+        let now = Instant::now();
+
+        // The server is queried every time, as the content behind the URL may change (e.g. permalinks).
+        let (compressed_data_path, source_hash) = download::download(
+            url_or_path,
+            &self.data_dir,
+            self.expected_sha256.as_deref(),
+            self.observer.as_deref(),
+        )
+        .await?;
+
+        self.load_data(&compressed_data_path, source_hash, now)
+    }
+
+    /// Loads and parses the data, without downloading anything.<br>
+    /// The path must point either to a directory containing the HRDF files or to an HRDF archive.
+    /// The supported archive formats are ZIP, tar and gzip-compressed tar, the format is detected from the content of the archive.
+    /// The archive is automatically decompressed into the data directory, whereas a directory is read in place.<br>
+    /// The cache is identified by the HRDF data (see cache_key), it's rebuilt if the data, the version or the crate version changes.
+    pub fn load_path(&self, path: impl AsRef<Path>) -> Result<Hrdf, HrdfError> {
+        let (hrdf, _) = self.load_path_with_diagnostics(path)?;
+        Ok(hrdf)
+    }
+
+    /// Same as load_path, but the diagnostics reported in lenient mode are returned as well.
+    pub fn load_path_with_diagnostics(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> Result<(Hrdf, Vec<Diagnostic>), HrdfError> {
         let now = Instant::now();
+        let path = path.as_ref();
 
-        let unique_filename = format!("{:x}", Sha256::digest(url_or_path.as_bytes()));
-        let cache_path = format!("{unique_filename}.cache");
+        if !path.exists() {
+            return Err(HrdfError::MissingFile(path.display().to_string()));
+        }
 
-        let hrdf = if Path::new(&cache_path).exists() && !force_rebuild_cache {
+        let source_hash = self.source_hash(path)?;
+        self.load_data(path, source_hash, now)
+    }
+
+    fn load_data(
+        &self,
+        data_path: &Path,
+        source_hash: String,
+        now: Instant,
+    ) -> Result<(Hrdf, Vec<Diagnostic>), HrdfError> {
+        let cache_header =
+            CacheHeader::new(self.version, source_hash.clone(), self.load_options.clone());
+        let cache_path = self.cache_dir.join(cache_header.file_name());
+
+        let hrdf = if self.use_cache && !self.force_rebuild_cache && cache_path.exists() {
             // Loading from cache.
-            log::info!("Loading HRDF data from cache ({cache_path})...");
+            log::info!("Loading HRDF data from cache ({})...", cache_path.display());
 
-            // If loading from cache fails, None is returned.
-            Hrdf::load_from_cache(&cache_path).ok()
+            // If loading from cache fails, the cache is rebuilt.
+            let now = Instant::now();
+            match cache::read(&cache_path, &cache_header) {
+                Ok(hrdf) => {
+                    self.notify(LoadEvent::CacheRead {
+                        path: cache_path.clone(),
+                        duration: now.elapsed(),
+                    });
+                    Some(hrdf)
+                }
+                Err(e) => {
+                    log::warn!("{e}, the cache is rebuilt.");
+                    None
+                }
+            }
         } else {
             // No loading from cache.
             None
         };
 
-        let hrdf = if let Some(hrdf) = hrdf {
+        let (hrdf, diagnostics) = if let Some(hrdf) = hrdf {
             // The cache has been loaded without error.
-            hrdf
+            (hrdf, Vec::new())
         } else {
             // The cache must be built.
-            // If cache loading has failed, the cache must be rebuilt.
-            let compressed_data_path = if Url::parse(url_or_path).is_ok() {
-                let compressed_data_path = format!("/tmp/{unique_filename}.zip");
-
-                if !Path::new(&compressed_data_path).exists() {
-                    // The data must be downloaded.
-                    log::info!("Downloading HRDF data to {compressed_data_path}...");
-                    let response = reqwest::get(url_or_path).await?;
-                    let mut file = std::fs::File::create(&compressed_data_path)?;
-                    let mut content = Cursor::new(response.bytes().await?);
-                    std::io::copy(&mut content, &mut file)?;
-                }
+            let (hrdf, diagnostics) = self.extract_and_parse(data_path, &source_hash)?;
+
+            if !self.use_cache {
+                // The cache is disabled.
+            } else if diagnostics.is_empty() {
+                log::info!("Building cache...");
+                fs::create_dir_all(&self.cache_dir)?;
 
-                compressed_data_path
+                let now = Instant::now();
+                cache::write(&cache_path, &cache_header, &hrdf, self.compress_cache)?;
+                self.notify(LoadEvent::CacheWritten {
+                    path: cache_path.clone(),
+                    bytes: fs::metadata(&cache_path)?.len(),
+                    duration: now.elapsed(),
+                });
             } else {
-                url_or_path.to_string()
-            };
+                log::warn!(
+                    "{} diagnostic(s) reported, the cache is not built.",
+                    diagnostics.len()
+                );
+            }
+            (hrdf, diagnostics)
+        };
+
+        let elapsed = now.elapsed();
+
+        log::info!("HRDF data loaded in {:.2?}!", elapsed);
+
+        Ok((hrdf, diagnostics))
+    }
+
+    /// Same as load, but the data is read in place from a memory-mapped cache (see load_path_mapped).
+    #[cfg(all(feature = "download", feature = "mmap-cache"))]
+    pub async fn load_mapped(&self, url_or_path: &str) -> Result<MappedHrdf, HrdfError> {
+        if Url::parse(url_or_path).is_err() {
+            return self.load_path_mapped(url_or_path);
+        }
+
+        let now = Instant::now();
+        let (compressed_data_path, source_hash) = download::download(
+            url_or_path,
+            &self.data_dir,
+            self.expected_sha256.as_deref(),
+            self.observer.as_deref(),
+        )
+        .await?;
+
+        self.load_data_mapped(&compressed_data_path, source_hash, now)
+    }
+
+    /// Same as load_path, but the data is read in place from a memory-mapped cache (see MappedHrdf), which is built first if needed.<br>
+    /// The cache is built under the same conditions as with load_path, i.e. only if it's enabled and no diagnostics have been reported.
+    /// Otherwise, the data is written into an anonymous file of the cache directory, which is removed once the MappedHrdf is dropped.
+    #[cfg(feature = "mmap-cache")]
+    pub fn load_path_mapped(&self, path: impl AsRef<Path>) -> Result<MappedHrdf, HrdfError> {
+        let now = Instant::now();
+        let path = path.as_ref();
+
+        if !path.exists() {
+            return Err(HrdfError::MissingFile(path.display().to_string()));
+        }
+
+        let source_hash = self.source_hash(path)?;
+        self.load_data_mapped(path, source_hash, now)
+    }
+
+    #[cfg(feature = "mmap-cache")]
+    fn load_data_mapped(
+        &self,
+        data_path: &Path,
+        source_hash: String,
+        now: Instant,
+    ) -> Result<MappedHrdf, HrdfError> {
+        let cache_header =
+            CacheHeader::new(self.version, source_hash.clone(), self.load_options.clone());
+        let cache_path = self
+            .cache_dir
+            .join(cache_header.file_name())
+            .with_extension("mmap");
+
+        if self.use_cache && !self.force_rebuild_cache && cache_path.exists() {
+            log::info!("Mapping HRDF data from cache ({})...", cache_path.display());
+
+            // If mapping the cache fails, the cache is rebuilt.
+            // The file may have been written or modified by another process, so the data is validated.
+            let read_started_at = Instant::now();
+            match cache::open_mapped(&cache_path, Some(&cache_header), true) {
+                Ok(mapped_hrdf) => {
+                    self.notify(LoadEvent::CacheRead {
+                        path: cache_path,
+                        duration: read_started_at.elapsed(),
+                    });
+                    log::info!("HRDF data loaded in {:.2?}!", now.elapsed());
+                    return Ok(mapped_hrdf);
+                }
+                Err(e) => log::warn!("{e}, the cache is rebuilt."),
+            }
+        }
+
+        let (hrdf, diagnostics) = self.extract_and_parse(data_path, &source_hash)?;
+        fs::create_dir_all(&self.cache_dir)?;
+
+        let mapped_hrdf = if self.use_cache && diagnostics.is_empty() {
+            log::info!("Building memory-mapped cache...");
+
+            let write_started_at = Instant::now();
+            let mapped_hrdf = cache::write_mapped(&cache_path, &cache_header, &hrdf)?;
+            self.notify(LoadEvent::CacheWritten {
+                path: cache_path.clone(),
+                bytes: fs::metadata(&cache_path)?.len(),
+                duration: write_started_at.elapsed(),
+            });
+            drop(hrdf);
+
+            mapped_hrdf
+        } else {
+            // The data is read from an anonymous file, as the cache is disabled or would be incomplete.
+            if self.use_cache {
+                log::warn!(
+                    "{} diagnostic(s) reported, the cache is not built.",
+                    diagnostics.len()
+                );
+            }
+            cache::write_temporary_mapped(&self.cache_dir, &cache_header, &hrdf)?
+        };
+
+        log::info!("HRDF data loaded in {:.2?}!", now.elapsed());
 
-            let decompressed_data_path = format!("/tmp/{unique_filename}");
+        Ok(mapped_hrdf)
+    }
+
+    /// Extracts the archive (if needed) and parses the data.
+    fn extract_and_parse(
+        &self,
+        data_path: &Path,
+        source_hash: &str,
+    ) -> Result<(Hrdf, Vec<Diagnostic>), HrdfError> {
+        let (decompressed_data_path, extracted_path) = if data_path.is_dir() {
+            // The data has already been extracted.
+            (data_path.to_path_buf(), None)
+        } else {
+            let decompressed_data_path = self.data_dir.join(source_hash);
 
-            if !Path::new(&decompressed_data_path).exists() {
+            if !decompressed_data_path.exists() {
                 // The data must be decompressed.
-                log::info!("Unzipping HRDF archive into {decompressed_data_path}...");
-                let file = File::open(&compressed_data_path)?;
-                let mut archive = ZipArchive::new(BufReader::new(file))?;
-                archive.extract(&decompressed_data_path)?;
+                log::info!(
+                    "Extracting HRDF archive into {}...",
+                    decompressed_data_path.display()
+                );
+                fs::create_dir_all(&self.data_dir)?;
+                extract_archive(data_path, &decompressed_data_path, self.observer.as_deref())?;
+            }
+
+            (
+                find_data_directory(&decompressed_data_path)?,
+                Some(decompressed_data_path),
+            )
+        };
+
+        log::info!(
+            "Parsing HRDF data from {}...",
+            decompressed_data_path.display()
+        );
+
+        let source = DirectorySource::new(decompressed_data_path);
+        let result = self.parse(&source);
+
+        // The extracted files are deleted even if the parsing failed, but failing to delete them doesn't hide the outcome of the parsing.
+        if let (Some(extracted_path), false) = (extracted_path, self.keep_extracted_files) {
+            log::info!("Deleting {}...", extracted_path.display());
+            if let Err(e) = fs::remove_dir_all(&extracted_path) {
+                log::warn!("{} could not be deleted: {e}", extracted_path.display());
             }
+        }
+
+        result
+    }
+
+    /// Loads and parses the data directly from an HRDF archive (ZIP file), without extracting it.<br>
+    /// The cache and extraction settings are ignored, as nothing is written to the filesystem.
+    pub fn load_from_reader<R: Read + Seek + Send>(
+        &self,
+        reader: R,
+    ) -> Result<(Hrdf, Vec<Diagnostic>), HrdfError> {
+        let now = Instant::now();
+
+        log::info!("Parsing HRDF data from the archive...");
+        let source = ZipSource::new(reader)?;
+        let (hrdf, diagnostics) = self.parse(&source)?;
 
-            log::info!("Parsing HRDF data from {decompressed_data_path}...");
+        log::info!("HRDF data loaded in {:.2?}!", now.elapsed());
 
-            let hrdf = Self {
-                data_storage: DataStorage::new(version, &decompressed_data_path)?,
-            };
+        Ok((hrdf, diagnostics))
+    }
+
+    fn notify(&self, event: LoadEvent) {
+        if let Some(observer) = &self.observer {
+            observer.on_event(&event);
+        }
+    }
 
-            log::info!("Building cache...");
-            hrdf.build_cache(&cache_path)?;
-            hrdf
+    fn parse(&self, source: &dyn HrdfSource) -> Result<(Hrdf, Vec<Diagnostic>), HrdfError> {
+        let version = match self.version {
+            Some(version) => version,
+            None => {
+                let version = parsing::detect_version(source)?;
+                log::info!("HRDF version detected: {version}.");
+                version
+            }
         };
 
-        let elapsed = now.elapsed();
+        let (data_storage, diagnostics) = DataStorage::from_source(
+            version,
+            source,
+            self.parsing_mode,
+            self.load_options.clone(),
+            self.observer.clone(),
+        )?;
+        Ok((
+            Hrdf {
+                version,
+                data_storage,
+            },
+            diagnostics,
+        ))
+    }
 
-        log::info!("HRDF data loaded in {:.2?}!", elapsed);
+    /// A directory is identified by its fingerprint (see fingerprint_data), as hashing its files would take as long as parsing them.<br>
+    /// An archive is identified by its SHA-256, which is only computed again if its fingerprint has changed.
+    /// The SHA-256 is kept in the cache directory, next to the caches.
+    fn source_hash(&self, path: &Path) -> Result<String, HrdfError> {
+        if let Some(cache_key) = &self.cache_key {
+            let is_valid = |c: char| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.');
+            if cache_key.is_empty() || !cache_key.chars().all(is_valid) {
+                return Err(HrdfError::InvalidData(format!(
+                    "The cache key {cache_key:?} is invalid."
+                )));
+            }
+            return Ok(cache_key.clone());
+        }
 
-        Ok(hrdf)
+        let fingerprint = fingerprint_data(path)?;
+        if path.is_dir() {
+            return Ok(fingerprint);
+        }
+        if !self.use_cache {
+            return hash_file(path);
+        }
+
+        let hash_path = self.cache_dir.join(format!("{fingerprint}.sha256"));
+        if let Some(hash) = fs::read_to_string(&hash_path)
+            .ok()
+            .filter(|hash| hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit()))
+        {
+            return Ok(hash);
+        }
+
+        let hash = hash_file(path)?;
+        // The SHA-256 is computed again on the next load if it cannot be kept.
+        if let Err(e) =
+            fs::create_dir_all(&self.cache_dir).and_then(|_| fs::write(&hash_path, &hash))
+        {
+            log::warn!("{}: {e}", hash_path.display());
+        }
+        Ok(hash)
     }
+}
 
-    // Getters/Setters
+// ------------------------------------------------------------------------------------------------
+// --- Helper Functions
+// ------------------------------------------------------------------------------------------------
 
-    pub fn data_storage(&self) -> &DataStorage {
-        &self.data_storage
+/// Computes a fingerprint of the path, the size and the modification time of the archive, or of the files of the directory.<br>
+/// It's cheap to compute, as the content of the files isn't read.
+fn fingerprint_data(path: &Path) -> Result<String, HrdfError> {
+    let mut hasher = Sha256::new();
+    let path = path.canonicalize()?;
+    hasher.update(path.to_string_lossy().as_bytes());
+
+    let files = if path.is_dir() {
+        let mut files = fs::read_dir(&path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        files.retain(|file| file.is_file());
+        files.sort();
+        files
+    } else {
+        vec![path]
+    };
+
+    for file in files {
+        // The name is part of the fingerprint, so that renaming a file changes it too.
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        let metadata = fs::metadata(&file)?;
+        // The modification time isn't available on every platform, in which case only the size is taken into account.
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |modified| modified.as_nanos());
+
+        hasher.update(name.as_bytes());
+        hasher.update(metadata.len().to_le_bytes());
+        hasher.update(modified.to_le_bytes());
     }
 
-    // Functions
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Computes the SHA-256 of the file.
+fn hash_file(path: &Path) -> Result<String, HrdfError> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Extracts the archive, whose format is detected from its first bytes.
+fn extract_archive(
+    archive_path: &Path,
+    destination: &Path,
+    observer: Option<&dyn LoadObserver>,
+) -> Result<(), HrdfError> {
+    let mut file = BufReader::new(File::open(archive_path)?);
+    let mut header = Vec::new();
+    file.by_ref().take(512).read_to_end(&mut header)?;
+    file.rewind()?;
 
-    pub fn build_cache(&self, path: &str) -> Result<(), Box<dyn Error>> {
-        let data = bincode::serialize(&self)?;
-        fs::write(path, data)?;
-        Ok(())
+    // The archive is first extracted into a temporary directory, so that an interrupted extraction isn't mistaken for a complete one.
+    let temporary_destination = destination.with_extension("part");
+    if temporary_destination.exists() {
+        fs::remove_dir_all(&temporary_destination)?;
     }
 
-    pub fn load_from_cache(path: &str) -> Result<Self, Box<dyn Error>> {
-        let data = fs::read(path)?;
-        let hrdf: Self = bincode::deserialize(&data)?;
-        Ok(hrdf)
+    if header.starts_with(b"PK\x03\x04") || header.starts_with(b"PK\x05\x06") {
+        extract_zip(ZipArchive::new(file)?, &temporary_destination, observer)?;
+    } else if header.starts_with(&[0x1f, 0x8b]) {
+        extract_tar(
+            tar::Archive::new(GzDecoder::new(file)),
+            &temporary_destination,
+            observer,
+        )?;
+    } else if header.get(257..262) == Some(b"ustar".as_slice()) {
+        extract_tar(tar::Archive::new(file), &temporary_destination, observer)?;
+    } else {
+        return Err(HrdfError::InvalidData(format!(
+            "{} is neither a directory, nor a ZIP, tar or gzip archive.",
+            archive_path.display()
+        )));
+    }
+
+    fs::rename(&temporary_destination, destination)?;
+    Ok(())
+}
+
+/// The entries are extracted one at a time, so that the progress can be reported to the observer.
+fn extract_zip(
+    mut archive: ZipArchive<impl Read + Seek>,
+    destination: &Path,
+    observer: Option<&dyn LoadObserver>,
+) -> Result<(), HrdfError> {
+    let total_entries = archive.len();
+
+    for i in 0..total_entries {
+        let mut entry = archive.by_index(i)?;
+        // The entries whose path would be outside of the destination are rejected.
+        let path = entry
+            .enclosed_name()
+            .map(|path| destination.join(path))
+            .ok_or_else(|| {
+                HrdfError::InvalidData(format!("The archive entry {} is invalid.", entry.name()))
+            })?;
+
+        if entry.is_dir() {
+            fs::create_dir_all(&path)?;
+        } else {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            io::copy(&mut entry, &mut File::create(&path)?)?;
+        }
+
+        if let Some(observer) = observer {
+            observer.on_event(&LoadEvent::ExtractionProgress {
+                entries_extracted: i + 1,
+                total_entries: Some(total_entries),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Same as extract_zip, but the number of entries is unknown until the end of the archive.
+fn extract_tar(
+    mut archive: tar::Archive<impl Read>,
+    destination: &Path,
+    observer: Option<&dyn LoadObserver>,
+) -> Result<(), HrdfError> {
+    fs::create_dir_all(destination)?;
+
+    for (i, entry) in archive.entries()?.enumerate() {
+        // The entries whose path would be outside of the destination are skipped.
+        entry?.unpack_in(destination)?;
+
+        if let Some(observer) = observer {
+            observer.on_event(&LoadEvent::ExtractionProgress {
+                entries_extracted: i + 1,
+                total_entries: None,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Archives sometimes contain the HRDF files in a subdirectory rather than at their root.
+fn find_data_directory(path: &Path) -> Result<PathBuf, HrdfError> {
+    // ECKDATEN is present in every version of the HRDF format.
+    if path.join("ECKDATEN").exists() {
+        return Ok(path.to_path_buf());
+    }
+
+    for entry in fs::read_dir(path)? {
+        let entry_path = entry?.path();
+        if entry_path.join("ECKDATEN").exists() {
+            return Ok(entry_path);
+        }
+    }
+
+    // The missing files are reported by the parsers.
+    Ok(path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::Write,
+        mem,
+        sync::{Arc, Mutex},
+    };
+
+    use zip::CompressionMethod;
+
+    use crate::fixtures::MemorySource;
+
+    use super::*;
+
+    #[test]
+    fn source_hash_of_directory_changes_with_files() {
+        let directory = tempfile::tempdir().unwrap();
+        let loader = HrdfLoader::new(Version::V_5_40_41_2_0_4).cache_dir(directory.path());
+        let data_path = directory.path().join("data");
+        fs::create_dir(&data_path).unwrap();
+        fs::write(data_path.join("ECKDATEN"), "10.12.2023").unwrap();
+
+        let source_hash = loader.source_hash(&data_path).unwrap();
+        assert_eq!(loader.source_hash(&data_path).unwrap(), source_hash);
+
+        fs::write(data_path.join("ECKDATEN"), "10.12.2023\r\n").unwrap();
+        assert_ne!(loader.source_hash(&data_path).unwrap(), source_hash);
+    }
+
+    #[test]
+    fn source_hash_of_archive_is_kept() {
+        let directory = tempfile::tempdir().unwrap();
+        let loader = HrdfLoader::new(Version::V_5_40_41_2_0_4).cache_dir(directory.path());
+        let archive_path = directory.path().join("data.zip");
+        fs::write(&archive_path, "content").unwrap();
+
+        let source_hash = loader.source_hash(&archive_path).unwrap();
+        assert_eq!(source_hash, format!("{:x}", Sha256::digest("content")));
+
+        // The kept SHA-256 is used as long as the fingerprint of the archive doesn't change.
+        let hash_path = directory.path().join(format!(
+            "{}.sha256",
+            fingerprint_data(&archive_path).unwrap()
+        ));
+        fs::write(&hash_path, "0".repeat(64)).unwrap();
+        assert_eq!(loader.source_hash(&archive_path).unwrap(), "0".repeat(64));
+    }
+
+    #[test]
+    fn zip_is_read_without_extraction() {
+        for compression_method in [CompressionMethod::Stored, CompressionMethod::Deflated] {
+            let zip = MemorySource::new().to_zip(compression_method);
+            let hrdf = Hrdf::from_zip_bytes(Version::V_5_40_41_2_0_4, zip).unwrap();
+            assert_eq!(hrdf.data_storage().journeys().entries().len(), 2);
+            assert_eq!(hrdf.data_storage().stops().entries().len(), 3);
+        }
+    }
+
+    #[test]
+    fn detected_version_is_exposed() {
+        let zip = MemorySource::new().to_zip(CompressionMethod::Stored);
+        let (hrdf, _) = HrdfLoader::with_detected_version()
+            .load_from_reader(Cursor::new(zip))
+            .unwrap();
+        assert_eq!(hrdf.version(), Version::V_5_40_41_2_0_4);
+    }
+
+    fn loader(directory: &Path) -> HrdfLoader {
+        HrdfLoader::new(Version::V_5_40_41_2_0_4)
+            .cache_dir(directory.join("cache"))
+            .data_dir(directory.join("extracted"))
+            .use_cache(false)
+    }
+
+    #[test]
+    fn directory_is_read_in_place() {
+        let directory = tempfile::tempdir().unwrap();
+        let data_path = directory.path().join("data");
+        MemorySource::new().write_to(&data_path).unwrap();
+
+        let hrdf = loader(directory.path()).load_path(&data_path).unwrap();
+        assert_eq!(hrdf.data_storage().journeys().entries().len(), 2);
+        assert!(!directory.path().join("extracted").exists());
+    }
+
+    #[test]
+    fn tar_archives_are_extracted() {
+        let directory = tempfile::tempdir().unwrap();
+        let data_path = directory.path().join("data");
+        MemorySource::new().write_to(&data_path).unwrap();
+
+        // The files are in a subdirectory of the archives.
+        let mut builder = tar::Builder::new(Vec::new());
+        builder.append_dir_all("hrdf", &data_path).unwrap();
+        let tar = builder.into_inner().unwrap();
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        encoder.write_all(&tar).unwrap();
+        let tar_gz = encoder.finish().unwrap();
+
+        for (name, content) in [("data.tar", tar), ("data.tar.gz", tar_gz)] {
+            let archive_path = directory.path().join(name);
+            fs::write(&archive_path, content).unwrap();
+
+            let hrdf = loader(directory.path()).load_path(&archive_path).unwrap();
+            assert_eq!(hrdf.data_storage().journeys().entries().len(), 2);
+        }
+    }
+
+    #[cfg(feature = "download")]
+    #[tokio::test]
+    async fn path_is_loaded_without_download() {
+        let directory = tempfile::tempdir().unwrap();
+        let data_path = directory.path().join("data");
+        MemorySource::new().write_to(&data_path).unwrap();
+
+        let hrdf = loader(directory.path())
+            .load(data_path.to_str().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(hrdf.data_storage().journeys().entries().len(), 2);
+    }
+
+    #[test]
+    fn unknown_archive_format_is_rejected() {
+        let directory = tempfile::tempdir().unwrap();
+        let archive_path = directory.path().join("data.rar");
+        fs::write(&archive_path, "Rar!").unwrap();
+
+        let result = loader(directory.path()).load_path(&archive_path);
+        assert!(matches!(result, Err(HrdfError::InvalidData(_))));
+    }
+
+    #[test]
+    fn missing_path_is_reported() {
+        let directory = tempfile::tempdir().unwrap();
+        let result = loader(directory.path()).load_path(directory.path().join("data"));
+        assert!(matches!(result, Err(HrdfError::MissingFile(_))));
+    }
+
+    #[test]
+    fn extracted_files_are_deleted() {
+        let directory = tempfile::tempdir().unwrap();
+        let archive_path = directory.path().join("data.zip");
+        fs::write(
+            &archive_path,
+            MemorySource::new().to_zip(CompressionMethod::Deflated),
+        )
+        .unwrap();
+
+        let data_dir = directory.path().join("data");
+        let loader = HrdfLoader::new(Version::V_5_40_41_2_0_4)
+            .cache_dir(directory.path())
+            .data_dir(&data_dir)
+            .use_cache(false)
+            .keep_extracted_files(false);
+        let hrdf = loader.load_path(&archive_path).unwrap();
+        assert_eq!(hrdf.data_storage().journeys().entries().len(), 2);
+        assert_eq!(fs::read_dir(&data_dir).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn cache_is_written_into_cache_dir() {
+        let directory = tempfile::tempdir().unwrap();
+        let archive_path = directory.path().join("data.zip");
+        fs::write(
+            &archive_path,
+            MemorySource::new().to_zip(CompressionMethod::Deflated),
+        )
+        .unwrap();
+
+        let cache_dir = directory.path().join("cache");
+        let data_dir = directory.path().join("extracted");
+        let loader = loader(directory.path()).use_cache(true);
+        loader.load_path(&archive_path).unwrap();
+
+        let cache_files: Vec<_> = fs::read_dir(&cache_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "cache")
+            })
+            .collect();
+        assert_eq!(cache_files.len(), 1);
+        // The extracted files are kept by default.
+        assert_eq!(fs::read_dir(&data_dir).unwrap().count(), 1);
+
+        // The second load reads the cache, without extracting the archive.
+        fs::remove_dir_all(&data_dir).unwrap();
+        let hrdf = loader.load_path(&archive_path).unwrap();
+        assert_eq!(hrdf.data_storage().journeys().entries().len(), 2);
+        assert!(!data_dir.exists());
+    }
+
+    #[test]
+    fn events_are_reported_to_observer() {
+        let directory = tempfile::tempdir().unwrap();
+        let archive_path = directory.path().join("data.zip");
+        fs::write(
+            &archive_path,
+            MemorySource::new().to_zip(CompressionMethod::Deflated),
+        )
+        .unwrap();
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let loader = {
+            let events = Arc::clone(&events);
+            loader(directory.path())
+                .use_cache(true)
+                .observer(move |event: &LoadEvent| events.lock().unwrap().push(event.clone()))
+        };
+
+        loader.load_path(&archive_path).unwrap();
+        let reported_events = mem::take(&mut *events.lock().unwrap());
+
+        assert!(reported_events.iter().any(|event| matches!(
+            event,
+            LoadEvent::ExtractionProgress { entries_extracted, total_entries: Some(total) }
+                if entries_extracted == total
+        )));
+        let started_files: Vec<_> = reported_events
+            .iter()
+            .filter_map(|event| match event {
+                LoadEvent::FileStarted { file } => Some(file.as_str()),
+                _ => None,
+            })
+            .collect();
+        let finished_files: Vec<_> = reported_events
+            .iter()
+            .filter_map(|event| match event {
+                LoadEvent::FileFinished { file, rows, .. } => Some((file.as_str(), *rows)),
+                _ => None,
+            })
+            .collect();
+        assert!(started_files.contains(&"FPLAN"));
+        assert_eq!(started_files.len(), finished_files.len());
+        assert!(finished_files
+            .iter()
+            .any(|&(file, rows)| file == "FPLAN" && rows > 0));
+        assert!(reported_events
+            .iter()
+            .any(|event| matches!(event, LoadEvent::IndexFinished { name, .. } if name == "bit_fields_by_day")));
+        assert!(matches!(
+            reported_events.last(),
+            Some(LoadEvent::CacheWritten { bytes, .. }) if *bytes > 0
+        ));
+
+        // The second load only reads the cache.
+        loader.load_path(&archive_path).unwrap();
+        let reported_events = mem::take(&mut *events.lock().unwrap());
+        assert!(matches!(
+            reported_events.as_slice(),
+            [LoadEvent::CacheRead { .. }]
+        ));
+    }
+
+    #[test]
+    fn nothing_is_written_without_cache() {
+        let directory = tempfile::tempdir().unwrap();
+        let data_path = directory.path().join("data");
+        MemorySource::new().write_to(&data_path).unwrap();
+
+        loader(directory.path()).load_path(&data_path).unwrap();
+        assert!(!directory.path().join("cache").exists());
+    }
+
+    #[test]
+    fn cache_key_is_validated() {
+        let loader = HrdfLoader::new(Version::V_5_40_41_2_0_4);
+        let path = Path::new(".");
+        assert_eq!(
+            loader
+                .clone()
+                .cache_key("2024-v1")
+                .source_hash(path)
+                .unwrap(),
+            "2024-v1"
+        );
+        assert!(loader.cache_key("../data").source_hash(path).is_err());
     }
 }
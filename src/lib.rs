@@ -1,11 +1,29 @@
+mod cache;
+#[cfg(feature = "download")]
+mod download;
+mod error;
+mod filtering;
+#[cfg(test)]
+mod fixtures;
 mod hrdf;
 mod models;
+mod observer;
 mod parsing;
+mod source;
 mod storage;
 mod utils;
 
-pub use hrdf::Hrdf;
+#[cfg(feature = "mmap-cache")]
+pub use cache::MappedHrdf;
+pub use error::{Diagnostic, HrdfError, ParsingMode, Severity};
+pub use filtering::Area;
+#[cfg(feature = "mmap-cache")]
+pub use hrdf::ArchivedHrdf;
+pub use hrdf::{Hrdf, HrdfLoader};
 pub use models::*;
-pub use storage::DataStorage;
+pub use observer::{LoadEvent, LoadObserver};
+#[cfg(feature = "mmap-cache")]
+pub use storage::{ArchivedDataStorage, ArchivedResourceStorage};
+pub use storage::{DataStorage, LoadOptions};
 pub use utils::timetable_end_date;
 pub use utils::timetable_start_date;
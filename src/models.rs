@@ -1,16 +1,31 @@
+// The resolver generated for Version by rkyv doesn't inherit the attributes of the enum.
+#![cfg_attr(feature = "mmap-cache", allow(non_camel_case_types))]
+
 use std::{
     collections::BTreeSet,
     hash::{DefaultHasher, Hash, Hasher},
 };
 
-use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use chrono::{DateTime, FixedOffset, LocalResult, NaiveDate, NaiveDateTime, NaiveTime};
 use rustc_hash::FxHashMap;
 use serde::{Deserialize, Serialize};
 use strum_macros::{self, Display, EnumString};
 
+mod shared;
+
+#[cfg(feature = "mmap-cache")]
+use rkyv::collections::hash_set::ArchivedHashSet;
+
+#[cfg(feature = "mmap-cache")]
+use crate::storage::ArchivedDataStorage;
 use crate::{
     storage::DataStorage,
-    utils::{add_1_day, sub_1_day},
+    utils::{add_days, create_offset},
+};
+
+use shared::{
+    CoordinatesData, DaylightSavingPeriodData, JourneyData, JourneyMetadataEntryData,
+    JourneyRouteEntryData, TimeDifferenceData,
 };
 
 // ------------------------------------------------------------------------------------------------
@@ -32,6 +47,12 @@ pub trait Model<M: Model<M>> {
     }
 }
 
+/// Same as Model, but for the data read in place (see MappedHrdf).
+#[cfg(feature = "mmap-cache")]
+pub trait ArchivedModel {
+    fn id(&self) -> i32;
+}
+
 macro_rules! impl_Model {
     ($m:ty) => {
         impl Model<$m> for $m {
@@ -41,14 +62,36 @@ macro_rules! impl_Model {
                 self.id
             }
         }
+
+        #[cfg(feature = "mmap-cache")]
+        impl ArchivedModel for <$m as rkyv::Archive>::Archived {
+            fn id(&self) -> i32 {
+                self.id
+            }
+        }
     };
 }
 
+/// Deserializes a value read in place that doesn't need any allocation, e.g. a NaiveTime.
+#[cfg(feature = "mmap-cache")]
+fn unarchive<T: rkyv::Archive>(value: &T::Archived) -> T
+where
+    T::Archived: rkyv::Deserialize<T, rkyv::Infallible>,
+{
+    // unwrap: The Infallible deserializer never fails.
+    rkyv::Deserialize::deserialize(value, &mut rkyv::Infallible).unwrap()
+}
+
 // ------------------------------------------------------------------------------------------------
 // --- Attribute
 // ------------------------------------------------------------------------------------------------
 
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "mmap-cache",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
 pub struct Attribute {
     id: i32,
     designation: String,
@@ -56,6 +99,11 @@ pub struct Attribute {
     main_sorting_priority: i16,
     secondary_sorting_priority: i16,
     description: FxHashMap<Language, String>,
+    // From the ATTRIBUT_* files.
+    output_text: FxHashMap<Language, String>,
+    // Designations of the attributes whose text is output when the attribute applies to a part of the journey or to the whole journey.
+    partial_stretch_designation: FxHashMap<Language, String>,
+    full_stretch_designation: FxHashMap<Language, String>,
 }
 
 impl_Model!(Attribute);
@@ -75,14 +123,91 @@ impl Attribute {
             main_sorting_priority,
             secondary_sorting_priority,
             description: FxHashMap::default(),
+            output_text: FxHashMap::default(),
+            partial_stretch_designation: FxHashMap::default(),
+            full_stretch_designation: FxHashMap::default(),
         }
     }
 
     // Getters/Setters
 
+    pub fn designation(&self) -> &str {
+        &self.designation
+    }
+
+    pub fn description(&self, language: Language) -> Option<&str> {
+        self.description.get(&language).map(|value| value.as_str())
+    }
+
     pub fn set_description(&mut self, language: Language, value: &str) {
         self.description.insert(language, value.to_string());
     }
+
+    /// The passenger-facing text, e.g. for the bike reservation or the wheelchair accessibility.
+    pub fn output_text(&self, language: Language) -> Option<&str> {
+        self.output_text.get(&language).map(|value| value.as_str())
+    }
+
+    pub fn set_output_text(&mut self, language: Language, value: &str) {
+        self.output_text.insert(language, value.to_string());
+    }
+
+    /// Designation of the attribute whose text is output when the attribute only applies to a part of the journey.
+    pub fn partial_stretch_designation(&self, language: Language) -> Option<&str> {
+        self.partial_stretch_designation
+            .get(&language)
+            .map(|value| value.as_str())
+    }
+
+    pub fn set_partial_stretch_designation(&mut self, language: Language, value: &str) {
+        self.partial_stretch_designation
+            .insert(language, value.to_string());
+    }
+
+    /// Designation of the attribute whose text is output when the attribute applies to the whole journey.
+    pub fn full_stretch_designation(&self, language: Language) -> Option<&str> {
+        self.full_stretch_designation
+            .get(&language)
+            .map(|value| value.as_str())
+    }
+
+    pub fn set_full_stretch_designation(&mut self, language: Language, value: &str) {
+        self.full_stretch_designation
+            .insert(language, value.to_string());
+    }
+}
+
+#[cfg(feature = "mmap-cache")]
+impl ArchivedAttribute {
+    // Getters/Setters
+
+    pub fn designation(&self) -> &str {
+        &self.designation
+    }
+
+    pub fn description(&self, language: Language) -> Option<&str> {
+        self.description
+            .get(&ArchivedLanguage::from(language))
+            .map(|value| value.as_str())
+    }
+
+    pub fn output_text(&self, language: Language) -> Option<&str> {
+        self.output_text
+            .get(&ArchivedLanguage::from(language))
+            .map(|value| value.as_str())
+    }
+
+    pub fn partial_stretch_designation(&self, language: Language) -> Option<&str> {
+        self.partial_stretch_designation
+            .get(&ArchivedLanguage::from(language))
+            .map(|value| value.as_str())
+    }
+
+    pub fn full_stretch_designation(&self, language: Language) -> Option<&str> {
+        self.full_stretch_designation
+            .get(&ArchivedLanguage::from(language))
+            .map(|value| value.as_str())
+    }
 }
 
 // ------------------------------------------------------------------------------------------------
@@ -90,6 +215,11 @@ impl Attribute {
 // ------------------------------------------------------------------------------------------------
 
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "mmap-cache",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
 pub struct BitField {
     id: i32,
     bits: Vec<u8>,
@@ -109,11 +239,73 @@ impl BitField {
     }
 }
 
+#[cfg(feature = "mmap-cache")]
+impl ArchivedBitField {
+    // Getters/Setters
+
+    pub fn bits(&self) -> &[u8] {
+        &self.bits
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// --- BorderPoint
+// ------------------------------------------------------------------------------------------------
+
+/// A stop at the border between two countries or tariff areas.<br>
+/// The ID of a border point is the ID of its stop.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "mmap-cache",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
+pub struct BorderPoint {
+    id: i32,
+    designation: String,
+}
+
+impl_Model!(BorderPoint);
+
+impl BorderPoint {
+    pub fn new(id: i32, designation: String) -> Self {
+        Self { id, designation }
+    }
+
+    // Getters/Setters
+
+    pub fn stop_id(&self) -> i32 {
+        self.id
+    }
+
+    pub fn designation(&self) -> &str {
+        &self.designation
+    }
+}
+
+#[cfg(feature = "mmap-cache")]
+impl ArchivedBorderPoint {
+    // Getters/Setters
+
+    pub fn stop_id(&self) -> i32 {
+        self.id
+    }
+
+    pub fn designation(&self) -> &str {
+        &self.designation
+    }
+}
+
 // ------------------------------------------------------------------------------------------------
 // --- Color
 // ------------------------------------------------------------------------------------------------
 
 #[derive(Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "mmap-cache",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
 pub struct Color {
     r: i16,
     g: i16,
@@ -141,11 +333,33 @@ impl Color {
     }
 }
 
+#[cfg(feature = "mmap-cache")]
+impl ArchivedColor {
+    // Getters/Setters
+
+    pub fn r(&self) -> i16 {
+        self.r
+    }
+
+    pub fn g(&self) -> i16 {
+        self.g
+    }
+
+    pub fn b(&self) -> i16 {
+        self.b
+    }
+}
+
 // ------------------------------------------------------------------------------------------------
 // --- CoordinateSystem
 // ------------------------------------------------------------------------------------------------
 
 #[derive(Clone, Copy, Debug, Default, Display, Eq, Hash, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "mmap-cache",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
 pub enum CoordinateSystem {
     #[default]
     LV95,
@@ -156,7 +370,12 @@ pub enum CoordinateSystem {
 // --- Coordinates
 // ------------------------------------------------------------------------------------------------
 
-#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "mmap-cache",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
 pub struct Coordinates {
     coordinate_system: CoordinateSystem,
     x: f64,
@@ -175,32 +394,193 @@ impl Coordinates {
 
     // Getters/Setters
 
+    pub fn coordinate_system(&self) -> CoordinateSystem {
+        self.coordinate_system
+    }
+
     pub fn easting(&self) -> f64 {
-        assert!(self.coordinate_system == CoordinateSystem::LV95);
-        self.x
+        CoordinatesData::easting(self)
     }
 
     pub fn northing(&self) -> f64 {
-        assert!(self.coordinate_system == CoordinateSystem::LV95);
-        self.y
+        CoordinatesData::northing(self)
     }
 
     pub fn latitude(&self) -> f64 {
-        assert!(self.coordinate_system == CoordinateSystem::WGS84);
+        CoordinatesData::latitude(self)
+    }
+
+    pub fn longitude(&self) -> f64 {
+        CoordinatesData::longitude(self)
+    }
+}
+
+impl CoordinatesData for Coordinates {
+    fn coordinate_system(&self) -> CoordinateSystem {
+        self.coordinate_system
+    }
+
+    fn x(&self) -> f64 {
         self.x
     }
 
+    fn y(&self) -> f64 {
+        self.y
+    }
+}
+
+#[cfg(feature = "mmap-cache")]
+impl ArchivedCoordinates {
+    // Getters/Setters
+
+    pub fn coordinate_system(&self) -> CoordinateSystem {
+        unarchive(&self.coordinate_system)
+    }
+
+    pub fn easting(&self) -> f64 {
+        CoordinatesData::easting(self)
+    }
+
+    pub fn northing(&self) -> f64 {
+        CoordinatesData::northing(self)
+    }
+
+    pub fn latitude(&self) -> f64 {
+        CoordinatesData::latitude(self)
+    }
+
     pub fn longitude(&self) -> f64 {
-        assert!(self.coordinate_system == CoordinateSystem::WGS84);
+        CoordinatesData::longitude(self)
+    }
+}
+
+#[cfg(feature = "mmap-cache")]
+impl CoordinatesData for ArchivedCoordinates {
+    fn coordinate_system(&self) -> CoordinateSystem {
+        unarchive(&self.coordinate_system)
+    }
+
+    fn x(&self) -> f64 {
+        self.x
+    }
+
+    fn y(&self) -> f64 {
         self.y
     }
 }
 
+// ------------------------------------------------------------------------------------------------
+// --- DaylightSavingPeriod
+// ------------------------------------------------------------------------------------------------
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "mmap-cache",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
+pub struct DaylightSavingPeriod {
+    // In seconds, east of UTC.
+    utc_offset: i32,
+    // UTC, start included and end excluded.
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+}
+
+impl DaylightSavingPeriod {
+    pub fn new(utc_offset: i32, start: NaiveDateTime, end: NaiveDateTime) -> Self {
+        Self {
+            utc_offset,
+            start,
+            end,
+        }
+    }
+
+    // Getters/Setters
+
+    pub fn utc_offset(&self) -> FixedOffset {
+        create_offset(self.utc_offset)
+    }
+
+    /// The UTC date and time at which the daylight saving time starts.
+    pub fn start(&self) -> NaiveDateTime {
+        self.start
+    }
+
+    /// The UTC date and time at which the daylight saving time ends.
+    pub fn end(&self) -> NaiveDateTime {
+        self.end
+    }
+
+    // Functions
+
+    pub fn contains(&self, utc: NaiveDateTime) -> bool {
+        DaylightSavingPeriodData::contains(self, utc)
+    }
+}
+
+impl DaylightSavingPeriodData for DaylightSavingPeriod {
+    fn utc_offset(&self) -> FixedOffset {
+        create_offset(self.utc_offset)
+    }
+
+    fn start(&self) -> NaiveDateTime {
+        self.start
+    }
+
+    fn end(&self) -> NaiveDateTime {
+        self.end
+    }
+}
+
+#[cfg(feature = "mmap-cache")]
+impl ArchivedDaylightSavingPeriod {
+    // Getters/Setters
+
+    pub fn utc_offset(&self) -> FixedOffset {
+        create_offset(self.utc_offset)
+    }
+
+    pub fn start(&self) -> NaiveDateTime {
+        unarchive(&self.start)
+    }
+
+    pub fn end(&self) -> NaiveDateTime {
+        unarchive(&self.end)
+    }
+
+    // Functions
+
+    pub fn contains(&self, utc: NaiveDateTime) -> bool {
+        DaylightSavingPeriodData::contains(self, utc)
+    }
+}
+
+#[cfg(feature = "mmap-cache")]
+impl DaylightSavingPeriodData for ArchivedDaylightSavingPeriod {
+    fn utc_offset(&self) -> FixedOffset {
+        create_offset(self.utc_offset)
+    }
+
+    fn start(&self) -> NaiveDateTime {
+        unarchive(&self.start)
+    }
+
+    fn end(&self) -> NaiveDateTime {
+        unarchive(&self.end)
+    }
+}
+
 // ------------------------------------------------------------------------------------------------
 // --- Direction
 // ------------------------------------------------------------------------------------------------
 
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "mmap-cache",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
 pub struct Direction {
     id: i32,
     name: String,
@@ -221,6 +601,11 @@ impl Direction {
 #[derive(
     Clone, Copy, Debug, Default, Display, Eq, Hash, PartialEq, EnumString, Serialize, Deserialize,
 )]
+#[cfg_attr(
+    feature = "mmap-cache",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
 pub enum DirectionType {
     #[default]
     #[strum(serialize = "R")]
@@ -235,6 +620,11 @@ pub enum DirectionType {
 // ------------------------------------------------------------------------------------------------
 
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "mmap-cache",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
 pub struct Holiday {
     id: i32,
     date: NaiveDate,
@@ -254,6 +644,11 @@ impl Holiday {
 // ------------------------------------------------------------------------------------------------
 
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "mmap-cache",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
 pub struct ExchangeTimeAdministration {
     id: i32,
     stop_id: Option<i32>, // A None value means that the exchange time applies to all stops if there is no specific entry for the stop and the 2 administrations.
@@ -300,11 +695,37 @@ impl ExchangeTimeAdministration {
     }
 }
 
+#[cfg(feature = "mmap-cache")]
+impl ArchivedExchangeTimeAdministration {
+    // Getters/Setters
+
+    pub fn stop_id(&self) -> Option<i32> {
+        self.stop_id.as_ref().copied()
+    }
+
+    pub fn administration_1(&self) -> &str {
+        &self.administration_1
+    }
+
+    pub fn administration_2(&self) -> &str {
+        &self.administration_2
+    }
+
+    pub fn duration(&self) -> i16 {
+        self.duration
+    }
+}
+
 // ------------------------------------------------------------------------------------------------
 // --- ExchangeTimeJourney
 // ------------------------------------------------------------------------------------------------
 
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "mmap-cache",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
 pub struct ExchangeTimeJourney {
     id: i32,
     stop_id: i32,
@@ -361,11 +782,41 @@ impl ExchangeTimeJourney {
     }
 }
 
+#[cfg(feature = "mmap-cache")]
+impl ArchivedExchangeTimeJourney {
+    // Getters/Setters
+
+    pub fn stop_id(&self) -> i32 {
+        self.stop_id
+    }
+
+    pub fn journey_id_1(&self) -> i32 {
+        self.journey_id_1
+    }
+
+    pub fn journey_id_2(&self) -> i32 {
+        self.journey_id_2
+    }
+
+    pub fn duration(&self) -> i16 {
+        self.duration
+    }
+
+    pub fn bit_field_id(&self) -> Option<i32> {
+        self.bit_field_id.as_ref().copied()
+    }
+}
+
 // ------------------------------------------------------------------------------------------------
 // --- ExchangeTimeLine
 // ------------------------------------------------------------------------------------------------
 
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "mmap-cache",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
 pub struct ExchangeTimeLine {
     id: i32,
     stop_id: Option<i32>,
@@ -378,6 +829,11 @@ pub struct ExchangeTimeLine {
 impl_Model!(ExchangeTimeLine);
 
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "mmap-cache",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
 pub(crate) struct LineInfo {
     administration: String,
     transport_type_id: i32,
@@ -419,6 +875,21 @@ impl ExchangeTimeLine {
             is_guaranteed,
         }
     }
+
+    // Getters/Setters
+
+    pub fn stop_id(&self) -> Option<i32> {
+        self.stop_id
+    }
+}
+
+#[cfg(feature = "mmap-cache")]
+impl ArchivedExchangeTimeLine {
+    // Getters/Setters
+
+    pub fn stop_id(&self) -> Option<i32> {
+        self.stop_id.as_ref().copied()
+    }
 }
 
 // ------------------------------------------------------------------------------------------------
@@ -426,6 +897,11 @@ impl ExchangeTimeLine {
 // ------------------------------------------------------------------------------------------------
 
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "mmap-cache",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
 pub struct InformationText {
     id: i32,
     content: FxHashMap<Language, String>,
@@ -453,6 +929,11 @@ impl InformationText {
 // ------------------------------------------------------------------------------------------------
 
 #[derive(Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "mmap-cache",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
 pub struct Journey {
     id: i32,
     administration: String,
@@ -462,6 +943,13 @@ pub struct Journey {
 
 impl_Model!(Journey);
 
+// (route entry, arrival date and time, departure date and time)
+type AbsoluteRouteEntry<'a> = (
+    &'a JourneyRouteEntry,
+    Option<DateTime<FixedOffset>>,
+    Option<DateTime<FixedOffset>>,
+);
+
 impl Journey {
     pub fn new(id: i32, administration: String) -> Self {
         Self {
@@ -478,7 +966,7 @@ impl Journey {
         &self.administration
     }
 
-    fn metadata(&self) -> &FxHashMap<JourneyMetadataType, Vec<JourneyMetadataEntry>> {
+    pub fn metadata(&self) -> &FxHashMap<JourneyMetadataType, Vec<JourneyMetadataEntry>> {
         &self.metadata
     }
 
@@ -488,45 +976,121 @@ impl Journey {
 
     // Functions
 
-    pub fn add_metadata_entry(&mut self, k: JourneyMetadataType, v: JourneyMetadataEntry) {
-        self.metadata.entry(k).or_default().push(v);
+    pub fn add_metadata_entry(&mut self, entry: JourneyMetadataEntry) {
+        self.metadata
+            .entry(entry.metadata_type())
+            .or_default()
+            .push(entry);
     }
 
     pub fn add_route_entry(&mut self, entry: JourneyRouteEntry) {
         self.route.push(entry);
     }
 
+    /// The entries of the given type, in the order of the HRDF file.
+    pub fn metadata_entries(&self, k: JourneyMetadataType) -> &[JourneyMetadataEntry] {
+        JourneyData::metadata_entries(self, k)
+    }
+
     pub fn bit_field_id(&self) -> Option<i32> {
-        // unwrap: There will always be a BitField entry.
-        let entry = &self.metadata().get(&JourneyMetadataType::BitField).unwrap()[0];
-        entry.bit_field_id
+        JourneyData::bit_field_id(self)
     }
 
-    pub fn transport_type_id(&self) -> i32 {
-        // unwrap: There will always be a TransportType entry.
-        let entry = &self
-            .metadata()
-            .get(&JourneyMetadataType::TransportType)
-            .unwrap()[0];
-        // unwrap: It's guaranteed to have value here.
-        entry.resource_id.unwrap()
+    /// The lines designated by their name instead of their id are not included.
+    pub fn line_ids(&self) -> Vec<i32> {
+        JourneyData::line_ids(self)
     }
 
-    pub fn transport_type<'a>(&'a self, data_storage: &'a DataStorage) -> &TransportType {
+    pub fn direction_ids(&self) -> Vec<i32> {
+        JourneyData::direction_ids(self)
+    }
+
+    pub fn transport_type_id(&self) -> i32 {
+        JourneyData::transport_type_id(self)
+    }
+
+    /// The attributes (see Attribute) that apply to at least a part of the section between the two stops.
+    pub fn attributes_between(&self, from_stop_id: i32, to_stop_id: i32) -> Vec<i32> {
+        JourneyData::attributes_between(self, from_stop_id, to_stop_id)
+    }
+
+    /// Same as attributes_between, but only the attributes that apply on the operating date are returned.
+    pub fn attributes_between_on(
+        &self,
+        from_stop_id: i32,
+        to_stop_id: i32,
+        operating_date: NaiveDate,
+        data_storage: &DataStorage,
+    ) -> Vec<i32> {
+        let Some(bit_field_ids) = data_storage.bit_fields_by_day().get(&operating_date) else {
+            return Vec::new();
+        };
+
+        self.attributes_between_where(from_stop_id, to_stop_id, |bit_field_id| {
+            bit_field_ids.contains(&bit_field_id.unwrap_or(0))
+        })
+    }
+
+    /// The line of the journey at the stop.<br>
+    /// When two sections meet at the stop, the one starting there is used.
+    pub fn line_at(&self, stop_id: i32) -> Option<&LineReference> {
+        JourneyData::line_at(self, stop_id)
+    }
+
+    /// The direction type and direction (see Direction) of the journey at the stop.<br>
+    /// When two sections meet at the stop, the one starting there is used.
+    pub fn direction_at(&self, stop_id: i32) -> Option<(DirectionType, Option<i32>)> {
+        JourneyData::direction_at(self, stop_id)
+    }
+
+    /// The codes and information texts (see InformationText) that apply to at least a part of the section between the two stops on the operating date.
+    pub fn information_texts_on(
+        &self,
+        from_stop_id: i32,
+        to_stop_id: i32,
+        operating_date: NaiveDate,
+        data_storage: &DataStorage,
+    ) -> Vec<(&str, i32)> {
+        let Some(bit_field_ids) = data_storage.bit_fields_by_day().get(&operating_date) else {
+            return Vec::new();
+        };
+
+        self.information_texts_between_where(from_stop_id, to_stop_id, |bit_field_id| {
+            bit_field_ids.contains(&bit_field_id.unwrap_or(0))
+        })
+    }
+
+    /// The minimum time to board the journey at the stop, in minutes.
+    pub fn exchange_time_boarding_at(&self, stop_id: i32) -> Option<i16> {
+        JourneyData::exchange_time_boarding_at(self, stop_id)
+    }
+
+    /// The minimum time to alight from the journey at the stop, in minutes.
+    pub fn exchange_time_disembarking_at(&self, stop_id: i32) -> Option<i16> {
+        JourneyData::exchange_time_disembarking_at(self, stop_id)
+    }
+
+    pub fn transport_type<'a>(&'a self, data_storage: &'a DataStorage) -> &'a TransportType {
         data_storage
             .transport_types()
             .find(self.transport_type_id())
             .unwrap_or_else(|| panic!("Transport type {:?} not found.", self.transport_type_id()))
     }
 
+    /// The border points crossed by the journey, in the order of its route.
+    pub fn border_points<'a>(&self, data_storage: &'a DataStorage) -> Vec<&'a BorderPoint> {
+        self.route()
+            .iter()
+            .filter_map(|route_entry| data_storage.border_points().find(route_entry.stop_id()))
+            .collect()
+    }
+
     pub fn first_stop_id(&self) -> i32 {
-        // unwrap: The route always contains at least 2 entries.
-        self.route.first().unwrap().stop_id()
+        JourneyData::first_stop_id(self)
     }
 
     pub fn last_stop_id(&self) -> i32 {
-        // unwrap: The route always contains at least 2 entries.
-        self.route.last().unwrap().stop_id()
+        JourneyData::last_stop_id(self)
     }
 
     pub fn is_last_stop(&self, stop_id: i32, ignore_loop: bool) -> bool {
@@ -565,28 +1129,44 @@ impl Journey {
     /// unwrap: Do not call this function if the stop is not part of the route.
     /// unwrap: Do not call this function if the stop has no departure time (only the last stop has no departure time).
     pub fn departure_time_of(&self, stop_id: i32) -> (NaiveTime, bool) {
-        let route = self.route();
-        let index = route
-            .iter()
-            .position(|route_entry| route_entry.stop_id() == stop_id)
-            .unwrap();
-        let departure_time = route[index].departure_time().unwrap();
+        let (departure_time, day_offset) = self.departure_time_and_day_offset_of(stop_id);
 
         (
             departure_time,
-            // The departure time is on the next day if this evaluates to true.
-            departure_time < route.first().unwrap().departure_time().unwrap(),
+            // The departure time is on a later day than the first departure if this evaluates to true.
+            day_offset > self.first_day_offset(),
         )
     }
 
+    /// The day offset is the number of days between the operating day and the departure.
+    /// unwrap: Do not call this function if the stop is not part of the route.
+    /// unwrap: Do not call this function if the stop has no departure time (only the last stop has no departure time).
+    pub fn departure_time_and_day_offset_of(&self, stop_id: i32) -> (NaiveTime, i32) {
+        self.route()
+            .iter()
+            .find(|route_entry| route_entry.stop_id() == stop_id)
+            .and_then(|route_entry| route_entry.departure_time_and_day_offset())
+            .unwrap()
+    }
+
     /// The date must correspond to the route's first entry.
     /// Do not call this function if the stop is not part of the route.
     /// Do not call this function if the stop has no departure time (only the last stop has no departure time).
     pub fn departure_at_of(&self, stop_id: i32, date: NaiveDate) -> NaiveDateTime {
-        match self.departure_time_of(stop_id) {
-            (departure_time, false) => NaiveDateTime::new(date, departure_time),
-            (departure_time, true) => NaiveDateTime::new(add_1_day(date), departure_time),
-        }
+        let operating_date = add_days(date, -self.first_day_offset());
+        self.departure_at_of_operating_day(stop_id, operating_date)
+    }
+
+    /// The operating date is the day on which the journey operates (see bit_field_id), the journey may start after midnight.
+    /// Do not call this function if the stop is not part of the route.
+    /// Do not call this function if the stop has no departure time (only the last stop has no departure time).
+    pub fn departure_at_of_operating_day(
+        &self,
+        stop_id: i32,
+        operating_date: NaiveDate,
+    ) -> NaiveDateTime {
+        let (departure_time, day_offset) = self.departure_time_and_day_offset_of(stop_id);
+        NaiveDateTime::new(add_days(operating_date, day_offset), departure_time)
     }
 
     /// The date must be associated with the origin_stop_id.
@@ -599,40 +1179,47 @@ impl Journey {
         is_departure_date: bool,
         origin_stop_id: i32,
     ) -> NaiveDateTime {
-        let (departure_time, is_next_day) = self.departure_time_of(stop_id);
-        let (_, origin_is_next_day) = if is_departure_date {
-            self.departure_time_of(origin_stop_id)
-        } else {
-            self.arrival_time_of(origin_stop_id)
-        };
-
-        match (is_next_day, origin_is_next_day) {
-            (true, false) => NaiveDateTime::new(add_1_day(date), departure_time),
-            (false, true) => NaiveDateTime::new(sub_1_day(date), departure_time),
-            _ => NaiveDateTime::new(date, departure_time),
-        }
+        let operating_date = self.operating_date_of(date, is_departure_date, origin_stop_id);
+        self.departure_at_of_operating_day(stop_id, operating_date)
     }
 
     /// unwrap: Do not call this function if the stop is not part of the route.
     /// unwrap: Do not call this function if the stop has no arrival time (only the first stop has no arrival time).
     pub fn arrival_time_of(&self, stop_id: i32) -> (NaiveTime, bool) {
-        let route = self.route();
-        let index = route
-            .iter()
-            // The first route entry has no arrival time.
-            .skip(1)
-            .position(|route_entry| route_entry.stop_id() == stop_id)
-            .map(|i| i + 1)
-            .unwrap();
-        let arrival_time = route[index].arrival_time().unwrap();
+        let (arrival_time, day_offset) = self.arrival_time_and_day_offset_of(stop_id);
 
         (
             arrival_time,
-            // The arrival time is on the next day if this evaluates to true.
-            arrival_time < route.first().unwrap().departure_time().unwrap(),
+            // The arrival time is on a later day than the first departure if this evaluates to true.
+            day_offset > self.first_day_offset(),
         )
     }
 
+    /// The day offset is the number of days between the operating day and the arrival.
+    /// unwrap: Do not call this function if the stop is not part of the route.
+    /// unwrap: Do not call this function if the stop has no arrival time (only the first stop has no arrival time).
+    pub fn arrival_time_and_day_offset_of(&self, stop_id: i32) -> (NaiveTime, i32) {
+        self.route()
+            .iter()
+            // The first route entry has no arrival time.
+            .skip(1)
+            .find(|route_entry| route_entry.stop_id() == stop_id)
+            .and_then(|route_entry| route_entry.arrival_time_and_day_offset())
+            .unwrap()
+    }
+
+    /// The operating date is the day on which the journey operates (see bit_field_id), the journey may start after midnight.
+    /// Do not call this function if the stop is not part of the route.
+    /// Do not call this function if the stop has no arrival time (only the first stop has no arrival time).
+    pub fn arrival_at_of_operating_day(
+        &self,
+        stop_id: i32,
+        operating_date: NaiveDate,
+    ) -> NaiveDateTime {
+        let (arrival_time, day_offset) = self.arrival_time_and_day_offset_of(stop_id);
+        NaiveDateTime::new(add_days(operating_date, day_offset), arrival_time)
+    }
+
     /// The date must be associated with the origin_stop_id.
     pub fn arrival_at_of_with_origin(
         &self,
@@ -642,18 +1229,95 @@ impl Journey {
         is_departure_date: bool,
         origin_stop_id: i32,
     ) -> NaiveDateTime {
-        let (arrival_time, is_next_day) = self.arrival_time_of(stop_id);
-        let (_, origin_is_next_day) = if is_departure_date {
-            self.departure_time_of(origin_stop_id)
+        let operating_date = self.operating_date_of(date, is_departure_date, origin_stop_id);
+        self.arrival_at_of_operating_day(stop_id, operating_date)
+    }
+
+    // The day offset of the first departure, it's greater than 0 if the journey starts after midnight of the operating day.
+    fn first_day_offset(&self) -> i32 {
+        // unwrap: The route always contains at least 2 entries and the first one has a departure time.
+        let (_, day_offset) = self.route[0].departure_time_and_day_offset().unwrap();
+        day_offset
+    }
+
+    // The operating date of the journey, knowing the date of the departure or arrival at the origin stop.
+    fn operating_date_of(
+        &self,
+        date: NaiveDate,
+        is_departure_date: bool,
+        origin_stop_id: i32,
+    ) -> NaiveDate {
+        let (_, day_offset) = if is_departure_date {
+            self.departure_time_and_day_offset_of(origin_stop_id)
         } else {
-            self.arrival_time_of(origin_stop_id)
+            self.arrival_time_and_day_offset_of(origin_stop_id)
         };
+        add_days(date, -day_offset)
+    }
 
-        match (is_next_day, origin_is_next_day) {
-            (true, false) => NaiveDateTime::new(add_1_day(date), arrival_time),
-            (false, true) => NaiveDateTime::new(sub_1_day(date), arrival_time),
-            _ => NaiveDateTime::new(date, arrival_time),
-        }
+    /// Same as departure_at_of, but the local time is converted with the time difference of the stop (see ZEITVS).<br>
+    /// The times around the daylight saving time switches are resolved by following the route.
+    /// The date must correspond to the route's first entry.
+    /// Returns None if the stop isn't part of the route, if it has no departure time (only the last stop has no departure time)
+    /// or if the time difference of a stop of the route is unknown.
+    pub fn departure_at_of_with_time_zone(
+        &self,
+        stop_id: i32,
+        date: NaiveDate,
+        data_storage: &DataStorage,
+    ) -> Option<DateTime<FixedOffset>> {
+        self.absolute_times(date, data_storage)?
+            .into_iter()
+            .find(|(route_entry, _, _)| route_entry.stop_id() == stop_id)
+            .and_then(|(_, _, departure_at)| departure_at)
+    }
+
+    /// Same as departure_at_of_with_time_zone, but for the arrival.
+    /// The date must correspond to the route's first entry.
+    /// Returns None if the stop isn't part of the route, if it has no arrival time (only the first stop has no arrival time)
+    /// or if the time difference of a stop of the route is unknown.
+    pub fn arrival_at_of_with_time_zone(
+        &self,
+        stop_id: i32,
+        date: NaiveDate,
+        data_storage: &DataStorage,
+    ) -> Option<DateTime<FixedOffset>> {
+        self.absolute_times(date, data_storage)?
+            .into_iter()
+            // The first route entry has no arrival time.
+            .skip(1)
+            .find(|(route_entry, _, _)| route_entry.stop_id() == stop_id)
+            .and_then(|(_, arrival_at, _)| arrival_at)
+    }
+
+    /// The arrival and departure times of each route entry.
+    /// Returns None if the time difference of a stop is unknown, as the following times cannot be resolved either.
+    fn absolute_times(
+        &self,
+        date: NaiveDate,
+        data_storage: &DataStorage,
+    ) -> Option<Vec<AbsoluteRouteEntry<'_>>> {
+        let operating_date = add_days(date, -self.first_day_offset());
+        let mut previous = None;
+
+        self.route
+            .iter()
+            .map(|route_entry| {
+                let time_difference = data_storage.time_difference_of(route_entry.stop_id())?;
+
+                let mut resolve = |local: Option<NaiveDateTime>| {
+                    local.map(|local| {
+                        let at = time_difference.at_local_not_before(local, previous);
+                        previous = Some(at);
+                        at
+                    })
+                };
+
+                let arrival_at = resolve(route_entry.arrival_at(operating_date));
+                let departure_at = resolve(route_entry.departure_at(operating_date));
+                Some((route_entry, arrival_at, departure_at))
+            })
+            .collect()
     }
 
     /// Excluding departure stop.
@@ -682,6 +1346,185 @@ impl Journey {
 
         result
     }
+
+    /// Same as route_section, but None is returned if the passengers can't board at the departure stop
+    /// or can't alight at the arrival stop (see JourneyRouteEntry::can_board and JourneyRouteEntry::can_alight).
+    pub fn allowed_route_section(
+        &self,
+        departure_stop_id: i32,
+        arrival_stop_id: i32,
+    ) -> Option<Vec<&JourneyRouteEntry>> {
+        let departure_entry = self
+            .route()
+            .iter()
+            .find(|route_entry| route_entry.stop_id() == departure_stop_id)?;
+        if !departure_entry.can_board() {
+            return None;
+        }
+
+        let result = self.route_section(departure_stop_id, arrival_stop_id);
+        let arrival_entry = result.last()?;
+        (arrival_entry.stop_id() == arrival_stop_id && arrival_entry.can_alight()).then_some(result)
+    }
+}
+
+impl JourneyData for Journey {
+    type RouteEntry = JourneyRouteEntry;
+    type MetadataEntry = JourneyMetadataEntry;
+
+    fn route(&self) -> &[JourneyRouteEntry] {
+        &self.route
+    }
+
+    fn metadata_entries(&self, k: JourneyMetadataType) -> &[JourneyMetadataEntry] {
+        self.metadata.get(&k).map_or(&[], |entries| entries)
+    }
+}
+
+#[cfg(feature = "mmap-cache")]
+impl ArchivedJourney {
+    // Getters/Setters
+
+    pub fn administration(&self) -> &str {
+        &self.administration
+    }
+
+    pub fn route(&self) -> &[ArchivedJourneyRouteEntry] {
+        &self.route
+    }
+
+    // Functions
+
+    /// The entries of the given type, in the order of the HRDF file.
+    pub fn metadata_entries(&self, k: JourneyMetadataType) -> &[ArchivedJourneyMetadataEntry] {
+        JourneyData::metadata_entries(self, k)
+    }
+
+    pub fn bit_field_id(&self) -> Option<i32> {
+        JourneyData::bit_field_id(self)
+    }
+
+    /// The lines designated by their name instead of their id are not included.
+    pub fn line_ids(&self) -> Vec<i32> {
+        JourneyData::line_ids(self)
+    }
+
+    pub fn direction_ids(&self) -> Vec<i32> {
+        JourneyData::direction_ids(self)
+    }
+
+    pub fn transport_type_id(&self) -> i32 {
+        JourneyData::transport_type_id(self)
+    }
+
+    /// The attributes (see Attribute) that apply to at least a part of the section between the two stops.
+    pub fn attributes_between(&self, from_stop_id: i32, to_stop_id: i32) -> Vec<i32> {
+        JourneyData::attributes_between(self, from_stop_id, to_stop_id)
+    }
+
+    /// Same as attributes_between, but only the attributes that apply on the operating date are returned.
+    pub fn attributes_between_on(
+        &self,
+        from_stop_id: i32,
+        to_stop_id: i32,
+        operating_date: NaiveDate,
+        data_storage: &ArchivedDataStorage,
+    ) -> Vec<i32> {
+        let Some(bit_field_ids) = archived_bit_field_ids_on(operating_date, data_storage) else {
+            return Vec::new();
+        };
+
+        self.attributes_between_where(from_stop_id, to_stop_id, |bit_field_id| {
+            bit_field_ids.contains(&bit_field_id.unwrap_or(0))
+        })
+    }
+
+    /// The line of the journey at the stop.<br>
+    /// When two sections meet at the stop, the one starting there is used.
+    pub fn line_at(&self, stop_id: i32) -> Option<&ArchivedLineReference> {
+        JourneyData::line_at(self, stop_id)
+    }
+
+    /// The direction type and direction (see Direction) of the journey at the stop.<br>
+    /// When two sections meet at the stop, the one starting there is used.
+    pub fn direction_at(&self, stop_id: i32) -> Option<(DirectionType, Option<i32>)> {
+        JourneyData::direction_at(self, stop_id)
+    }
+
+    /// The codes and information texts (see InformationText) that apply to at least a part of the section between the two stops on the operating date.
+    pub fn information_texts_on(
+        &self,
+        from_stop_id: i32,
+        to_stop_id: i32,
+        operating_date: NaiveDate,
+        data_storage: &ArchivedDataStorage,
+    ) -> Vec<(&str, i32)> {
+        let Some(bit_field_ids) = archived_bit_field_ids_on(operating_date, data_storage) else {
+            return Vec::new();
+        };
+
+        self.information_texts_between_where(from_stop_id, to_stop_id, |bit_field_id| {
+            bit_field_ids.contains(&bit_field_id.unwrap_or(0))
+        })
+    }
+
+    /// The minimum time to board the journey at the stop, in minutes.
+    pub fn exchange_time_boarding_at(&self, stop_id: i32) -> Option<i16> {
+        JourneyData::exchange_time_boarding_at(self, stop_id)
+    }
+
+    /// The minimum time to alight from the journey at the stop, in minutes.
+    pub fn exchange_time_disembarking_at(&self, stop_id: i32) -> Option<i16> {
+        JourneyData::exchange_time_disembarking_at(self, stop_id)
+    }
+
+    /// The border points crossed by the journey, in the order of its route.
+    pub fn border_points<'a>(
+        &self,
+        data_storage: &'a ArchivedDataStorage,
+    ) -> Vec<&'a ArchivedBorderPoint> {
+        self.route
+            .iter()
+            .filter_map(|route_entry| data_storage.border_points().find(route_entry.stop_id()))
+            .collect()
+    }
+
+    pub fn first_stop_id(&self) -> i32 {
+        JourneyData::first_stop_id(self)
+    }
+
+    pub fn last_stop_id(&self) -> i32 {
+        JourneyData::last_stop_id(self)
+    }
+}
+
+// The archived dates can't be borrowed as NaiveDate, they are compared with it instead.
+#[cfg(feature = "mmap-cache")]
+fn archived_bit_field_ids_on(
+    operating_date: NaiveDate,
+    data_storage: &ArchivedDataStorage,
+) -> Option<&ArchivedHashSet<i32>> {
+    data_storage
+        .bit_fields_by_day()
+        .get_with(&operating_date, |date, operating_date| {
+            date == operating_date
+        })
+}
+
+#[cfg(feature = "mmap-cache")]
+impl JourneyData for ArchivedJourney {
+    type RouteEntry = ArchivedJourneyRouteEntry;
+    type MetadataEntry = ArchivedJourneyMetadataEntry;
+
+    fn route(&self) -> &[ArchivedJourneyRouteEntry] {
+        &self.route
+    }
+
+    fn metadata_entries(&self, k: JourneyMetadataType) -> &[ArchivedJourneyMetadataEntry] {
+        self.metadata
+            .get(&ArchivedJourneyMetadataType::from(k))
+            .map_or(&[], |entries| entries)
+    }
 }
 
 // ------------------------------------------------------------------------------------------------
@@ -689,6 +1532,14 @@ impl Journey {
 // ------------------------------------------------------------------------------------------------
 
 #[derive(Clone, Copy, Debug, Default, Display, Eq, Hash, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "mmap-cache",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes),
+    archive_attr(derive(Hash, PartialEq, Eq)),
+    // Same representation as the archived enum, so that both have the same hash when used as keys.
+    repr(u8)
+)]
 pub enum JourneyMetadataType {
     #[default]
     Attribute,
@@ -701,45 +1552,361 @@ pub enum JourneyMetadataType {
     TransportType,
 }
 
+#[cfg(feature = "mmap-cache")]
+impl From<JourneyMetadataType> for ArchivedJourneyMetadataType {
+    fn from(value: JourneyMetadataType) -> Self {
+        match value {
+            JourneyMetadataType::Attribute => Self::Attribute,
+            JourneyMetadataType::BitField => Self::BitField,
+            JourneyMetadataType::Direction => Self::Direction,
+            JourneyMetadataType::InformationText => Self::InformationText,
+            JourneyMetadataType::Line => Self::Line,
+            JourneyMetadataType::ExchangeTimeBoarding => Self::ExchangeTimeBoarding,
+            JourneyMetadataType::ExchangeTimeDisembarking => Self::ExchangeTimeDisembarking,
+            JourneyMetadataType::TransportType => Self::TransportType,
+        }
+    }
+}
+
 // ------------------------------------------------------------------------------------------------
 // --- JourneyMetadataEntry
 // ------------------------------------------------------------------------------------------------
 
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "mmap-cache",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
 pub struct JourneyMetadataEntry {
+    // The entry applies to the whole route if the stops are not specified.
     from_stop_id: Option<i32>,
     until_stop_id: Option<i32>,
-    resource_id: Option<i32>,
+    // The entry applies every day if the bit field is not specified.
     bit_field_id: Option<i32>,
-    departure_time: Option<NaiveTime>,
-    arrival_time: Option<NaiveTime>,
-    extra_field_1: Option<String>,
-    extra_field_2: Option<i32>,
+    // The departure time at from_stop_id and the arrival time at until_stop_id, used when the route passes several times through a stop.
+    // In minutes since the start of the operating day, as JourneyRouteEntry::departure_minutes/arrival_minutes.
+    departure_minutes: Option<i32>,
+    arrival_minutes: Option<i32>,
+    value: JourneyMetadata,
 }
 
 impl JourneyMetadataEntry {
-    #[allow(clippy::too_many_arguments)]
     pub fn new(
         from_stop_id: Option<i32>,
         until_stop_id: Option<i32>,
-        resource_id: Option<i32>,
         bit_field_id: Option<i32>,
-        departure_time: Option<NaiveTime>,
-        arrival_time: Option<NaiveTime>,
-        extra_field_1: Option<String>,
-        extra_field_2: Option<i32>,
+        departure_minutes: Option<i32>,
+        arrival_minutes: Option<i32>,
+        value: JourneyMetadata,
     ) -> Self {
         Self {
             from_stop_id,
             until_stop_id,
-            resource_id,
             bit_field_id,
-            departure_time,
-            arrival_time,
-            extra_field_1,
-            extra_field_2,
+            departure_minutes,
+            arrival_minutes,
+            value,
         }
     }
+
+    // Getters/Setters
+
+    pub fn from_stop_id(&self) -> Option<i32> {
+        self.from_stop_id
+    }
+
+    pub fn until_stop_id(&self) -> Option<i32> {
+        self.until_stop_id
+    }
+
+    pub fn bit_field_id(&self) -> Option<i32> {
+        self.bit_field_id
+    }
+
+    /// The number of minutes since the start of the operating day, it can exceed 24 hours.
+    pub fn departure_minutes(&self) -> Option<i32> {
+        self.departure_minutes
+    }
+
+    /// The number of minutes since the start of the operating day, it can exceed 24 hours.
+    pub fn arrival_minutes(&self) -> Option<i32> {
+        self.arrival_minutes
+    }
+
+    pub fn value(&self) -> &JourneyMetadata {
+        &self.value
+    }
+
+    // Functions
+
+    pub fn metadata_type(&self) -> JourneyMetadataType {
+        self.value.metadata_type()
+    }
+}
+
+#[cfg(feature = "mmap-cache")]
+impl ArchivedJourneyMetadataEntry {
+    // Getters/Setters
+
+    pub fn from_stop_id(&self) -> Option<i32> {
+        self.from_stop_id.as_ref().copied()
+    }
+
+    pub fn until_stop_id(&self) -> Option<i32> {
+        self.until_stop_id.as_ref().copied()
+    }
+
+    pub fn bit_field_id(&self) -> Option<i32> {
+        self.bit_field_id.as_ref().copied()
+    }
+
+    pub fn departure_minutes(&self) -> Option<i32> {
+        self.departure_minutes.as_ref().copied()
+    }
+
+    pub fn arrival_minutes(&self) -> Option<i32> {
+        self.arrival_minutes.as_ref().copied()
+    }
+
+    pub fn value(&self) -> &ArchivedJourneyMetadata {
+        &self.value
+    }
+}
+
+impl JourneyMetadataEntryData for JourneyMetadataEntry {
+    type Line = LineReference;
+
+    fn from_stop_id(&self) -> Option<i32> {
+        self.from_stop_id
+    }
+
+    fn until_stop_id(&self) -> Option<i32> {
+        self.until_stop_id
+    }
+
+    fn bit_field_id(&self) -> Option<i32> {
+        self.bit_field_id
+    }
+
+    fn departure_minutes(&self) -> Option<i32> {
+        self.departure_minutes
+    }
+
+    fn arrival_minutes(&self) -> Option<i32> {
+        self.arrival_minutes
+    }
+
+    fn attribute_id(&self) -> Option<i32> {
+        match self.value {
+            JourneyMetadata::Attribute { attribute_id } => Some(attribute_id),
+            _ => None,
+        }
+    }
+
+    fn direction(&self) -> Option<(DirectionType, Option<i32>)> {
+        match self.value {
+            JourneyMetadata::Direction {
+                direction_type,
+                direction_id,
+            } => Some((direction_type, direction_id)),
+            _ => None,
+        }
+    }
+
+    fn exchange_time(&self) -> Option<i16> {
+        match self.value {
+            JourneyMetadata::ExchangeTimeBoarding { exchange_time }
+            | JourneyMetadata::ExchangeTimeDisembarking { exchange_time } => Some(exchange_time),
+            _ => None,
+        }
+    }
+
+    fn information_text(&self) -> Option<(&str, i32)> {
+        match &self.value {
+            JourneyMetadata::InformationText {
+                code,
+                information_text_id,
+            } => Some((code, *information_text_id)),
+            _ => None,
+        }
+    }
+
+    fn line(&self) -> Option<&LineReference> {
+        match &self.value {
+            JourneyMetadata::Line(line) => Some(line),
+            _ => None,
+        }
+    }
+
+    fn line_id(&self) -> Option<i32> {
+        match self.line()? {
+            LineReference::Id(line_id) => Some(*line_id),
+            LineReference::Designation(_) => None,
+        }
+    }
+
+    fn transport_type_id(&self) -> Option<i32> {
+        match self.value {
+            JourneyMetadata::TransportType { transport_type_id } => Some(transport_type_id),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "mmap-cache")]
+impl JourneyMetadataEntryData for ArchivedJourneyMetadataEntry {
+    type Line = ArchivedLineReference;
+
+    fn from_stop_id(&self) -> Option<i32> {
+        self.from_stop_id.as_ref().copied()
+    }
+
+    fn until_stop_id(&self) -> Option<i32> {
+        self.until_stop_id.as_ref().copied()
+    }
+
+    fn bit_field_id(&self) -> Option<i32> {
+        self.bit_field_id.as_ref().copied()
+    }
+
+    fn departure_minutes(&self) -> Option<i32> {
+        self.departure_minutes.as_ref().copied()
+    }
+
+    fn arrival_minutes(&self) -> Option<i32> {
+        self.arrival_minutes.as_ref().copied()
+    }
+
+    fn attribute_id(&self) -> Option<i32> {
+        match self.value {
+            ArchivedJourneyMetadata::Attribute { attribute_id } => Some(attribute_id),
+            _ => None,
+        }
+    }
+
+    fn direction(&self) -> Option<(DirectionType, Option<i32>)> {
+        match &self.value {
+            ArchivedJourneyMetadata::Direction {
+                direction_type,
+                direction_id,
+            } => Some((unarchive(direction_type), direction_id.as_ref().copied())),
+            _ => None,
+        }
+    }
+
+    fn exchange_time(&self) -> Option<i16> {
+        match self.value {
+            ArchivedJourneyMetadata::ExchangeTimeBoarding { exchange_time }
+            | ArchivedJourneyMetadata::ExchangeTimeDisembarking { exchange_time } => {
+                Some(exchange_time)
+            }
+            _ => None,
+        }
+    }
+
+    fn information_text(&self) -> Option<(&str, i32)> {
+        match &self.value {
+            ArchivedJourneyMetadata::InformationText {
+                code,
+                information_text_id,
+            } => Some((code, *information_text_id)),
+            _ => None,
+        }
+    }
+
+    fn line(&self) -> Option<&ArchivedLineReference> {
+        match &self.value {
+            ArchivedJourneyMetadata::Line(line) => Some(line),
+            _ => None,
+        }
+    }
+
+    fn line_id(&self) -> Option<i32> {
+        match self.line()? {
+            ArchivedLineReference::Id(line_id) => Some(*line_id),
+            ArchivedLineReference::Designation(_) => None,
+        }
+    }
+
+    fn transport_type_id(&self) -> Option<i32> {
+        match self.value {
+            ArchivedJourneyMetadata::TransportType { transport_type_id } => Some(transport_type_id),
+            _ => None,
+        }
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// --- JourneyMetadata
+// ------------------------------------------------------------------------------------------------
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "mmap-cache",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
+pub enum JourneyMetadata {
+    Attribute {
+        attribute_id: i32,
+    },
+    /// The operating days of the journey are given by the bit field of the entry.
+    BitField,
+    Direction {
+        direction_type: DirectionType,
+        // Not specified if the journey has no direction.
+        direction_id: Option<i32>,
+    },
+    InformationText {
+        code: String,
+        information_text_id: i32,
+    },
+    Line(LineReference),
+    /// The minimum time to board the journey, in minutes.
+    ExchangeTimeBoarding {
+        exchange_time: i16,
+    },
+    /// The minimum time to alight from the journey, in minutes.
+    ExchangeTimeDisembarking {
+        exchange_time: i16,
+    },
+    TransportType {
+        transport_type_id: i32,
+    },
+}
+
+impl JourneyMetadata {
+    // Functions
+
+    pub fn metadata_type(&self) -> JourneyMetadataType {
+        match self {
+            Self::Attribute { .. } => JourneyMetadataType::Attribute,
+            Self::BitField => JourneyMetadataType::BitField,
+            Self::Direction { .. } => JourneyMetadataType::Direction,
+            Self::InformationText { .. } => JourneyMetadataType::InformationText,
+            Self::Line(_) => JourneyMetadataType::Line,
+            Self::ExchangeTimeBoarding { .. } => JourneyMetadataType::ExchangeTimeBoarding,
+            Self::ExchangeTimeDisembarking { .. } => JourneyMetadataType::ExchangeTimeDisembarking,
+            Self::TransportType { .. } => JourneyMetadataType::TransportType,
+        }
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// --- LineReference
+// ------------------------------------------------------------------------------------------------
+
+/// A line is either referenced by its id (see Line) or only designated by its name.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "mmap-cache",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
+pub enum LineReference {
+    Id(i32),
+    Designation(String),
 }
 
 // ------------------------------------------------------------------------------------------------
@@ -747,10 +1914,21 @@ impl JourneyMetadataEntry {
 // ------------------------------------------------------------------------------------------------
 
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "mmap-cache",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
 pub struct JourneyRouteEntry {
     stop_id: i32,
     arrival_time: Option<NaiveTime>,
     departure_time: Option<NaiveTime>,
+    // Number of days between the operating day and the arrival/departure, e.g. 1 for 25:30.
+    arrival_day_offset: i32,
+    departure_day_offset: i32,
+    // False if the time is negative in FPLAN.
+    alighting_allowed: bool,
+    boarding_allowed: bool,
 }
 
 impl JourneyRouteEntry {
@@ -763,6 +1941,10 @@ impl JourneyRouteEntry {
             stop_id,
             arrival_time,
             departure_time,
+            arrival_day_offset: 0,
+            departure_day_offset: 0,
+            alighting_allowed: true,
+            boarding_allowed: true,
         }
     }
 
@@ -772,17 +1954,75 @@ impl JourneyRouteEntry {
         self.stop_id
     }
 
+    /// The time of day, without the day offset (see arrival_time_and_day_offset).
     pub fn arrival_time(&self) -> &Option<NaiveTime> {
         &self.arrival_time
     }
 
+    /// The time of day, without the day offset (see departure_time_and_day_offset).
     pub fn departure_time(&self) -> &Option<NaiveTime> {
         &self.departure_time
     }
 
+    pub fn set_arrival_day_offset(&mut self, value: i32) {
+        self.arrival_day_offset = value;
+    }
+
+    pub fn set_departure_day_offset(&mut self, value: i32) {
+        self.departure_day_offset = value;
+    }
+
+    pub fn set_alighting_allowed(&mut self, value: bool) {
+        self.alighting_allowed = value;
+    }
+
+    pub fn set_boarding_allowed(&mut self, value: bool) {
+        self.boarding_allowed = value;
+    }
+
     // Functions
 
-    pub fn stop<'a>(&'a self, data_storage: &'a DataStorage) -> &Stop {
+    /// False if the journey doesn't stop to let passengers alight (negative arrival time in FPLAN) or if it starts at this stop.
+    pub fn can_alight(&self) -> bool {
+        JourneyRouteEntryData::can_alight(self)
+    }
+
+    /// False if the journey doesn't stop to let passengers board (negative departure time in FPLAN) or if it ends at this stop.
+    pub fn can_board(&self) -> bool {
+        JourneyRouteEntryData::can_board(self)
+    }
+
+    /// The day offset is the number of days between the operating day and the arrival.
+    pub fn arrival_time_and_day_offset(&self) -> Option<(NaiveTime, i32)> {
+        JourneyRouteEntryData::arrival_time_and_day_offset(self)
+    }
+
+    /// The day offset is the number of days between the operating day and the departure.
+    pub fn departure_time_and_day_offset(&self) -> Option<(NaiveTime, i32)> {
+        JourneyRouteEntryData::departure_time_and_day_offset(self)
+    }
+
+    /// The number of minutes since the start of the operating day, it can exceed 24 hours.
+    pub fn arrival_minutes(&self) -> Option<i32> {
+        JourneyRouteEntryData::arrival_minutes(self)
+    }
+
+    /// The number of minutes since the start of the operating day, it can exceed 24 hours.
+    pub fn departure_minutes(&self) -> Option<i32> {
+        JourneyRouteEntryData::departure_minutes(self)
+    }
+
+    /// The operating date is the day on which the journey operates (see Journey::bit_field_id).
+    pub fn arrival_at(&self, operating_date: NaiveDate) -> Option<NaiveDateTime> {
+        JourneyRouteEntryData::arrival_at(self, operating_date)
+    }
+
+    /// The operating date is the day on which the journey operates (see Journey::bit_field_id).
+    pub fn departure_at(&self, operating_date: NaiveDate) -> Option<NaiveDateTime> {
+        JourneyRouteEntryData::departure_at(self, operating_date)
+    }
+
+    pub fn stop<'a>(&'a self, data_storage: &'a DataStorage) -> &'a Stop {
         data_storage
             .stops()
             .find(self.stop_id())
@@ -790,11 +2030,128 @@ impl JourneyRouteEntry {
     }
 }
 
+impl JourneyRouteEntryData for JourneyRouteEntry {
+    fn stop_id(&self) -> i32 {
+        self.stop_id
+    }
+
+    fn arrival_time(&self) -> Option<NaiveTime> {
+        self.arrival_time
+    }
+
+    fn departure_time(&self) -> Option<NaiveTime> {
+        self.departure_time
+    }
+
+    fn arrival_day_offset(&self) -> i32 {
+        self.arrival_day_offset
+    }
+
+    fn departure_day_offset(&self) -> i32 {
+        self.departure_day_offset
+    }
+
+    fn alighting_allowed(&self) -> bool {
+        self.alighting_allowed
+    }
+
+    fn boarding_allowed(&self) -> bool {
+        self.boarding_allowed
+    }
+}
+
+#[cfg(feature = "mmap-cache")]
+impl ArchivedJourneyRouteEntry {
+    // Getters/Setters
+
+    pub fn stop_id(&self) -> i32 {
+        self.stop_id
+    }
+
+    pub fn arrival_time(&self) -> Option<NaiveTime> {
+        self.arrival_time.as_ref().map(unarchive)
+    }
+
+    pub fn departure_time(&self) -> Option<NaiveTime> {
+        self.departure_time.as_ref().map(unarchive)
+    }
+
+    // Functions
+
+    pub fn can_alight(&self) -> bool {
+        JourneyRouteEntryData::can_alight(self)
+    }
+
+    pub fn can_board(&self) -> bool {
+        JourneyRouteEntryData::can_board(self)
+    }
+
+    pub fn arrival_time_and_day_offset(&self) -> Option<(NaiveTime, i32)> {
+        JourneyRouteEntryData::arrival_time_and_day_offset(self)
+    }
+
+    pub fn departure_time_and_day_offset(&self) -> Option<(NaiveTime, i32)> {
+        JourneyRouteEntryData::departure_time_and_day_offset(self)
+    }
+
+    pub fn arrival_minutes(&self) -> Option<i32> {
+        JourneyRouteEntryData::arrival_minutes(self)
+    }
+
+    pub fn departure_minutes(&self) -> Option<i32> {
+        JourneyRouteEntryData::departure_minutes(self)
+    }
+
+    pub fn arrival_at(&self, operating_date: NaiveDate) -> Option<NaiveDateTime> {
+        JourneyRouteEntryData::arrival_at(self, operating_date)
+    }
+
+    pub fn departure_at(&self, operating_date: NaiveDate) -> Option<NaiveDateTime> {
+        JourneyRouteEntryData::departure_at(self, operating_date)
+    }
+}
+
+#[cfg(feature = "mmap-cache")]
+impl JourneyRouteEntryData for ArchivedJourneyRouteEntry {
+    fn stop_id(&self) -> i32 {
+        self.stop_id
+    }
+
+    fn arrival_time(&self) -> Option<NaiveTime> {
+        self.arrival_time.as_ref().map(unarchive)
+    }
+
+    fn departure_time(&self) -> Option<NaiveTime> {
+        self.departure_time.as_ref().map(unarchive)
+    }
+
+    fn arrival_day_offset(&self) -> i32 {
+        self.arrival_day_offset
+    }
+
+    fn departure_day_offset(&self) -> i32 {
+        self.departure_day_offset
+    }
+
+    fn alighting_allowed(&self) -> bool {
+        self.alighting_allowed
+    }
+
+    fn boarding_allowed(&self) -> bool {
+        self.boarding_allowed
+    }
+}
+
 // ------------------------------------------------------------------------------------------------
 // --- JourneyPlatform
 // ------------------------------------------------------------------------------------------------
 
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "mmap-cache",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
 pub struct JourneyPlatform {
     journey_id: i32,
     platform_id: i32,
@@ -816,6 +2173,45 @@ impl JourneyPlatform {
             bit_field_id,
         }
     }
+
+    // Getters/Setters
+
+    pub fn journey_id(&self) -> i32 {
+        self.journey_id
+    }
+
+    pub fn platform_id(&self) -> i32 {
+        self.platform_id
+    }
+
+    pub fn time(&self) -> Option<NaiveTime> {
+        self.time
+    }
+
+    pub fn bit_field_id(&self) -> Option<i32> {
+        self.bit_field_id
+    }
+}
+
+#[cfg(feature = "mmap-cache")]
+impl ArchivedJourneyPlatform {
+    // Getters/Setters
+
+    pub fn journey_id(&self) -> i32 {
+        self.journey_id
+    }
+
+    pub fn platform_id(&self) -> i32 {
+        self.platform_id
+    }
+
+    pub fn time(&self) -> Option<NaiveTime> {
+        self.time.as_ref().map(unarchive)
+    }
+
+    pub fn bit_field_id(&self) -> Option<i32> {
+        self.bit_field_id.as_ref().copied()
+    }
 }
 
 impl Model<JourneyPlatform> for JourneyPlatform {
@@ -833,6 +2229,14 @@ impl Model<JourneyPlatform> for JourneyPlatform {
 #[derive(
     Clone, Copy, Debug, Default, Display, Eq, Hash, PartialEq, EnumString, Serialize, Deserialize,
 )]
+#[cfg_attr(
+    feature = "mmap-cache",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes),
+    archive_attr(derive(Hash, PartialEq, Eq)),
+    // Same representation as the archived enum, so that both have the same hash when used as keys.
+    repr(u8)
+)]
 pub enum Language {
     #[default]
     #[strum(serialize = "deu")]
@@ -848,11 +2252,28 @@ pub enum Language {
     English,
 }
 
+#[cfg(feature = "mmap-cache")]
+impl From<Language> for ArchivedLanguage {
+    fn from(value: Language) -> Self {
+        match value {
+            Language::German => Self::German,
+            Language::French => Self::French,
+            Language::Italian => Self::Italian,
+            Language::English => Self::English,
+        }
+    }
+}
+
 // ------------------------------------------------------------------------------------------------
 // --- Line
 // ------------------------------------------------------------------------------------------------
 
 #[derive(Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "mmap-cache",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
 pub struct Line {
     id: i32,
     name: String,
@@ -894,6 +2315,11 @@ impl Line {
 // ------------------------------------------------------------------------------------------------
 
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "mmap-cache",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
 pub struct Platform {
     id: i32,
     name: String,
@@ -902,6 +2328,7 @@ pub struct Platform {
     sloid: String,
     lv95_coordinates: Coordinates,
     wgs84_coordinates: Coordinates,
+    altitude: Option<f64>,
 }
 
 impl_Model!(Platform);
@@ -916,22 +2343,89 @@ impl Platform {
             sloid: String::default(),
             lv95_coordinates: Coordinates::default(),
             wgs84_coordinates: Coordinates::default(),
+            altitude: None,
         }
     }
 
     // Getters/Setters
 
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn sectors(&self) -> Option<&str> {
+        self.sectors.as_deref()
+    }
+
+    pub fn stop_id(&self) -> i32 {
+        self.stop_id
+    }
+
+    pub fn sloid(&self) -> &str {
+        &self.sloid
+    }
+
     pub fn set_sloid(&mut self, value: String) {
         self.sloid = value;
     }
 
+    pub fn lv95_coordinates(&self) -> Coordinates {
+        self.lv95_coordinates
+    }
+
     pub fn set_lv95_coordinates(&mut self, value: Coordinates) {
         self.lv95_coordinates = value;
     }
 
+    pub fn wgs84_coordinates(&self) -> Coordinates {
+        self.wgs84_coordinates
+    }
+
     pub fn set_wgs84_coordinates(&mut self, value: Coordinates) {
         self.wgs84_coordinates = value;
     }
+
+    /// In meters, only provided by some HRDF versions.
+    pub fn altitude(&self) -> Option<f64> {
+        self.altitude
+    }
+
+    pub fn set_altitude(&mut self, value: Option<f64>) {
+        self.altitude = value;
+    }
+}
+
+#[cfg(feature = "mmap-cache")]
+impl ArchivedPlatform {
+    // Getters/Setters
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn sectors(&self) -> Option<&str> {
+        self.sectors.as_deref()
+    }
+
+    pub fn stop_id(&self) -> i32 {
+        self.stop_id
+    }
+
+    pub fn sloid(&self) -> &str {
+        &self.sloid
+    }
+
+    pub fn lv95_coordinates(&self) -> &ArchivedCoordinates {
+        &self.lv95_coordinates
+    }
+
+    pub fn wgs84_coordinates(&self) -> &ArchivedCoordinates {
+        &self.wgs84_coordinates
+    }
+
+    pub fn altitude(&self) -> Option<f64> {
+        self.altitude.as_ref().copied()
+    }
 }
 
 // ------------------------------------------------------------------------------------------------
@@ -939,6 +2433,11 @@ impl Platform {
 // ------------------------------------------------------------------------------------------------
 
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "mmap-cache",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
 pub struct Stop {
     id: i32,
     name: String,
@@ -1024,20 +2523,79 @@ impl Stop {
         self.exchange_time = value;
     }
 
+    pub fn restrictions(&self) -> i16 {
+        self.restrictions
+    }
+
     pub fn set_restrictions(&mut self, value: i16) {
         self.restrictions = value;
     }
 
+    pub fn sloid(&self) -> &str {
+        &self.sloid
+    }
+
     pub fn set_sloid(&mut self, value: String) {
         self.sloid = value;
     }
 
+    pub fn boarding_areas(&self) -> &Vec<String> {
+        &self.boarding_areas
+    }
+
     // Functions
 
     pub fn add_boarding_area(&mut self, value: String) {
         self.boarding_areas.push(value);
     }
 
+    pub fn clear_boarding_areas(&mut self) {
+        self.boarding_areas.clear();
+    }
+
+    pub fn can_be_used_as_exchange_point(&self) -> bool {
+        self.exchange_flag() != 0
+    }
+}
+
+#[cfg(feature = "mmap-cache")]
+impl ArchivedStop {
+    // Getters/Setters
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn lv95_coordinates(&self) -> Option<&ArchivedCoordinates> {
+        self.lv95_coordinates.as_ref()
+    }
+
+    pub fn wgs84_coordinates(&self) -> Option<&ArchivedCoordinates> {
+        self.wgs84_coordinates.as_ref()
+    }
+
+    pub fn exchange_flag(&self) -> i16 {
+        self.exchange_flag
+    }
+
+    pub fn exchange_time(&self) -> Option<(i16, i16)> {
+        self.exchange_time.as_ref().map(|value| (value.0, value.1))
+    }
+
+    pub fn restrictions(&self) -> i16 {
+        self.restrictions
+    }
+
+    pub fn sloid(&self) -> &str {
+        &self.sloid
+    }
+
+    pub fn boarding_areas(&self) -> impl Iterator<Item = &str> {
+        self.boarding_areas.iter().map(|value| value.as_str())
+    }
+
+    // Functions
+
     pub fn can_be_used_as_exchange_point(&self) -> bool {
         self.exchange_flag() != 0
     }
@@ -1048,6 +2606,11 @@ impl Stop {
 // ------------------------------------------------------------------------------------------------
 
 #[derive(Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "mmap-cache",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
 pub struct StopConnection {
     id: i32,
     stop_id_1: i32,
@@ -1088,11 +2651,33 @@ impl StopConnection {
     }
 }
 
+#[cfg(feature = "mmap-cache")]
+impl ArchivedStopConnection {
+    // Getters/Setters
+
+    pub fn stop_id_1(&self) -> i32 {
+        self.stop_id_1
+    }
+
+    pub fn stop_id_2(&self) -> i32 {
+        self.stop_id_2
+    }
+
+    pub fn duration(&self) -> i16 {
+        self.duration
+    }
+}
+
 // ------------------------------------------------------------------------------------------------
 // --- ThroughService
 // ------------------------------------------------------------------------------------------------
 
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "mmap-cache",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
 pub struct ThroughService {
     id: i32,
     journey_1_id: i32,
@@ -1122,6 +2707,191 @@ impl ThroughService {
             bit_field_id,
         }
     }
+
+    // Getters/Setters
+
+    pub fn journey_1_id(&self) -> i32 {
+        self.journey_1_id
+    }
+
+    pub fn journey_1_stop_id(&self) -> i32 {
+        self.journey_1_stop_id
+    }
+
+    pub fn journey_2_id(&self) -> i32 {
+        self.journey_2_id
+    }
+
+    pub fn journey_2_stop_id(&self) -> Option<i32> {
+        self.journey_2_stop_id
+    }
+
+    pub fn bit_field_id(&self) -> i32 {
+        self.bit_field_id
+    }
+}
+
+#[cfg(feature = "mmap-cache")]
+impl ArchivedThroughService {
+    // Getters/Setters
+
+    pub fn journey_1_id(&self) -> i32 {
+        self.journey_1_id
+    }
+
+    pub fn journey_1_stop_id(&self) -> i32 {
+        self.journey_1_stop_id
+    }
+
+    pub fn journey_2_id(&self) -> i32 {
+        self.journey_2_id
+    }
+
+    pub fn journey_2_stop_id(&self) -> Option<i32> {
+        self.journey_2_stop_id.as_ref().copied()
+    }
+
+    pub fn bit_field_id(&self) -> i32 {
+        self.bit_field_id
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// --- TimeDifference
+// ------------------------------------------------------------------------------------------------
+
+/// The time difference to UTC of a stop or of all the stops of a country (e.g. 8500000 for Switzerland).<br>
+/// The ID 0 is used for the stops without a specific time difference (see DataStorage::time_difference_of).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "mmap-cache",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
+pub struct TimeDifference {
+    id: i32,
+    // In seconds, east of UTC.
+    utc_offset: i32,
+    daylight_saving_periods: Vec<DaylightSavingPeriod>,
+}
+
+impl_Model!(TimeDifference);
+
+impl TimeDifference {
+    pub fn new(id: i32, utc_offset: i32) -> Self {
+        Self {
+            id,
+            utc_offset,
+            daylight_saving_periods: Vec::new(),
+        }
+    }
+
+    // Getters/Setters
+
+    /// The offset outside of the daylight saving periods.
+    pub fn utc_offset(&self) -> FixedOffset {
+        create_offset(self.utc_offset)
+    }
+
+    pub fn daylight_saving_periods(&self) -> &Vec<DaylightSavingPeriod> {
+        &self.daylight_saving_periods
+    }
+
+    pub fn set_id(&mut self, value: i32) {
+        self.id = value;
+    }
+
+    // Functions
+
+    pub fn add_daylight_saving_period(&mut self, period: DaylightSavingPeriod) {
+        self.daylight_saving_periods.push(period);
+    }
+
+    /// The offset in effect at the given UTC date and time.
+    pub fn offset_at(&self, utc: NaiveDateTime) -> FixedOffset {
+        TimeDifferenceData::offset_at(self, utc)
+    }
+
+    pub fn at_utc(&self, utc: NaiveDateTime) -> DateTime<FixedOffset> {
+        TimeDifferenceData::at_utc(self, utc)
+    }
+
+    /// The local time doesn't exist when the clocks are set forward (LocalResult::None)
+    /// and is ambiguous when they are set back (LocalResult::Ambiguous, the earliest first).
+    pub fn at_local(&self, local: NaiveDateTime) -> LocalResult<DateTime<FixedOffset>> {
+        TimeDifferenceData::at_local(self, local)
+    }
+
+    /// Same as at_local, but the local time is always resolved.<br>
+    /// A local time that doesn't exist is shifted forward, e.g. 02:30 becomes 03:30.
+    /// An ambiguous local time is resolved to the earliest instant that isn't before not_before.
+    pub fn at_local_not_before(
+        &self,
+        local: NaiveDateTime,
+        not_before: Option<DateTime<FixedOffset>>,
+    ) -> DateTime<FixedOffset> {
+        TimeDifferenceData::at_local_not_before(self, local, not_before)
+    }
+}
+
+impl TimeDifferenceData for TimeDifference {
+    type Period = DaylightSavingPeriod;
+
+    fn utc_offset(&self) -> FixedOffset {
+        create_offset(self.utc_offset)
+    }
+
+    fn daylight_saving_periods(&self) -> &[DaylightSavingPeriod] {
+        &self.daylight_saving_periods
+    }
+}
+
+#[cfg(feature = "mmap-cache")]
+impl ArchivedTimeDifference {
+    // Getters/Setters
+
+    pub fn utc_offset(&self) -> FixedOffset {
+        create_offset(self.utc_offset)
+    }
+
+    pub fn daylight_saving_periods(&self) -> &[ArchivedDaylightSavingPeriod] {
+        &self.daylight_saving_periods
+    }
+
+    // Functions
+
+    pub fn offset_at(&self, utc: NaiveDateTime) -> FixedOffset {
+        TimeDifferenceData::offset_at(self, utc)
+    }
+
+    pub fn at_utc(&self, utc: NaiveDateTime) -> DateTime<FixedOffset> {
+        TimeDifferenceData::at_utc(self, utc)
+    }
+
+    pub fn at_local(&self, local: NaiveDateTime) -> LocalResult<DateTime<FixedOffset>> {
+        TimeDifferenceData::at_local(self, local)
+    }
+
+    pub fn at_local_not_before(
+        &self,
+        local: NaiveDateTime,
+        not_before: Option<DateTime<FixedOffset>>,
+    ) -> DateTime<FixedOffset> {
+        TimeDifferenceData::at_local_not_before(self, local, not_before)
+    }
+}
+
+#[cfg(feature = "mmap-cache")]
+impl TimeDifferenceData for ArchivedTimeDifference {
+    type Period = ArchivedDaylightSavingPeriod;
+
+    fn utc_offset(&self) -> FixedOffset {
+        create_offset(self.utc_offset)
+    }
+
+    fn daylight_saving_periods(&self) -> &[ArchivedDaylightSavingPeriod] {
+        &self.daylight_saving_periods
+    }
 }
 
 // ------------------------------------------------------------------------------------------------
@@ -1129,6 +2899,11 @@ impl ThroughService {
 // ------------------------------------------------------------------------------------------------
 
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "mmap-cache",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
 pub struct TimetableMetadataEntry {
     id: i32,
     key: String,
@@ -1159,11 +2934,29 @@ impl TimetableMetadataEntry {
     }
 }
 
+#[cfg(feature = "mmap-cache")]
+impl ArchivedTimetableMetadataEntry {
+    // Getters/Setters
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+}
+
 // ------------------------------------------------------------------------------------------------
 // --- TransportCompany
 // ------------------------------------------------------------------------------------------------
 
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "mmap-cache",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
 pub struct TransportCompany {
     id: i32,
     short_name: FxHashMap<Language, String>,
@@ -1205,6 +2998,11 @@ impl TransportCompany {
 // ------------------------------------------------------------------------------------------------
 
 #[derive(Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "mmap-cache",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
 pub struct TransportType {
     id: i32,
     designation: String,
@@ -1265,13 +3063,374 @@ impl TransportType {
     }
 }
 
+#[cfg(feature = "mmap-cache")]
+impl ArchivedTransportType {
+    // Getters/Setters
+
+    pub fn designation(&self) -> &str {
+        &self.designation
+    }
+
+    pub fn product_class_id(&self) -> i16 {
+        self.product_class_id
+    }
+}
+
 // ------------------------------------------------------------------------------------------------
 // --- Version
 // ------------------------------------------------------------------------------------------------
 
 #[derive(Clone, Copy, Debug, Display, Eq, Hash, PartialEq, Serialize, Deserialize)]
 #[allow(non_camel_case_types)]
+#[cfg_attr(
+    feature = "mmap-cache",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes),
+    archive_attr(allow(non_camel_case_types))
+)]
 pub enum Version {
     V_5_40_41_2_0_4,
     V_5_40_41_2_0_5,
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::Timelike;
+
+    use crate::{error::ParsingMode, fixtures::MemorySource, storage::LoadOptions};
+
+    use super::*;
+
+    fn load(source: &MemorySource) -> DataStorage {
+        let (data_storage, _) = DataStorage::from_source(
+            Version::V_5_40_41_2_0_4,
+            source,
+            ParsingMode::Strict,
+            LoadOptions::default(),
+            None,
+        )
+        .unwrap();
+        data_storage
+    }
+
+    fn at(date: NaiveDate, hour: u32, minute: u32, utc_offset: i32) -> DateTime<FixedOffset> {
+        NaiveDateTime::new(date, NaiveTime::from_hms_opt(hour, minute, 0).unwrap())
+            .and_local_timezone(create_offset(utc_offset))
+            .unwrap()
+    }
+
+    #[test]
+    fn times_with_time_zone() {
+        let data_storage = load(&MemorySource::new());
+        let journey = data_storage.journeys().find(1).unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+
+        assert_eq!(
+            journey.departure_at_of_with_time_zone(8500010, date, &data_storage),
+            Some(at(date, 23, 30, 3600))
+        );
+        // The time difference of the stop is +0000 in winter.
+        assert_eq!(
+            journey.arrival_at_of_with_time_zone(8500030, date, &data_storage),
+            Some(at(date.succ_opt().unwrap(), 1, 0, 0))
+        );
+        // The last stop has no departure time.
+        assert_eq!(
+            journey.departure_at_of_with_time_zone(8500030, date, &data_storage),
+            None
+        );
+        // The stop isn't part of the route.
+        assert_eq!(
+            journey.departure_at_of_with_time_zone(8500040, date, &data_storage),
+            None
+        );
+    }
+
+    #[test]
+    fn times_with_unknown_time_zone() {
+        let source = MemorySource::new().with("ZEITVS", "8500030 +0100\n");
+        let data_storage = load(&source);
+        let journey = data_storage.journeys().find(1).unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+
+        assert_eq!(
+            journey.arrival_at_of_with_time_zone(8500030, date, &data_storage),
+            None
+        );
+    }
+
+    #[test]
+    fn border_points_of_journey() {
+        let data_storage = load(&MemorySource::new());
+
+        // Only the first journey stops at the border point (8500020).
+        let journey = data_storage.journeys().find(1).unwrap();
+        let border_points = journey.border_points(&data_storage);
+        assert_eq!(border_points.len(), 1);
+        assert_eq!(border_points[0].id(), 8500020);
+
+        let journey = data_storage.journeys().find(2).unwrap();
+        assert!(journey.border_points(&data_storage).is_empty());
+    }
+
+    fn local(month: u32, day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDateTime::new(
+            NaiveDate::from_ymd_opt(2024, month, day).unwrap(),
+            NaiveTime::from_hms_opt(hour, minute, 0).unwrap(),
+        )
+    }
+
+    #[test]
+    fn time_difference_of_stop() {
+        let data_storage = load(&MemorySource::new());
+
+        // The stop, then its country, then the default time difference.
+        assert_eq!(
+            data_storage.time_difference_of(8500030).unwrap().id(),
+            8500030
+        );
+        assert_eq!(
+            data_storage.time_difference_of(8500010).unwrap().id(),
+            8500000
+        );
+        assert_eq!(
+            data_storage.time_difference_of(7000010).unwrap().id(),
+            7000000
+        );
+        assert_eq!(data_storage.time_difference_of(8700010).unwrap().id(), 0);
+    }
+
+    #[test]
+    fn local_time_in_daylight_saving_gap() {
+        let data_storage = load(&MemorySource::new());
+        let time_difference = data_storage.time_difference_of(8500010).unwrap();
+
+        // The clocks are set forward from 02:00 to 03:00 on 31.03.2024.
+        let local = local(3, 31, 2, 30);
+        assert_eq!(time_difference.at_local(local), LocalResult::None);
+        assert_eq!(
+            time_difference.at_local_not_before(local, None),
+            at(local.date(), 3, 30, 7200)
+        );
+
+        assert_eq!(
+            time_difference.at_local(local.with_hour(1).unwrap()),
+            LocalResult::Single(at(local.date(), 1, 30, 3600))
+        );
+        assert_eq!(
+            time_difference.at_local(local.with_hour(3).unwrap()),
+            LocalResult::Single(at(local.date(), 3, 30, 7200))
+        );
+    }
+
+    #[test]
+    fn local_time_in_daylight_saving_overlap() {
+        let data_storage = load(&MemorySource::new());
+        let time_difference = data_storage.time_difference_of(8500010).unwrap();
+
+        // The clocks are set back from 03:00 to 02:00 on 27.10.2024.
+        let local = local(10, 27, 2, 30);
+        let (earliest, latest) = (at(local.date(), 2, 30, 7200), at(local.date(), 2, 30, 3600));
+        assert_eq!(
+            time_difference.at_local(local),
+            LocalResult::Ambiguous(earliest, latest)
+        );
+        assert_eq!(time_difference.at_local_not_before(local, None), earliest);
+        assert_eq!(
+            time_difference.at_local_not_before(local, Some(at(local.date(), 2, 45, 7200))),
+            latest
+        );
+
+        assert_eq!(
+            time_difference
+                .offset_at(earliest.naive_utc())
+                .local_minus_utc(),
+            7200
+        );
+        assert_eq!(
+            time_difference
+                .offset_at(latest.naive_utc())
+                .local_minus_utc(),
+            3600
+        );
+    }
+
+    #[test]
+    fn route_times_past_midnight() {
+        let data_storage = load(&MemorySource::new());
+        let route = data_storage.journeys().find(1).unwrap().route();
+        let date = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let next_date = date.succ_opt().unwrap();
+
+        assert_eq!(route[0].departure_minutes(), Some(23 * 60 + 30));
+        assert_eq!(
+            route[0].departure_time_and_day_offset(),
+            Some((NaiveTime::from_hms_opt(23, 30, 0).unwrap(), 0))
+        );
+
+        // 24:00 and 24:05 are on the next day.
+        assert_eq!(route[1].arrival_minutes(), Some(24 * 60));
+        assert_eq!(
+            route[1].arrival_time_and_day_offset(),
+            Some((NaiveTime::MIN, 1))
+        );
+        assert_eq!(route[1].departure_minutes(), Some(24 * 60 + 5));
+        // The time of day doesn't include the day offset.
+        assert_eq!(route[1].arrival_time(), &Some(NaiveTime::MIN));
+
+        assert_eq!(route[2].arrival_minutes(), Some(25 * 60));
+        assert_eq!(
+            route[2].arrival_at(date),
+            Some(NaiveDateTime::new(
+                next_date,
+                NaiveTime::from_hms_opt(1, 0, 0).unwrap()
+            ))
+        );
+        assert_eq!(route[2].departure_minutes(), None);
+    }
+
+    #[test]
+    fn boarding_and_alighting_restrictions() {
+        let data_storage = load(&MemorySource::new());
+        let can_board_and_alight = |journey_id| {
+            data_storage
+                .journeys()
+                .find(journey_id)
+                .unwrap()
+                .route()
+                .iter()
+                .map(|route_entry| (route_entry.can_board(), route_entry.can_alight()))
+                .collect::<Vec<_>>()
+        };
+
+        // The negative arrival times of the first journey forbid alighting at Zuerich HB and Bern.
+        assert_eq!(
+            can_board_and_alight(1),
+            vec![(true, false), (true, false), (false, false)]
+        );
+        assert_eq!(can_board_and_alight(2), vec![(true, false), (false, true)]);
+
+        // A negative departure time forbids boarding.
+        let fplan = MemorySource::new()
+            .file("FPLAN")
+            .replace("-02400  02405", "-02400 -02405");
+        let data_storage = load(&MemorySource::new().with("FPLAN", &fplan));
+        let route = data_storage.journeys().find(1).unwrap().route();
+        assert!(!route[1].can_board());
+        // The times are kept, without their sign.
+        assert_eq!(route[1].departure_minutes(), Some(24 * 60 + 5));
+    }
+
+    #[test]
+    fn metadata_of_journey_sections() {
+        let data_storage = load(&MemorySource::new());
+        let journey = data_storage.journeys().find(1).unwrap();
+        let attribute_id = |designation| {
+            data_storage
+                .attributes()
+                .entries()
+                .into_iter()
+                .find(|attribute| attribute.designation() == designation)
+                .unwrap()
+                .id()
+        };
+        let attributes_between = |from_stop_id, to_stop_id| {
+            let mut attribute_ids = journey.attributes_between(from_stop_id, to_stop_id);
+            attribute_ids.sort();
+            attribute_ids
+        };
+
+        assert_eq!(journey.bit_field_id(), Some(1));
+
+        // Y only applies from Zuerich HB.
+        assert_eq!(
+            attributes_between(8500010, 8500020),
+            vec![attribute_id("VR")]
+        );
+        let mut attribute_ids = vec![attribute_id("VR"), attribute_id("Y")];
+        attribute_ids.sort();
+        assert_eq!(attributes_between(8500020, 8500030), attribute_ids);
+        assert_eq!(attributes_between(8500010, 8500030), attribute_ids);
+
+        // The line changes at Zuerich HB.
+        assert!(matches!(
+            journey.line_at(8500010),
+            Some(LineReference::Id(1))
+        ));
+        for stop_id in [8500020, 8500030] {
+            assert!(matches!(
+                journey.line_at(stop_id),
+                Some(LineReference::Designation(designation)) if designation == "IC9"
+            ));
+        }
+        assert!(journey.line_at(8500040).is_none());
+
+        assert!(matches!(
+            journey.direction_at(8500020),
+            Some((DirectionType::Return, Some(_)))
+        ));
+        let journey_2 = data_storage.journeys().find(2).unwrap();
+        assert_eq!(
+            journey_2.direction_at(8500030),
+            Some((DirectionType::Return, None))
+        );
+
+        assert_eq!(journey.exchange_time_boarding_at(8500010), Some(2));
+        assert_eq!(journey.exchange_time_disembarking_at(8500030), Some(3));
+        assert_eq!(journey.exchange_time_disembarking_at(8500010), None);
+    }
+
+    #[test]
+    fn metadata_of_operating_date() {
+        let data_storage = load(&MemorySource::new());
+        let journey = data_storage.journeys().find(1).unwrap();
+        let first_date = NaiveDate::from_ymd_opt(2023, 12, 10).unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+
+        // XI is only active on the first 2 days of the timetable and only applies from Zuerich HB.
+        assert_eq!(
+            journey.information_texts_on(8500010, 8500030, first_date, &data_storage),
+            vec![("JY", 1), ("XI", 1)]
+        );
+        assert_eq!(
+            journey.information_texts_on(8500010, 8500020, first_date, &data_storage),
+            vec![("JY", 1)]
+        );
+        assert_eq!(
+            journey.information_texts_on(8500010, 8500030, date, &data_storage),
+            vec![("JY", 1)]
+        );
+
+        assert_eq!(
+            journey
+                .attributes_between_on(8500010, 8500030, date, &data_storage)
+                .len(),
+            2
+        );
+        // The times identify the stops of the section.
+        for (times, expected) in [
+            ("002405 002500", vec![("JY", 1), ("XI", 1)]),
+            ("002330 002500", vec![("JY", 1)]),
+        ] {
+            let fplan = MemorySource::new().file("FPLAN").replace(
+                "*I XI 8500020 8500030 000003 000000001",
+                &format!("*I XI 8500020 8500030 000003 000000001 {times}"),
+            );
+            let data_storage = load(&MemorySource::new().with("FPLAN", &fplan));
+            let journey = data_storage.journeys().find(1).unwrap();
+            assert_eq!(
+                journey.information_texts_on(8500020, 8500030, first_date, &data_storage),
+                expected
+            );
+        }
+
+        // The date is outside of the timetable period.
+        let date = NaiveDate::from_ymd_opt(2025, 3, 1).unwrap();
+        assert!(journey
+            .information_texts_on(8500010, 8500030, date, &data_storage)
+            .is_empty());
+        assert!(journey
+            .attributes_between_on(8500010, 8500030, date, &data_storage)
+            .is_empty());
+    }
+}
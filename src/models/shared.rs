@@ -0,0 +1,427 @@
+// The models and their archived counterparts (see the "mmap-cache" feature) implement the accessors of these traits,
+// so that the functions working on their data are written only once.
+use std::iter;
+
+use chrono::{DateTime, FixedOffset, LocalResult, NaiveDate, NaiveDateTime, NaiveTime};
+
+use crate::utils::{add_days, create_minutes};
+
+use super::{CoordinateSystem, DirectionType, JourneyMetadataType};
+
+// ------------------------------------------------------------------------------------------------
+// --- CoordinatesData
+// ------------------------------------------------------------------------------------------------
+
+pub(super) trait CoordinatesData {
+    fn coordinate_system(&self) -> CoordinateSystem;
+
+    fn x(&self) -> f64;
+
+    fn y(&self) -> f64;
+
+    fn easting(&self) -> f64 {
+        assert!(self.coordinate_system() == CoordinateSystem::LV95);
+        self.x()
+    }
+
+    fn northing(&self) -> f64 {
+        assert!(self.coordinate_system() == CoordinateSystem::LV95);
+        self.y()
+    }
+
+    fn latitude(&self) -> f64 {
+        assert!(self.coordinate_system() == CoordinateSystem::WGS84);
+        self.x()
+    }
+
+    fn longitude(&self) -> f64 {
+        assert!(self.coordinate_system() == CoordinateSystem::WGS84);
+        self.y()
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// --- DaylightSavingPeriodData
+// ------------------------------------------------------------------------------------------------
+
+pub(super) trait DaylightSavingPeriodData {
+    fn utc_offset(&self) -> FixedOffset;
+
+    fn start(&self) -> NaiveDateTime;
+
+    fn end(&self) -> NaiveDateTime;
+
+    fn contains(&self, utc: NaiveDateTime) -> bool {
+        self.start() <= utc && utc < self.end()
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// --- TimeDifferenceData
+// ------------------------------------------------------------------------------------------------
+
+pub(super) trait TimeDifferenceData {
+    type Period: DaylightSavingPeriodData;
+
+    fn utc_offset(&self) -> FixedOffset;
+
+    fn daylight_saving_periods(&self) -> &[Self::Period];
+
+    fn offset_at(&self, utc: NaiveDateTime) -> FixedOffset {
+        self.daylight_saving_periods()
+            .iter()
+            .find(|period| period.contains(utc))
+            .map_or_else(|| self.utc_offset(), |period| period.utc_offset())
+    }
+
+    fn at_utc(&self, utc: NaiveDateTime) -> DateTime<FixedOffset> {
+        DateTime::from_naive_utc_and_offset(utc, self.offset_at(utc))
+    }
+
+    fn at_local(&self, local: NaiveDateTime) -> LocalResult<DateTime<FixedOffset>> {
+        let mut candidates: Vec<_> = iter::once(self.utc_offset())
+            .chain(
+                self.daylight_saving_periods()
+                    .iter()
+                    .map(|period| period.utc_offset()),
+            )
+            .filter_map(|offset| {
+                let utc = local - offset;
+                (self.offset_at(utc) == offset).then(|| self.at_utc(utc))
+            })
+            .collect();
+        candidates.sort();
+        candidates.dedup();
+
+        match candidates[..] {
+            [] => LocalResult::None,
+            [x] => LocalResult::Single(x),
+            [earliest, .., latest] => LocalResult::Ambiguous(earliest, latest),
+        }
+    }
+
+    fn at_local_not_before(
+        &self,
+        local: NaiveDateTime,
+        not_before: Option<DateTime<FixedOffset>>,
+    ) -> DateTime<FixedOffset> {
+        match self.at_local(local) {
+            LocalResult::Single(x) => x,
+            LocalResult::Ambiguous(earliest, latest) => {
+                if not_before.is_some_and(|not_before| earliest < not_before) {
+                    latest
+                } else {
+                    earliest
+                }
+            }
+            // The clocks are only set forward when the daylight saving time starts, the standard offset was in effect before.
+            LocalResult::None => self.at_utc(local - self.utc_offset()),
+        }
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// --- JourneyRouteEntryData
+// ------------------------------------------------------------------------------------------------
+
+pub(super) trait JourneyRouteEntryData {
+    fn stop_id(&self) -> i32;
+
+    fn arrival_time(&self) -> Option<NaiveTime>;
+
+    fn departure_time(&self) -> Option<NaiveTime>;
+
+    fn arrival_day_offset(&self) -> i32;
+
+    fn departure_day_offset(&self) -> i32;
+
+    fn alighting_allowed(&self) -> bool;
+
+    fn boarding_allowed(&self) -> bool;
+
+    fn can_alight(&self) -> bool {
+        self.alighting_allowed() && self.arrival_time().is_some()
+    }
+
+    fn can_board(&self) -> bool {
+        self.boarding_allowed() && self.departure_time().is_some()
+    }
+
+    fn arrival_time_and_day_offset(&self) -> Option<(NaiveTime, i32)> {
+        self.arrival_time()
+            .map(|time| (time, self.arrival_day_offset()))
+    }
+
+    fn departure_time_and_day_offset(&self) -> Option<(NaiveTime, i32)> {
+        self.departure_time()
+            .map(|time| (time, self.departure_day_offset()))
+    }
+
+    fn arrival_minutes(&self) -> Option<i32> {
+        self.arrival_time_and_day_offset()
+            .map(|(time, day_offset)| create_minutes(time, day_offset))
+    }
+
+    fn departure_minutes(&self) -> Option<i32> {
+        self.departure_time_and_day_offset()
+            .map(|(time, day_offset)| create_minutes(time, day_offset))
+    }
+
+    fn arrival_at(&self, operating_date: NaiveDate) -> Option<NaiveDateTime> {
+        self.arrival_time_and_day_offset()
+            .map(|(time, day_offset)| {
+                NaiveDateTime::new(add_days(operating_date, day_offset), time)
+            })
+    }
+
+    fn departure_at(&self, operating_date: NaiveDate) -> Option<NaiveDateTime> {
+        self.departure_time_and_day_offset()
+            .map(|(time, day_offset)| {
+                NaiveDateTime::new(add_days(operating_date, day_offset), time)
+            })
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// --- JourneyMetadataEntryData
+// ------------------------------------------------------------------------------------------------
+
+pub(super) trait JourneyMetadataEntryData {
+    // LineReference or its archived counterpart.
+    type Line;
+
+    // Same name as the field of the entry.
+    #[allow(clippy::wrong_self_convention)]
+    fn from_stop_id(&self) -> Option<i32>;
+
+    fn until_stop_id(&self) -> Option<i32>;
+
+    fn bit_field_id(&self) -> Option<i32>;
+
+    fn departure_minutes(&self) -> Option<i32>;
+
+    fn arrival_minutes(&self) -> Option<i32>;
+
+    // The functions below return None if the entry is of another type.
+
+    fn attribute_id(&self) -> Option<i32>;
+
+    fn direction(&self) -> Option<(DirectionType, Option<i32>)>;
+
+    fn exchange_time(&self) -> Option<i16>;
+
+    // The code and the information text.
+    fn information_text(&self) -> Option<(&str, i32)>;
+
+    fn line(&self) -> Option<&Self::Line>;
+
+    fn line_id(&self) -> Option<i32>;
+
+    fn transport_type_id(&self) -> Option<i32>;
+}
+
+// ------------------------------------------------------------------------------------------------
+// --- JourneyData
+// ------------------------------------------------------------------------------------------------
+
+pub(super) trait JourneyData {
+    type RouteEntry: JourneyRouteEntryData;
+    type MetadataEntry: JourneyMetadataEntryData;
+
+    fn route(&self) -> &[Self::RouteEntry];
+
+    fn metadata_entries(&self, k: JourneyMetadataType) -> &[Self::MetadataEntry];
+
+    fn bit_field_id(&self) -> Option<i32> {
+        // unwrap: There will always be a BitField entry.
+        self.metadata_entries(JourneyMetadataType::BitField)[0].bit_field_id()
+    }
+
+    fn line_ids(&self) -> Vec<i32> {
+        self.metadata_entries(JourneyMetadataType::Line)
+            .iter()
+            .filter_map(|entry| entry.line_id())
+            .collect()
+    }
+
+    fn direction_ids(&self) -> Vec<i32> {
+        self.metadata_entries(JourneyMetadataType::Direction)
+            .iter()
+            .filter_map(|entry| entry.direction().and_then(|(_, direction_id)| direction_id))
+            .collect()
+    }
+
+    fn transport_type_id(&self) -> i32 {
+        // unwrap: There will always be a TransportType entry.
+        self.metadata_entries(JourneyMetadataType::TransportType)[0]
+            .transport_type_id()
+            .unwrap()
+    }
+
+    fn attributes_between(&self, from_stop_id: i32, to_stop_id: i32) -> Vec<i32> {
+        self.attributes_between_where(from_stop_id, to_stop_id, |_| true)
+    }
+
+    // Same as attributes_between, but only the entries whose bit field (None if every day) matches are taken into account.
+    fn attributes_between_where(
+        &self,
+        from_stop_id: i32,
+        to_stop_id: i32,
+        is_active: impl Fn(Option<i32>) -> bool,
+    ) -> Vec<i32> {
+        let mut result = Vec::new();
+        for entry in self.metadata_between_where(
+            JourneyMetadataType::Attribute,
+            from_stop_id,
+            to_stop_id,
+            is_active,
+        ) {
+            if let Some(attribute_id) = entry.attribute_id() {
+                if !result.contains(&attribute_id) {
+                    result.push(attribute_id);
+                }
+            }
+        }
+        result
+    }
+
+    // Same as attributes_between_where, but for the codes and information texts.
+    fn information_texts_between_where(
+        &self,
+        from_stop_id: i32,
+        to_stop_id: i32,
+        is_active: impl Fn(Option<i32>) -> bool,
+    ) -> Vec<(&str, i32)> {
+        self.metadata_between_where(
+            JourneyMetadataType::InformationText,
+            from_stop_id,
+            to_stop_id,
+            is_active,
+        )
+        .into_iter()
+        .filter_map(|entry| entry.information_text())
+        .collect()
+    }
+
+    fn line_at(
+        &self,
+        stop_id: i32,
+    ) -> Option<&<Self::MetadataEntry as JourneyMetadataEntryData>::Line> {
+        self.metadata_at(JourneyMetadataType::Line, stop_id)?.line()
+    }
+
+    fn direction_at(&self, stop_id: i32) -> Option<(DirectionType, Option<i32>)> {
+        self.metadata_at(JourneyMetadataType::Direction, stop_id)?
+            .direction()
+    }
+
+    fn exchange_time_boarding_at(&self, stop_id: i32) -> Option<i16> {
+        self.metadata_at(JourneyMetadataType::ExchangeTimeBoarding, stop_id)?
+            .exchange_time()
+    }
+
+    fn exchange_time_disembarking_at(&self, stop_id: i32) -> Option<i16> {
+        self.metadata_at(JourneyMetadataType::ExchangeTimeDisembarking, stop_id)?
+            .exchange_time()
+    }
+
+    fn first_stop_id(&self) -> i32 {
+        // unwrap: The route always contains at least 2 entries.
+        self.route().first().unwrap().stop_id()
+    }
+
+    fn last_stop_id(&self) -> i32 {
+        // unwrap: The route always contains at least 2 entries.
+        self.route().last().unwrap().stop_id()
+    }
+
+    // The entry of the given type whose section contains the stop, the latest starting one if there are several.
+    fn metadata_at(&self, k: JourneyMetadataType, stop_id: i32) -> Option<&Self::MetadataEntry> {
+        let index = self
+            .route()
+            .iter()
+            .position(|route_entry| route_entry.stop_id() == stop_id)?;
+
+        self.metadata_sections(k)
+            .filter(|(_, start, end)| (*start..=*end).contains(&index))
+            .max_by_key(|(_, start, _)| *start)
+            .map(|(entry, _, _)| entry)
+    }
+
+    // The entries of the given type that apply to at least a part of the section between the two stops,
+    // if their bit field (None if every day) matches.
+    fn metadata_between_where(
+        &self,
+        k: JourneyMetadataType,
+        from_stop_id: i32,
+        to_stop_id: i32,
+        is_active: impl Fn(Option<i32>) -> bool,
+    ) -> Vec<&Self::MetadataEntry> {
+        let Some((from, to)) = self.section_indexes(from_stop_id, to_stop_id) else {
+            return Vec::new();
+        };
+
+        self.metadata_sections(k)
+            .filter(|(entry, start, end)| {
+                *start < to && *end > from && is_active(entry.bit_field_id())
+            })
+            .map(|(entry, _, _)| entry)
+            .collect()
+    }
+
+    // The entries of the given type with the indexes of the first and last route entries of their section.
+    fn metadata_sections(
+        &self,
+        k: JourneyMetadataType,
+    ) -> impl Iterator<Item = (&Self::MetadataEntry, usize, usize)> {
+        self.metadata_entries(k).iter().filter_map(|entry| {
+            let (start, end) = metadata_section(self.route(), entry)?;
+            Some((entry, start, end))
+        })
+    }
+
+    // The indexes of the departure and arrival stops in the route.
+    fn section_indexes(&self, from_stop_id: i32, to_stop_id: i32) -> Option<(usize, usize)> {
+        let route = self.route();
+        let from = route
+            .iter()
+            .position(|route_entry| route_entry.stop_id() == from_stop_id)?;
+        let to = from
+            + 1
+            + route[from + 1..]
+                .iter()
+                .position(|route_entry| route_entry.stop_id() == to_stop_id)?;
+        Some((from, to))
+    }
+}
+
+/// The indexes of the first and last route entries to which a metadata entry applies.<br>
+/// The times identify the stops when the route passes several times through them, they are compared with the day offsets,
+/// so that e.g. 00:30 on the first day and 00:30 on the next day are two different stops.
+fn metadata_section(
+    route: &[impl JourneyRouteEntryData],
+    entry: &impl JourneyMetadataEntryData,
+) -> Option<(usize, usize)> {
+    let start = match entry.from_stop_id() {
+        Some(from_stop_id) => route.iter().position(|route_entry| {
+            route_entry.stop_id() == from_stop_id
+                && entry
+                    .departure_minutes()
+                    .is_none_or(|minutes| route_entry.departure_minutes() == Some(minutes))
+        })?,
+        None => 0,
+    };
+    let end = match entry.until_stop_id() {
+        // A stop after the start is preferred, as the route may be a loop.
+        Some(until_stop_id) => (start + 1..route.len())
+            .chain(iter::once(start))
+            .find(|&i| {
+                route[i].stop_id() == until_stop_id
+                    && entry
+                        .arrival_minutes()
+                        .is_none_or(|minutes| route[i].arrival_minutes() == Some(minutes))
+            })?,
+        None => route.len().checked_sub(1)?,
+    };
+    Some((start, end))
+}
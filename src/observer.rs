@@ -0,0 +1,57 @@
+use std::{path::PathBuf, time::Duration};
+
+// ------------------------------------------------------------------------------------------------
+// --- LoadEvent
+// ------------------------------------------------------------------------------------------------
+
+/// Reports the progress of the loading to a LoadObserver.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum LoadEvent {
+    /// A chunk of the HRDF archive has been downloaded. The total is unknown if the server doesn't provide it.
+    DownloadProgress {
+        bytes_received: u64,
+        total_bytes: Option<u64>,
+    },
+    /// An entry of the HRDF archive has been extracted. The total is unknown for tar archives.
+    ExtractionProgress {
+        entries_extracted: usize,
+        total_entries: Option<usize>,
+    },
+    /// The parsing of an HRDF file, e.g. FPLAN, has started.
+    FileStarted { file: String },
+    /// The parsing of an HRDF file has finished, rows is the number of non-empty rows read.
+    FileFinished {
+        file: String,
+        rows: usize,
+        duration: Duration,
+    },
+    /// The building of a map of DataStorage, e.g. bit_fields_by_day, has started.
+    IndexStarted { name: String },
+    /// The building of a map of DataStorage has finished.
+    IndexFinished { name: String, duration: Duration },
+    /// The data has been loaded from the cache.
+    CacheRead { path: PathBuf, duration: Duration },
+    /// The cache has been written.
+    CacheWritten {
+        path: PathBuf,
+        bytes: u64,
+        duration: Duration,
+    },
+}
+
+// ------------------------------------------------------------------------------------------------
+// --- LoadObserver
+// ------------------------------------------------------------------------------------------------
+
+/// Receives the LoadEvents, e.g. to render a progress bar or to record the loading time of each file.<br>
+/// The HRDF files may be parsed concurrently (see the "parallel" feature), so the events may come from several threads.
+pub trait LoadObserver: Send + Sync {
+    fn on_event(&self, event: &LoadEvent);
+}
+
+impl<F: Fn(&LoadEvent) + Send + Sync> LoadObserver for F {
+    fn on_event(&self, event: &LoadEvent) {
+        self(event)
+    }
+}
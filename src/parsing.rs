@@ -1,5 +1,6 @@
 mod attribute_parser;
 mod bit_field_parser;
+mod border_point_parser;
 mod direction_parser;
 mod exchange_administration_parser;
 mod exchange_journey_parser;
@@ -12,12 +13,15 @@ mod platform_parser;
 mod stop_connection_parser;
 mod stop_parser;
 mod through_service_parser;
+mod time_difference_parser;
 mod timetable_metadata_parser;
 mod transport_company_parser;
 mod transport_type_parser;
+mod version_parser;
 
 pub use attribute_parser::parse as load_attributes;
 pub use bit_field_parser::parse as load_bit_fields;
+pub use border_point_parser::parse as load_border_points;
 pub use direction_parser::parse as load_directions;
 pub use exchange_administration_parser::parse as load_exchange_times_administration;
 pub use exchange_journey_parser::parse as load_exchange_times_journey;
@@ -30,18 +34,22 @@ pub use platform_parser::parse as load_platforms;
 pub use stop_connection_parser::parse as load_stop_connections;
 pub use stop_parser::parse as load_stops;
 pub use through_service_parser::parse as load_through_service;
+pub use time_difference_parser::parse as load_time_differences;
 pub use timetable_metadata_parser::parse as load_timetable_metadata;
 pub use transport_company_parser::parse as load_transport_companies;
 pub use transport_type_parser::parse as load_transport_types;
+pub use version_parser::parse as detect_version;
 
-use std::{
-    error::Error,
-    fs::File,
-    io::{self, Read, Seek},
-};
+use std::{io::BufRead, str::FromStr, time::Instant};
 
 use regex::Regex;
 
+use crate::{
+    error::{Diagnostics, HrdfError, RowError},
+    observer::LoadEvent,
+    source::HrdfSource,
+};
+
 pub enum ExpectedType {
     Float,
     Integer16,
@@ -147,8 +155,9 @@ impl RowMatcher for FastRowMatcher {
         // This is not a problem, as the start index is always at the beginning of the
         // string, where there is no character requiring more than 1 byte.
         let start = self.start - 1;
-        let target_value = &row[start..(start + self.length)];
-        self.should_equal_value == (target_value == self.value)
+        // Rows that are too short don't contain the value.
+        let target_value = row.get(start..(start + self.length));
+        self.should_equal_value == (target_value == Some(self.value.as_str()))
     }
 }
 
@@ -161,10 +170,11 @@ pub struct AdvancedRowMatcher {
 }
 
 impl AdvancedRowMatcher {
-    pub fn new(re: &str) -> Result<Self, Box<dyn Error>> {
-        Ok(Self {
-            re: Regex::new(re)?,
-        })
+    pub fn new(re: &str) -> Self {
+        Self {
+            // If this error occurs, it's due to an invalid regular expression and it's the developer's fault.
+            re: Regex::new(re).expect("Invalid regular expression."),
+        }
     }
 }
 
@@ -220,6 +230,41 @@ impl RowDefinition {
             row_configuration,
         }
     }
+
+    fn parse(&self, row: &str) -> Result<Vec<ParsedValue>, RowError> {
+        self.row_configuration
+            .iter()
+            .map(|column_definition| {
+                let start = column_definition.start - 1;
+                let stop = if column_definition.stop == -1 {
+                    row.chars().count()
+                } else {
+                    column_definition.stop as usize
+                };
+                let columns = Some((column_definition.start, stop));
+
+                // Converts start/stop columns into real indexes.
+                let start = row
+                    .char_indices()
+                    .map(|(i, _)| i)
+                    .nth(start)
+                    .ok_or_else(|| RowError::Parse {
+                        columns,
+                        reason: "The start column is out of range.".to_string(),
+                    })?;
+                let stop = if let Some(i) = row.char_indices().map(|(i, _)| i).nth(stop) {
+                    i
+                } else {
+                    row.len()
+                };
+
+                let value = row[start..stop].trim();
+
+                parse_value(value, &column_definition.expected_type)
+                    .map_err(|reason| RowError::Parse { columns, reason })
+            })
+            .collect()
+    }
 }
 
 impl From<RowConfiguration> for RowDefinition {
@@ -248,52 +293,7 @@ impl RowParser {
         Self { row_definitions }
     }
 
-    fn parse(&self, row: &str) -> Result<ParsedRow, Box<dyn Error>> {
-        let row_definition = self.row_definition(row)?;
-        // 2 bytes for \r\n
-        let bytes_read = row.len() as u64 + 2;
-        let values = row_definition
-            .row_configuration
-            .iter()
-            .map(|column_definition| {
-                let start = column_definition.start - 1;
-                let stop = if column_definition.stop == -1 {
-                    row.chars().count()
-                } else {
-                    column_definition.stop as usize
-                };
-
-                // Converts start/stop columns into real indexes.
-                let start = row
-                    .char_indices()
-                    .map(|(i, _)| i)
-                    .nth(start)
-                    .ok_or("The start column is out of range.")?;
-                let stop = if let Some(i) = row.char_indices().map(|(i, _)| i).nth(stop) {
-                    i
-                } else {
-                    row.len()
-                };
-
-                let value = row[start..stop].trim();
-
-                let result = match column_definition.expected_type {
-                    ExpectedType::Float => ParsedValue::Float(value.parse()?),
-                    ExpectedType::Integer16 => ParsedValue::Integer16(value.parse()?),
-                    ExpectedType::Integer32 => ParsedValue::Integer32(value.parse()?),
-                    // The "value" variable is a &str, so it's impossible to fail by converting it to a String.
-                    ExpectedType::String => ParsedValue::String(value.to_owned()),
-                    ExpectedType::OptionInteger32 => {
-                        ParsedValue::OptionInteger32(value.parse().ok())
-                    }
-                };
-                Ok::<ParsedValue, Box<dyn Error>>(result)
-            })
-            .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
-        Ok((row_definition.id, bytes_read, values))
-    }
-
-    fn row_definition(&self, row: &str) -> Result<&RowDefinition, Box<dyn Error>> {
+    fn row_definition(&self, row: &str) -> Result<&RowDefinition, RowError> {
         if self.row_definitions.len() == 1 {
             return Ok(&self.row_definitions[0]);
         }
@@ -304,47 +304,107 @@ impl RowParser {
             // unwrap: "row_matcher" is guaranteed to always have a value when there are multiple row definitions.
             .find(|row_definition| row_definition.row_matcher.as_ref().unwrap().match_row(row));
 
-        matched_row_definition.ok_or(format!("This type of row is unknown:\n{}", row).into())
+        matched_row_definition.ok_or_else(|| RowError::invalid("This type of row is unknown."))
     }
 }
 
+fn parse_value(value: &str, expected_type: &ExpectedType) -> Result<ParsedValue, String> {
+    fn parse<T: FromStr>(value: &str) -> Result<T, String>
+    where
+        T::Err: std::fmt::Display,
+    {
+        value
+            .parse()
+            .map_err(|e| format!("Invalid value \"{value}\": {e}"))
+    }
+
+    let result = match expected_type {
+        ExpectedType::Float => ParsedValue::Float(parse(value)?),
+        ExpectedType::Integer16 => ParsedValue::Integer16(parse(value)?),
+        ExpectedType::Integer32 => ParsedValue::Integer32(parse(value)?),
+        // The "value" variable is a &str, so it's impossible to fail by converting it to a String.
+        ExpectedType::String => ParsedValue::String(value.to_owned()),
+        ExpectedType::OptionInteger32 => ParsedValue::OptionInteger32(value.parse().ok()),
+    };
+    Ok(result)
+}
+
 // ------------------------------------------------------------------------------------------------
 // --- FileParser
 // ------------------------------------------------------------------------------------------------
 
-pub struct FileParser {
-    rows: Vec<String>,
+/// The rows are read one at a time, so that the whole file never has to be held in memory.
+pub struct FileParser<'a> {
+    // Name of the HRDF file, e.g. FPLAN.
+    file: String,
+    reader: Box<dyn BufRead + 'a>,
+    // 1-based indexing
+    first_line: usize,
     row_parser: RowParser,
+    diagnostics: &'a Diagnostics,
 }
 
-impl FileParser {
-    pub fn new(path: &str, row_parser: RowParser) -> io::Result<Self> {
-        Self::new_with_bytes_offset(path, row_parser, 0)
+impl<'a> FileParser<'a> {
+    pub fn new(
+        source: &'a dyn HrdfSource,
+        file: &str,
+        row_parser: RowParser,
+        diagnostics: &'a Diagnostics,
+    ) -> Result<Self, HrdfError> {
+        Self::new_with_bytes_offset(source, file, row_parser, diagnostics, 0, 0)
     }
 
+    /// The rows before the offset are skipped, skipped_lines is their number of lines,
+    /// so that the line numbers reported in errors remain correct.
     pub fn new_with_bytes_offset(
-        path: &str,
+        source: &'a dyn HrdfSource,
+        file: &str,
         row_parser: RowParser,
+        diagnostics: &'a Diagnostics,
         bytes_offset: u64,
-    ) -> io::Result<Self> {
-        let rows = Self::read_lines(path, bytes_offset)?;
-        Ok(Self { rows, row_parser })
+        skipped_lines: usize,
+    ) -> Result<Self, HrdfError> {
+        let reader = source.open_at(file, bytes_offset)?;
+        Self::from_reader(file, reader, row_parser, diagnostics, skipped_lines)
     }
 
-    fn read_lines(path: &str, bytes_offset: u64) -> io::Result<Vec<String>> {
-        let mut file = File::open(path)?;
-        file.seek(io::SeekFrom::Start(bytes_offset))?;
-        let mut reader = io::BufReader::new(file);
-        let mut contents = String::new();
-        reader.read_to_string(&mut contents)?;
-        let lines = contents.lines().map(String::from).collect();
-        Ok(lines)
+    /// The file parameter is the name of the HRDF file, it's only used to locate the errors.<br>
+    /// The reader may start after the beginning of the file, skipped_lines is then the number of lines before it.
+    pub fn from_reader(
+        file: &str,
+        reader: impl BufRead + 'a,
+        row_parser: RowParser,
+        diagnostics: &'a Diagnostics,
+        skipped_lines: usize,
+    ) -> Result<Self, HrdfError> {
+        Ok(Self {
+            file: file.to_string(),
+            reader: Box::new(reader),
+            first_line: skipped_lines + 1,
+            row_parser,
+            diagnostics,
+        })
     }
 
-    pub fn parse(&self) -> ParsedRowIterator {
+    pub fn parse(self) -> ParsedRowIterator<'a> {
+        self.diagnostics.notify(LoadEvent::FileStarted {
+            file: self.file.clone(),
+        });
+
         ParsedRowIterator {
-            rows_iter: self.rows.iter(),
-            row_parser: &self.row_parser,
+            file: self.file,
+            reader: self.reader,
+            row_parser: self.row_parser,
+            diagnostics: self.diagnostics,
+            record_start: None,
+            skipping_record: false,
+            buffer: String::new(),
+            // The line counter is incremented before each row is read.
+            current_line: self.first_line - 1,
+            current_id: None,
+            rows: 0,
+            started_at: Instant::now(),
+            finished: false,
         }
     }
 }
@@ -353,18 +413,421 @@ impl FileParser {
 // --- ParsedRowIterator
 // ------------------------------------------------------------------------------------------------
 
+/// In lenient mode, the rows that fail to be parsed or processed are skipped and reported to the Diagnostics.
 pub struct ParsedRowIterator<'a> {
-    rows_iter: std::slice::Iter<'a, String>,
-    row_parser: &'a RowParser,
+    file: String,
+    reader: Box<dyn BufRead + 'a>,
+    row_parser: RowParser,
+    diagnostics: &'a Diagnostics,
+    // Type of the rows starting a record, e.g. a journey in FPLAN.
+    record_start: Option<i32>,
+    // Set when the row starting the current record has been skipped, the rest of the record is skipped as well.
+    skipping_record: bool,
+    // Contains the last row read, including its line terminator. The buffer is reused for every row.
+    buffer: String,
+    // Location of the last row returned, errors occurring while processing it are attached to it.
+    current_line: usize,
+    current_id: Option<i32>,
+    // Number of non-empty rows read, reported with the duration once the end of the file is reached.
+    rows: usize,
+    started_at: Instant,
+    finished: bool,
+}
+
+impl<'a> ParsedRowIterator<'a> {
+    /// The rows of type id start a record, to which the rows that follow belong.
+    /// In lenient mode, if such a row is skipped, the whole record is skipped.
+    pub fn with_record_start(mut self, id: i32) -> Self {
+        self.record_start = Some(id);
+        self
+    }
+
+    /// Same as map, except that the errors returned by f are located at the row being processed.
+    pub fn map_rows<T, F>(self, f: F) -> ProcessedRowIterator<'a, F>
+    where
+        F: FnMut(ParsedRow) -> Result<T, RowError>,
+    {
+        ProcessedRowIterator { rows: self, f }
+    }
+
+    /// Same as try_for_each, except that the errors returned by f are located at the row being processed.
+    pub fn try_for_each_row<F>(self, f: F) -> Result<(), HrdfError>
+    where
+        F: FnMut(ParsedRow) -> Result<(), RowError>,
+    {
+        self.map_rows(f).collect()
+    }
+
+    /// Same as try_for_each_row, but f also receives the line and the content of the row,
+    /// so that the errors detected once the whole file has been parsed can be located (see RowError::locate).
+    pub fn try_for_each_located_row<F>(mut self, mut f: F) -> Result<(), HrdfError>
+    where
+        F: FnMut(ParsedRow, usize, &str) -> Result<(), RowError>,
+    {
+        while let Some(row) = self.next() {
+            let row = row?;
+            if let Err(e) = f(row, self.current_line, strip_line_terminator(&self.buffer)) {
+                self.skip_row(e)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn locate(&self, error: RowError) -> HrdfError {
+        error.locate(
+            &self.file,
+            self.current_line,
+            strip_line_terminator(&self.buffer),
+        )
+    }
+
+    /// Returns the error if it cannot be skipped, i.e. in strict mode.
+    fn skip_row(&mut self, error: RowError) -> Result<(), HrdfError> {
+        self.diagnostics.report(self.locate(error))?;
+        if self.current_id.is_some() && self.current_id == self.record_start {
+            self.skipping_record = true;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) {
+        if !self.finished {
+            self.finished = true;
+            self.diagnostics.notify(LoadEvent::FileFinished {
+                file: self.file.clone(),
+                rows: self.rows,
+                duration: self.started_at.elapsed(),
+            });
+        }
+    }
+
+    fn parse_current_row(&mut self, bytes_read: u64) -> Result<Option<ParsedRow>, RowError> {
+        let row = strip_line_terminator(&self.buffer);
+        let row_definition = self.row_parser.row_definition(row);
+        let id = row_definition.as_ref().ok().map(|x| x.id);
+        self.current_id = id;
+
+        if self.skipping_record {
+            if id != self.record_start {
+                self.diagnostics.warn(
+                    &self.file,
+                    self.current_line,
+                    "The row belongs to a record that has been skipped.",
+                );
+                return Ok(None);
+            }
+            self.skipping_record = false;
+        }
+
+        let row_definition = row_definition?;
+        let values = row_definition.parse(row)?;
+        Ok(Some((row_definition.id, bytes_read, values)))
+    }
+}
+
+impl<'a> Iterator for ParsedRowIterator<'a> {
+    type Item = Result<ParsedRow, HrdfError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.buffer.clear();
+            let bytes_read = match self.reader.read_line(&mut self.buffer) {
+                Ok(0) => {
+                    self.finish();
+                    return None;
+                }
+                Ok(bytes_read) => bytes_read as u64,
+                Err(e) => return Some(Err(e.into())),
+            };
+            self.current_line += 1;
+
+            if self.buffer.trim().is_empty() {
+                continue;
+            }
+            self.rows += 1;
+
+            match self.parse_current_row(bytes_read) {
+                Ok(Some(parsed_row)) => return Some(Ok(parsed_row)),
+                Ok(None) => {}
+                Err(e) => {
+                    if let Err(e) = self.skip_row(e) {
+                        return Some(Err(e));
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Some parsers stop before the end of the file, which must also be reported to the observer.
+impl Drop for ParsedRowIterator<'_> {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+fn strip_line_terminator(line: &str) -> &str {
+    let line = line.strip_suffix('\n').unwrap_or(line);
+    line.strip_suffix('\r').unwrap_or(line)
+}
+
+// ------------------------------------------------------------------------------------------------
+// --- ProcessedRowIterator
+// ------------------------------------------------------------------------------------------------
+
+pub struct ProcessedRowIterator<'a, F> {
+    rows: ParsedRowIterator<'a>,
+    f: F,
 }
 
-impl Iterator for ParsedRowIterator<'_> {
-    type Item = Result<ParsedRow, Box<dyn Error>>;
+impl<T, F> Iterator for ProcessedRowIterator<'_, F>
+where
+    F: FnMut(ParsedRow) -> Result<T, RowError>,
+{
+    type Item = Result<T, HrdfError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.rows_iter
-            .by_ref()
-            .find(|row| !row.trim().is_empty())
-            .map(|row| self.row_parser.parse(row))
+        loop {
+            let row = match self.rows.next()? {
+                Ok(row) => row,
+                Err(e) => return Some(Err(e)),
+            };
+
+            match (self.f)(row) {
+                Ok(value) => return Some(Ok(value)),
+                Err(e) => {
+                    if let Err(e) = self.rows.skip_row(e) {
+                        return Some(Err(e));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Cursor, Read};
+
+    use crate::{
+        error::{ParsingMode, Severity},
+        fixtures::MemorySource,
+    };
+
+    use super::*;
+
+    const ROW_A: i32 = 1;
+    const ROW_B: i32 = 2;
+
+    // The rows starting with * start a record, to which the rows that follow belong.
+    fn row_parser() -> RowParser {
+        RowParser::new(vec![
+            RowDefinition::new(
+                ROW_A,
+                Box::new(FastRowMatcher::new(1, 1, "*", true)),
+                vec![ColumnDefinition::new(3, 8, ExpectedType::Integer32)],
+            ),
+            RowDefinition::new(
+                ROW_B,
+                Box::new(AdvancedRowMatcher::new(r"^[0-9]")),
+                vec![
+                    ColumnDefinition::new(1, 7, ExpectedType::Integer32),
+                    ColumnDefinition::new(9, -1, ExpectedType::String),
+                ],
+            ),
+        ])
+    }
+
+    fn file_parser<'a>(content: &'a str, diagnostics: &'a Diagnostics) -> FileParser<'a> {
+        FileParser::from_reader("TEST", Cursor::new(content), row_parser(), diagnostics, 0).unwrap()
+    }
+
+    fn parse_rows(
+        content: &str,
+        diagnostics: &Diagnostics,
+    ) -> Result<Vec<(i32, Vec<ParsedValue>)>, HrdfError> {
+        file_parser(content, diagnostics)
+            .parse()
+            .map(|row| row.map(|(id, _, values)| (id, values)))
+            .collect()
+    }
+
+    #[test]
+    fn rows_are_parsed() {
+        let diagnostics = Diagnostics::new(ParsingMode::Strict, None);
+        let rows = parse_rows("* 000001\r\n8500010 Basel SBB\r\n", &diagnostics).unwrap();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].0, ROW_A);
+        assert!(matches!(rows[0].1[..], [ParsedValue::Integer32(1)]));
+        assert_eq!(rows[1].0, ROW_B);
+        assert!(matches!(
+            &rows[1].1[..],
+            [ParsedValue::Integer32(8500010), ParsedValue::String(name)] if name == "Basel SBB"
+        ));
+    }
+
+    #[test]
+    fn invalid_value_is_located() {
+        let diagnostics = Diagnostics::new(ParsingMode::Strict, None);
+        let error = parse_rows("* 000001\r\n85000x0 Basel SBB\r\n", &diagnostics).unwrap_err();
+
+        assert!(matches!(
+            &error,
+            HrdfError::Parse { file, line: 2, columns: Some((1, 7)), row, .. }
+                if file == "TEST" && row == "85000x0 Basel SBB"
+        ));
+        assert!(error.to_string().starts_with("TEST, line 2, columns 1-7: "));
+    }
+
+    #[test]
+    fn unknown_row_is_located() {
+        let diagnostics = Diagnostics::new(ParsingMode::Strict, None);
+        let error = parse_rows("* 000001\r\n\r\n# comment\r\n", &diagnostics).unwrap_err();
+
+        assert!(matches!(
+            error,
+            HrdfError::Parse { line: 3, columns: None, ref reason, .. }
+                if reason == "This type of row is unknown."
+        ));
+    }
+
+    #[test]
+    fn short_row_is_located() {
+        let diagnostics = Diagnostics::new(ParsingMode::Strict, None);
+        let error = parse_rows("*\r\n", &diagnostics).unwrap_err();
+
+        assert!(matches!(
+            error,
+            HrdfError::Parse {
+                line: 1,
+                columns: Some((3, 8)),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn invalid_rows_are_skipped_in_lenient_mode() {
+        let diagnostics = Diagnostics::new(ParsingMode::Lenient, None);
+        let rows = parse_rows(
+            "* 000001\r\n85000x0 Basel SBB\r\n8500020 Zuerich HB\r\n",
+            &diagnostics,
+        )
+        .unwrap();
+        assert_eq!(rows.len(), 2);
+
+        let diagnostics = diagnostics.into_entries();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity(), Severity::Error);
+        assert_eq!((diagnostics[0].file(), diagnostics[0].line()), ("TEST", 2));
+        assert!(diagnostics[0].reason().starts_with("columns 1-7: "));
+    }
+
+    #[test]
+    fn records_are_skipped_in_lenient_mode() {
+        let diagnostics = Diagnostics::new(ParsingMode::Lenient, None);
+        let content = "* 00000x\r\n8500010 Basel SBB\r\n* 000002\r\n8500020 Zuerich HB\r\n";
+        let rows: Vec<_> = file_parser(content, &diagnostics)
+            .parse()
+            .with_record_start(ROW_A)
+            .map(|row| row.map(|(id, _, _)| id))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(rows, vec![ROW_A, ROW_B]);
+
+        // The rows of the skipped record are reported as warnings.
+        let diagnostics = diagnostics.into_entries();
+        let severities: Vec<_> = diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.line(), diagnostic.severity()))
+            .collect();
+        assert_eq!(
+            severities,
+            vec![(1, Severity::Error), (2, Severity::Warning)]
+        );
+    }
+
+    #[test]
+    fn processing_errors_are_skipped_in_lenient_mode() {
+        let diagnostics = Diagnostics::new(ParsingMode::Lenient, None);
+        let ids: Vec<i32> =
+            file_parser("8500010 Basel SBB\r\n8500020 Zuerich HB\r\n", &diagnostics)
+                .parse()
+                .map_rows(|(_, _, mut values)| {
+                    let id: i32 = values.remove(0).into();
+                    if id == 8500010 {
+                        return Err(RowError::unknown_reference("stop", id));
+                    }
+                    Ok(id)
+                })
+                .collect::<Result<_, _>>()
+                .unwrap();
+        assert_eq!(ids, vec![8500020]);
+
+        let diagnostics = diagnostics.into_entries();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].reason(), "unknown stop 8500010");
+    }
+
+    #[test]
+    fn rows_are_parsed_from_offset() {
+        let diagnostics = Diagnostics::new(ParsingMode::Strict, None);
+        let source =
+            MemorySource::new().with("TEST", "* 000001\n8500010 Basel SBB\n85000x0 Bern\n");
+        let mut rows = FileParser::new_with_bytes_offset(
+            &source,
+            "TEST",
+            row_parser(),
+            &diagnostics,
+            "* 000001\r\n".len() as u64,
+            1,
+        )
+        .unwrap()
+        .parse();
+
+        assert!(matches!(rows.next(), Some(Ok((ROW_B, 19, _)))));
+        // The line numbers take the skipped rows into account.
+        assert!(matches!(
+            rows.next(),
+            Some(Err(HrdfError::Parse { line: 3, .. }))
+        ));
+    }
+
+    #[test]
+    fn line_terminators_are_stripped() {
+        let diagnostics = Diagnostics::new(ParsingMode::Strict, None);
+        let rows = parse_rows(
+            "8500010 Basel SBB\n\n8500020 Zuerich HB\r\n8500030 Bern",
+            &diagnostics,
+        )
+        .unwrap();
+
+        let names: Vec<_> = rows
+            .into_iter()
+            .map(|(_, mut values)| String::from(values.remove(1)))
+            .collect();
+        assert_eq!(names, vec!["Basel SBB", "Zuerich HB", "Bern"]);
+    }
+
+    #[test]
+    fn rows_are_read_one_at_a_time() {
+        // Fails once the first row has been read.
+        struct BrokenReader;
+
+        impl Read for BrokenReader {
+            fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::other("broken"))
+            }
+        }
+
+        let diagnostics = Diagnostics::new(ParsingMode::Strict, None);
+        let reader = io::BufReader::new(Cursor::new("8500010 Basel SBB\r\n").chain(BrokenReader));
+        let mut rows = FileParser::from_reader("TEST", reader, row_parser(), &diagnostics, 0)
+            .unwrap()
+            .parse();
+
+        assert!(matches!(rows.next(), Some(Ok((ROW_B, _, _)))));
+        assert!(matches!(rows.next(), Some(Err(HrdfError::Io(_)))));
     }
 }
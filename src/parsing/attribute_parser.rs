@@ -1,26 +1,28 @@
 // 5 file(s).
 // File(s) read by the parser:
-// ATTRIBUT
-// ---
-// Files not used by the parser:
-// ATTRIBUT_DE, ATTRIBUT_EN, ATTRIBUT_FR, ATTRIBUT_IT
-use std::{error::Error, str::FromStr};
+// ATTRIBUT, ATTRIBUT_DE, ATTRIBUT_EN, ATTRIBUT_FR, ATTRIBUT_IT
+use std::str::FromStr;
 
 use rustc_hash::FxHashMap;
 
 use crate::{
+    error::{Diagnostics, HrdfError, RowError},
     models::{Attribute, Language, Model},
     parsing::{
         AdvancedRowMatcher, ColumnDefinition, ExpectedType, FastRowMatcher, FileParser,
         ParsedValue, RowDefinition, RowParser,
     },
+    source::HrdfSource,
     storage::ResourceStorage,
     utils::AutoIncrement,
 };
 
 type AttributeAndTypeConverter = (ResourceStorage<Attribute>, FxHashMap<String, i32>);
 
-pub fn parse(path: &str) -> Result<AttributeAndTypeConverter, Box<dyn Error>> {
+pub fn parse(
+    source: &dyn HrdfSource,
+    diagnostics: &Diagnostics,
+) -> Result<AttributeAndTypeConverter, HrdfError> {
     log::info!("Parsing ATTRIBUT...");
     const ROW_A: i32 = 1;
     const ROW_B: i32 = 2;
@@ -31,7 +33,7 @@ pub fn parse(path: &str) -> Result<AttributeAndTypeConverter, Box<dyn Error>> {
     let row_parser = RowParser::new(vec![
         // This row is used to create an Attribute instance.
         RowDefinition::new(ROW_A, Box::new(
-            AdvancedRowMatcher::new(r"^.{2} [0-9] [0-9 ]{3} [0-9 ]{2}$")?
+            AdvancedRowMatcher::new(r"^.{2} [0-9] [0-9 ]{3} [0-9 ]{2}$")
         ), vec![
             ColumnDefinition::new(1, 2, ExpectedType::String),
             ColumnDefinition::new(4, 4, ExpectedType::Integer16),
@@ -45,13 +47,13 @@ pub fn parse(path: &str) -> Result<AttributeAndTypeConverter, Box<dyn Error>> {
             ColumnDefinition::new(1, -1, ExpectedType::String),
         ]),
         // This row contains the description in a specific language.
-        RowDefinition::new(ROW_D, Box::new(AdvancedRowMatcher::new(r"^.{2} .+$")?), vec![
+        RowDefinition::new(ROW_D, Box::new(AdvancedRowMatcher::new(r"^.{2} .+$")), vec![
             ColumnDefinition::new(1, 2, ExpectedType::String),
             ColumnDefinition::new(4, -1, ExpectedType::String),
         ]),
     ]);
-    // The ATTRIBUT file is used instead of ATTRIBUT_* for simplicity's sake.
-    let parser = FileParser::new(&format!("{path}/ATTRIBUT"), row_parser)?;
+    // The descriptions are read from ATTRIBUT, the ATTRIBUT_* files provide the output texts (see load_localized_texts).
+    let parser = FileParser::new(source, "ATTRIBUT", row_parser, diagnostics)?;
 
     let auto_increment = AutoIncrement::new();
     let mut data = FxHashMap::default();
@@ -59,23 +61,90 @@ pub fn parse(path: &str) -> Result<AttributeAndTypeConverter, Box<dyn Error>> {
 
     let mut current_language = Language::default();
 
-    for x in parser.parse() {
-        let (id, _, values) = x?;
-        match id {
-            ROW_A => {
-                let attribute = create_instance(values, &auto_increment, &mut pk_type_converter);
-                data.insert(attribute.id(), attribute);
+    // The translations belong to the language set by the type C row preceding them.
+    parser
+        .parse()
+        .with_record_start(ROW_C)
+        .try_for_each_row(|(id, _, values)| {
+            match id {
+                ROW_A => {
+                    let attribute =
+                        create_instance(values, &auto_increment, &mut pk_type_converter);
+                    data.insert(attribute.id(), attribute);
+                }
+                ROW_B => {}
+                ROW_C => update_current_language(values, &mut current_language)?,
+                ROW_D => set_description(values, &pk_type_converter, &mut data, current_language)?,
+                _ => unreachable!(),
             }
-            ROW_B => {}
-            ROW_C => update_current_language(values, &mut current_language)?,
-            ROW_D => set_description(values, &pk_type_converter, &mut data, current_language)?,
-            _ => unreachable!(),
-        }
+            Ok(())
+        })?;
+
+    for language in [
+        Language::German,
+        Language::English,
+        Language::French,
+        Language::Italian,
+    ] {
+        load_localized_texts(source, diagnostics, &pk_type_converter, &mut data, language)?;
     }
 
     Ok((ResourceStorage::new(data), pk_type_converter))
 }
 
+/// The ATTRIBUT_* files contain the output texts and the partial/full stretch designations in a specific language.
+fn load_localized_texts(
+    source: &dyn HrdfSource,
+    diagnostics: &Diagnostics,
+    pk_type_converter: &FxHashMap<String, i32>,
+    data: &mut FxHashMap<i32, Attribute>,
+    language: Language,
+) -> Result<(), HrdfError> {
+    const ROW_A: i32 = 1;
+    const ROW_B: i32 = 2;
+    const ROW_C: i32 = 3;
+    const ROW_D: i32 = 4;
+
+    #[rustfmt::skip]
+    let row_parser = RowParser::new(vec![
+        // This row is ignored, the attributes are already defined by ATTRIBUT.
+        RowDefinition::new(ROW_A, Box::new(
+            AdvancedRowMatcher::new(r"^.{2} [0-9] [0-9 ]{3} [0-9 ]{2}$")
+        ), Vec::new()),
+        // This row contains the designations of the attributes to output for a partial and for a full stretch.
+        RowDefinition::new(ROW_B, Box::new(FastRowMatcher::new(1, 1, "#", true)), vec![
+            ColumnDefinition::new(3, 4, ExpectedType::String),
+            ColumnDefinition::new(6, 7, ExpectedType::String),
+            ColumnDefinition::new(9, 10, ExpectedType::String),
+        ]),
+        // This row starts the section of the output texts.
+        RowDefinition::new(ROW_C, Box::new(FastRowMatcher::new(1, 1, "<", true)), Vec::new()),
+        // This row contains the output text.
+        RowDefinition::new(ROW_D, Box::new(AdvancedRowMatcher::new(r"^.{2} .+$")), vec![
+            ColumnDefinition::new(1, 2, ExpectedType::String),
+            ColumnDefinition::new(4, -1, ExpectedType::String),
+        ]),
+    ]);
+    let filename = match language {
+        Language::German => "ATTRIBUT_DE",
+        Language::English => "ATTRIBUT_EN",
+        Language::French => "ATTRIBUT_FR",
+        Language::Italian => "ATTRIBUT_IT",
+    };
+    log::info!("Parsing {filename}...");
+    let parser = FileParser::new(source, filename, row_parser, diagnostics)?;
+
+    parser.parse().try_for_each_row(|(id, _, values)| {
+        match id {
+            ROW_A | ROW_C => {}
+            ROW_B => set_stretch_designations(values, pk_type_converter, data, language)?,
+            ROW_D => set_output_text(values, pk_type_converter, data, language)?,
+            _ => unreachable!(),
+        }
+        Ok(())
+    })
+}
+
 // ------------------------------------------------------------------------------------------------
 // --- Data Processing Functions
 // ------------------------------------------------------------------------------------------------
@@ -107,16 +176,42 @@ fn set_description(
     pk_type_converter: &FxHashMap<String, i32>,
     data: &mut FxHashMap<i32, Attribute>,
     language: Language,
-) -> Result<(), Box<dyn Error>> {
+) -> Result<(), RowError> {
     let legacy_id: String = values.remove(0).into();
     let description: String = values.remove(0).into();
 
-    let id = pk_type_converter
-        .get(&legacy_id)
-        .ok_or("Unknown legacy ID")?;
-    data.get_mut(id)
-        .ok_or("Unknown ID")?
-        .set_description(language, &description);
+    find_attribute(&legacy_id, pk_type_converter, data)?.set_description(language, &description);
+
+    Ok(())
+}
+
+fn set_stretch_designations(
+    mut values: Vec<ParsedValue>,
+    pk_type_converter: &FxHashMap<String, i32>,
+    data: &mut FxHashMap<i32, Attribute>,
+    language: Language,
+) -> Result<(), RowError> {
+    let legacy_id: String = values.remove(0).into();
+    let partial_stretch_designation: String = values.remove(0).into();
+    let full_stretch_designation: String = values.remove(0).into();
+
+    let attribute = find_attribute(&legacy_id, pk_type_converter, data)?;
+    attribute.set_partial_stretch_designation(language, &partial_stretch_designation);
+    attribute.set_full_stretch_designation(language, &full_stretch_designation);
+
+    Ok(())
+}
+
+fn set_output_text(
+    mut values: Vec<ParsedValue>,
+    pk_type_converter: &FxHashMap<String, i32>,
+    data: &mut FxHashMap<i32, Attribute>,
+    language: Language,
+) -> Result<(), RowError> {
+    let legacy_id: String = values.remove(0).into();
+    let output_text: String = values.remove(0).into();
+
+    find_attribute(&legacy_id, pk_type_converter, data)?.set_output_text(language, &output_text);
 
     Ok(())
 }
@@ -128,7 +223,7 @@ fn set_description(
 fn update_current_language(
     mut values: Vec<ParsedValue>,
     current_language: &mut Language,
-) -> Result<(), Box<dyn Error>> {
+) -> Result<(), RowError> {
     let language: String = values.remove(0).into();
     let language = &language[1..&language.len() - 1];
 
@@ -138,3 +233,66 @@ fn update_current_language(
 
     Ok(())
 }
+
+fn find_attribute<'a>(
+    legacy_id: &str,
+    pk_type_converter: &FxHashMap<String, i32>,
+    data: &'a mut FxHashMap<i32, Attribute>,
+) -> Result<&'a mut Attribute, RowError> {
+    let id = pk_type_converter
+        .get(legacy_id)
+        .ok_or_else(|| RowError::unknown_reference("attribute", legacy_id))?;
+    data.get_mut(id)
+        .ok_or_else(|| RowError::unknown_reference("attribute", id))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{error::ParsingMode, fixtures::MemorySource};
+
+    use super::*;
+
+    #[test]
+    fn localized_texts_are_parsed() {
+        let diagnostics = Diagnostics::new(ParsingMode::Strict, None);
+        let (attributes, pk_type_converter) = parse(&MemorySource::new(), &diagnostics).unwrap();
+        let attribute = attributes.find(pk_type_converter["VR"]).unwrap();
+
+        assert_eq!(attribute.designation(), "VR");
+        assert_eq!(
+            attribute.description(Language::German),
+            Some("Velos: Reservierung obligatorisch")
+        );
+        assert_eq!(
+            attribute.description(Language::French),
+            Some("Velos: reservation")
+        );
+        assert_eq!(attribute.description(Language::Italian), None);
+
+        for (language, suffix) in [
+            (Language::German, "DE"),
+            (Language::English, "EN"),
+            (Language::French, "FR"),
+            (Language::Italian, "IT"),
+        ] {
+            assert_eq!(
+                attribute.output_text(language),
+                Some(format!("Bike reservation required ({suffix})").as_str())
+            );
+            assert_eq!(attribute.partial_stretch_designation(language), Some("VR"));
+            assert_eq!(attribute.full_stretch_designation(language), Some("Y"));
+        }
+    }
+
+    #[test]
+    fn unknown_attribute_is_located() {
+        let attribut_en = format!("{}XX Unknown\n", MemorySource::new().file("ATTRIBUT_EN"));
+        let source = MemorySource::new().with("ATTRIBUT_EN", &attribut_en);
+
+        let error = parse(&source, &Diagnostics::new(ParsingMode::Strict, None)).unwrap_err();
+        assert!(matches!(
+            error,
+            HrdfError::UnknownReference { ref file, line: 8, .. } if file == "ATTRIBUT_EN"
+        ));
+    }
+}
@@ -1,15 +1,18 @@
 // 1 file(s).
 // File(s) read by the parser:
 // BITFELD
-use std::error::Error;
-
 use crate::{
+    error::{Diagnostics, HrdfError, RowError},
     models::{BitField, Model},
     parsing::{ColumnDefinition, ExpectedType, FileParser, ParsedValue, RowDefinition, RowParser},
+    source::HrdfSource,
     storage::ResourceStorage,
 };
 
-pub fn parse(path: &str) -> Result<ResourceStorage<BitField>, Box<dyn Error>> {
+pub fn parse(
+    source: &dyn HrdfSource,
+    diagnostics: &Diagnostics,
+) -> Result<ResourceStorage<BitField>, HrdfError> {
     log::info!("Parsing BITFELD...");
     #[rustfmt::skip]
     let row_parser = RowParser::new(vec![
@@ -19,11 +22,11 @@ pub fn parse(path: &str) -> Result<ResourceStorage<BitField>, Box<dyn Error>> {
             ColumnDefinition::new(8, 103, ExpectedType::String),
         ]),
     ]);
-    let parser = FileParser::new(&format!("{path}/BITFELD"), row_parser)?;
+    let parser = FileParser::new(source, "BITFELD", row_parser, diagnostics)?;
 
     let data = parser
         .parse()
-        .map(|x| x.and_then(|(_, _, values)| create_instance(values)))
+        .map_rows(|(_, _, values)| create_instance(values))
         .collect::<Result<Vec<_>, _>>()?;
     let data = BitField::vec_to_map(data);
 
@@ -34,7 +37,7 @@ pub fn parse(path: &str) -> Result<ResourceStorage<BitField>, Box<dyn Error>> {
 // --- Data Processing Functions
 // ------------------------------------------------------------------------------------------------
 
-fn create_instance(mut values: Vec<ParsedValue>) -> Result<BitField, Box<dyn Error>> {
+fn create_instance(mut values: Vec<ParsedValue>) -> Result<BitField, RowError> {
     let id: i32 = values.remove(0).into();
     let hex_number: String = values.remove(0).into();
 
@@ -48,13 +51,13 @@ fn create_instance(mut values: Vec<ParsedValue>) -> Result<BitField, Box<dyn Err
 // ------------------------------------------------------------------------------------------------
 
 /// Converts a hexadecimal number into a list of where each item represents a bit.
-fn convert_hex_number_to_bits(hex_number: String) -> Result<Vec<u8>, Box<dyn Error>> {
+fn convert_hex_number_to_bits(hex_number: String) -> Result<Vec<u8>, RowError> {
     let result = hex_number
         .chars()
         .map(|hex_digit| {
             hex_digit
                 .to_digit(16)
-                .ok_or("Invalid hexadecimal digit")
+                .ok_or_else(|| RowError::invalid("Invalid hexadecimal digit."))
                 .map(|val| (0..4).rev().map(move |i| ((val >> i) & 1) as u8))
         })
         .collect::<Result<Vec<_>, _>>()?
@@ -0,0 +1,79 @@
+// 1 file(s).
+// File(s) read by the parser:
+// GRENZHLT
+use rustc_hash::FxHashMap;
+
+use crate::{
+    error::{Diagnostics, HrdfError},
+    models::{BorderPoint, Model},
+    parsing::{ColumnDefinition, ExpectedType, FileParser, ParsedValue, RowDefinition, RowParser},
+    source::HrdfSource,
+    storage::ResourceStorage,
+};
+
+pub fn parse(
+    source: &dyn HrdfSource,
+    diagnostics: &Diagnostics,
+) -> Result<ResourceStorage<BorderPoint>, HrdfError> {
+    log::info!("Parsing GRENZHLT...");
+    #[rustfmt::skip]
+    let row_parser = RowParser::new(vec![
+        // This row is used to create a BorderPoint instance.
+        RowDefinition::from(vec![
+            ColumnDefinition::new(1, 7, ExpectedType::Integer32),
+            ColumnDefinition::new(9, -1, ExpectedType::String),
+        ]),
+    ]);
+    // Some HRDF data sets don't have any border points, in which case GRENZHLT may be missing.
+    let parser = match FileParser::new(source, "GRENZHLT", row_parser, diagnostics) {
+        Ok(parser) => parser,
+        Err(HrdfError::MissingFile(_)) => return Ok(ResourceStorage::new(FxHashMap::default())),
+        Err(e) => return Err(e),
+    };
+
+    let data = parser
+        .parse()
+        .map_rows(|(_, _, values)| Ok(create_instance(values)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let data = BorderPoint::vec_to_map(data);
+
+    Ok(ResourceStorage::new(data))
+}
+
+// ------------------------------------------------------------------------------------------------
+// --- Data Processing Functions
+// ------------------------------------------------------------------------------------------------
+
+fn create_instance(mut values: Vec<ParsedValue>) -> BorderPoint {
+    let stop_id: i32 = values.remove(0).into();
+    let designation: String = values.remove(0).into();
+
+    BorderPoint::new(stop_id, designation)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{error::ParsingMode, fixtures::MemorySource};
+
+    use super::*;
+
+    #[test]
+    fn missing_file_is_empty() {
+        let diagnostics = Diagnostics::new(ParsingMode::Strict, None);
+        let source = MemorySource::new().without("GRENZHLT");
+
+        let border_points = parse(&source, &diagnostics).unwrap();
+        assert!(border_points.entries().is_empty());
+        assert!(diagnostics.into_entries().is_empty());
+    }
+
+    #[test]
+    fn border_points_are_parsed() {
+        let diagnostics = Diagnostics::new(ParsingMode::Strict, None);
+        let source = MemorySource::new().with("GRENZHLT", "8500030 Bern\n");
+
+        let border_points = parse(&source, &diagnostics).unwrap();
+        assert_eq!(border_points.entries().len(), 1);
+        assert!(border_points.find(8500030).is_some());
+    }
+}
@@ -1,19 +1,22 @@
 // 1 file(s).
 // File(s) read by the parser:
 // RICHTUNG
-use std::error::Error;
-
 use rustc_hash::FxHashMap;
 
 use crate::{
+    error::{Diagnostics, HrdfError, RowError},
     models::{Direction, Model},
     parsing::{ColumnDefinition, ExpectedType, FileParser, ParsedValue, RowDefinition, RowParser},
+    source::HrdfSource,
     storage::ResourceStorage,
 };
 
 type DirectionAndTypeConverter = (ResourceStorage<Direction>, FxHashMap<String, i32>);
 
-pub fn parse(path: &str) -> Result<DirectionAndTypeConverter, Box<dyn Error>> {
+pub fn parse(
+    source: &dyn HrdfSource,
+    diagnostics: &Diagnostics,
+) -> Result<DirectionAndTypeConverter, HrdfError> {
     log::info!("Parsing RICHTUNG...");
     #[rustfmt::skip]
     let row_parser = RowParser::new(vec![
@@ -23,13 +26,13 @@ pub fn parse(path: &str) -> Result<DirectionAndTypeConverter, Box<dyn Error>> {
             ColumnDefinition::new(9, -1, ExpectedType::String),
         ]),
     ]);
-    let parser = FileParser::new(&format!("{path}/RICHTUNG"), row_parser)?;
+    let parser = FileParser::new(source, "RICHTUNG", row_parser, diagnostics)?;
 
     let mut pk_type_converter = FxHashMap::default();
 
     let data = parser
         .parse()
-        .map(|x| x.and_then(|(_, _, values)| create_instance(values, &mut pk_type_converter)))
+        .map_rows(|(_, _, values)| create_instance(values, &mut pk_type_converter))
         .collect::<Result<Vec<_>, _>>()?;
     let data = Direction::vec_to_map(data);
 
@@ -43,7 +46,7 @@ pub fn parse(path: &str) -> Result<DirectionAndTypeConverter, Box<dyn Error>> {
 fn create_instance(
     mut values: Vec<ParsedValue>,
     pk_type_converter: &mut FxHashMap<String, i32>,
-) -> Result<Direction, Box<dyn Error>> {
+) -> Result<Direction, RowError> {
     let legacy_id: String = values.remove(0).into();
     let name: String = values.remove(0).into();
 
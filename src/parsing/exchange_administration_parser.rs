@@ -1,16 +1,19 @@
 // 1 file(s).
 // File(s) read by the parser:
 // UMSTEIGV
-use std::error::Error;
-
 use crate::{
+    error::{Diagnostics, HrdfError},
     models::{ExchangeTimeAdministration, Model},
     parsing::{ColumnDefinition, ExpectedType, FileParser, ParsedValue, RowDefinition, RowParser},
+    source::HrdfSource,
     storage::ResourceStorage,
     utils::AutoIncrement,
 };
 
-pub fn parse(path: &str) -> Result<ResourceStorage<ExchangeTimeAdministration>, Box<dyn Error>> {
+pub fn parse(
+    source: &dyn HrdfSource,
+    diagnostics: &Diagnostics,
+) -> Result<ResourceStorage<ExchangeTimeAdministration>, HrdfError> {
     log::info!("Parsing UMSTEIGV...");
     #[rustfmt::skip]
     let row_parser = RowParser::new(vec![
@@ -22,7 +25,7 @@ pub fn parse(path: &str) -> Result<ResourceStorage<ExchangeTimeAdministration>,
             ColumnDefinition::new(23, 24, ExpectedType::Integer16),
         ]),
     ]);
-    let parser = FileParser::new(&format!("{path}/UMSTEIGV"), row_parser)?;
+    let parser = FileParser::new(source, "UMSTEIGV", row_parser, diagnostics)?;
 
     let auto_increment = AutoIncrement::new();
 
@@ -1,21 +1,22 @@
 // 1 file(s).
 // File(s) read by the parser:
 // UMSTEIGZ
-use std::error::Error;
-
 use rustc_hash::FxHashMap;
 
 use crate::{
+    error::{Diagnostics, HrdfError, RowError},
     models::{ExchangeTimeJourney, Model},
     parsing::{ColumnDefinition, ExpectedType, FileParser, ParsedValue, RowDefinition, RowParser},
+    source::HrdfSource,
     storage::ResourceStorage,
     utils::AutoIncrement,
 };
 
 pub fn parse(
-    path: &str,
+    source: &dyn HrdfSource,
+    diagnostics: &Diagnostics,
     journeys_pk_type_converter: &FxHashMap<(i32, String), i32>,
-) -> Result<ResourceStorage<ExchangeTimeJourney>, Box<dyn Error>> {
+) -> Result<ResourceStorage<ExchangeTimeJourney>, HrdfError> {
     log::info!("Parsing UMSTEIGZ...");
     #[rustfmt::skip]
     let row_parser = RowParser::new(vec![
@@ -31,16 +32,14 @@ pub fn parse(
             ColumnDefinition::new(42, 47, ExpectedType::OptionInteger32),
         ]),
     ]);
-    let parser = FileParser::new(&format!("{path}/UMSTEIGZ"), row_parser)?;
+    let parser = FileParser::new(source, "UMSTEIGZ", row_parser, diagnostics)?;
 
     let auto_increment = AutoIncrement::new();
 
     let data = parser
         .parse()
-        .map(|x| {
-            x.and_then(|(_, _, values)| {
-                create_instance(values, &auto_increment, journeys_pk_type_converter)
-            })
+        .map_rows(|(_, _, values)| {
+            create_instance(values, &auto_increment, journeys_pk_type_converter)
         })
         .collect::<Result<Vec<_>, _>>()?;
     let data = ExchangeTimeJourney::vec_to_map(data);
@@ -56,7 +55,7 @@ fn create_instance(
     mut values: Vec<ParsedValue>,
     auto_increment: &AutoIncrement,
     journeys_pk_type_converter: &FxHashMap<(i32, String), i32>,
-) -> Result<ExchangeTimeJourney, Box<dyn Error>> {
+) -> Result<ExchangeTimeJourney, RowError> {
     let stop_id: i32 = values.remove(0).into();
     let journey_id_1: i32 = values.remove(0).into();
     let administration_1: String = values.remove(0).into();
@@ -66,13 +65,15 @@ fn create_instance(
     let is_guaranteed: String = values.remove(0).into();
     let bit_field_id: Option<i32> = values.remove(0).into();
 
+    let journey_1 = (journey_id_1, administration_1);
     let journey_id_1 = *journeys_pk_type_converter
-        .get(&(journey_id_1, administration_1))
-        .ok_or("Unknown legacy ID")?;
+        .get(&journey_1)
+        .ok_or_else(|| RowError::unknown_reference("journey", &journey_1))?;
 
+    let journey_2 = (journey_id_2, administration_2);
     let journey_id_2 = *journeys_pk_type_converter
-        .get(&(journey_id_2, administration_2))
-        .ok_or("Unknown legacy ID")?;
+        .get(&journey_2)
+        .ok_or_else(|| RowError::unknown_reference("journey", &journey_2))?;
 
     let is_guaranteed = is_guaranteed == "!";
 
@@ -1,21 +1,24 @@
 // 1 file(s).
 // File(s) read by the parser:
 // UMSTEIGL
-use std::{error::Error, str::FromStr};
+use std::str::FromStr;
 
 use rustc_hash::FxHashMap;
 
 use crate::{
+    error::{Diagnostics, HrdfError, RowError},
     models::{DirectionType, ExchangeTimeLine, LineInfo, Model},
     parsing::{ColumnDefinition, ExpectedType, FileParser, ParsedValue, RowDefinition, RowParser},
+    source::HrdfSource,
     storage::ResourceStorage,
     utils::AutoIncrement,
 };
 
 pub fn parse(
-    path: &str,
+    source: &dyn HrdfSource,
+    diagnostics: &Diagnostics,
     transport_types_pk_type_converter: &FxHashMap<String, i32>,
-) -> Result<ResourceStorage<ExchangeTimeLine>, Box<dyn Error>> {
+) -> Result<ResourceStorage<ExchangeTimeLine>, HrdfError> {
     log::info!("Parsing UMSTEIGL...");
     #[rustfmt::skip]
     let row_parser = RowParser::new(vec![
@@ -34,16 +37,14 @@ pub fn parse(
             ColumnDefinition::new(56, 56, ExpectedType::String),
         ]),
     ]);
-    let parser = FileParser::new(&format!("{path}/UMSTEIGL"), row_parser)?;
+    let parser = FileParser::new(source, "UMSTEIGL", row_parser, diagnostics)?;
 
     let auto_increment = AutoIncrement::new();
 
     let data = parser
         .parse()
-        .map(|x| {
-            x.and_then(|(_, _, values)| {
-                create_instance(values, &auto_increment, transport_types_pk_type_converter)
-            })
+        .map_rows(|(_, _, values)| {
+            create_instance(values, &auto_increment, transport_types_pk_type_converter)
         })
         .collect::<Result<Vec<_>, _>>()?;
     let data = ExchangeTimeLine::vec_to_map(data);
@@ -59,7 +60,7 @@ fn create_instance(
     mut values: Vec<ParsedValue>,
     auto_increment: &AutoIncrement,
     transport_types_pk_type_converter: &FxHashMap<String, i32>,
-) -> Result<ExchangeTimeLine, Box<dyn Error>> {
+) -> Result<ExchangeTimeLine, RowError> {
     let stop_id: Option<i32> = values.remove(0).into();
     let administration_1: String = values.remove(0).into();
     let transport_type_id_1: String = values.remove(0).into();
@@ -74,7 +75,7 @@ fn create_instance(
 
     let transport_type_id_1 = *transport_types_pk_type_converter
         .get(&transport_type_id_1)
-        .ok_or("Unknown legacy ID")?;
+        .ok_or_else(|| RowError::unknown_reference("transport type", &transport_type_id_1))?;
 
     let line_id_1 = if line_id_1 == "*" {
         None
@@ -90,7 +91,7 @@ fn create_instance(
 
     let transport_type_id_2 = *transport_types_pk_type_converter
         .get(&transport_type_id_2)
-        .ok_or("Unknown legacy ID")?;
+        .ok_or_else(|| RowError::unknown_reference("transport type", &transport_type_id_2))?;
 
     let line_id_2 = if line_id_2 == "*" {
         None
@@ -1,19 +1,24 @@
 // 1 file(s).
 // File(s) read by the parser:
 // FEIERTAG
-use std::{error::Error, str::FromStr};
+use std::str::FromStr;
 
 use chrono::NaiveDate;
 use rustc_hash::FxHashMap;
 
 use crate::{
+    error::{Diagnostics, HrdfError, RowError},
     models::{Holiday, Language, Model},
     parsing::{ColumnDefinition, ExpectedType, FileParser, ParsedValue, RowDefinition, RowParser},
+    source::HrdfSource,
     storage::ResourceStorage,
     utils::AutoIncrement,
 };
 
-pub fn parse(path: &str) -> Result<ResourceStorage<Holiday>, Box<dyn Error>> {
+pub fn parse(
+    source: &dyn HrdfSource,
+    diagnostics: &Diagnostics,
+) -> Result<ResourceStorage<Holiday>, HrdfError> {
     log::info!("Parsing FEIERTAG...");
     #[rustfmt::skip]
     let row_parser = RowParser::new(vec![
@@ -23,13 +28,13 @@ pub fn parse(path: &str) -> Result<ResourceStorage<Holiday>, Box<dyn Error>> {
             ColumnDefinition::new(12, -1, ExpectedType::String),
         ]),
     ]);
-    let parser = FileParser::new(&format!("{path}/FEIERTAG"), row_parser)?;
+    let parser = FileParser::new(source, "FEIERTAG", row_parser, diagnostics)?;
 
     let auto_increment = AutoIncrement::new();
 
     let data = parser
         .parse()
-        .map(|x| x.and_then(|(_, _, values)| create_instance(values, &auto_increment)))
+        .map_rows(|(_, _, values)| create_instance(values, &auto_increment))
         .collect::<Result<Vec<_>, _>>()?;
     let data = Holiday::vec_to_map(data);
 
@@ -43,7 +48,7 @@ pub fn parse(path: &str) -> Result<ResourceStorage<Holiday>, Box<dyn Error>> {
 fn create_instance(
     mut values: Vec<ParsedValue>,
     auto_increment: &AutoIncrement,
-) -> Result<Holiday, Box<dyn Error>> {
+) -> Result<Holiday, RowError> {
     let date: String = values.remove(0).into();
     let name_translations: String = values.remove(0).into();
 
@@ -59,15 +64,21 @@ fn create_instance(
 
 fn parse_name_translations(
     name_translations: String,
-) -> Result<FxHashMap<Language, String>, Box<dyn Error>> {
+) -> Result<FxHashMap<Language, String>, RowError> {
     name_translations
         .split('>')
         .filter(|&s| !s.is_empty())
-        .map(|s| -> Result<(Language, String), Box<dyn Error>> {
+        .map(|s| -> Result<(Language, String), RowError> {
             let mut parts = s.split('<');
 
-            let v = parts.next().ok_or("Missing value part")?.to_string();
-            let k = parts.next().ok_or("Missing value part")?.to_string();
+            let v = parts
+                .next()
+                .ok_or_else(|| RowError::invalid("Missing value part."))?
+                .to_string();
+            let k = parts
+                .next()
+                .ok_or_else(|| RowError::invalid("Missing value part."))?
+                .to_string();
             let k = Language::from_str(&k)?;
 
             Ok((k, v))
@@ -1,17 +1,20 @@
 // 4 file(s).
 // File(s) read by the parser:
 // INFOTEXT_DE, INFOTEXT_EN, INFOTEXT_FR, INFOTEXT_IT
-use std::error::Error;
-
 use rustc_hash::FxHashMap;
 
 use crate::{
+    error::{Diagnostics, HrdfError, RowError},
     models::{InformationText, Language, Model},
     parsing::{ColumnDefinition, ExpectedType, FileParser, ParsedValue, RowDefinition, RowParser},
+    source::HrdfSource,
     storage::ResourceStorage,
 };
 
-pub fn parse(path: &str) -> Result<ResourceStorage<InformationText>, Box<dyn Error>> {
+pub fn parse(
+    source: &dyn HrdfSource,
+    diagnostics: &Diagnostics,
+) -> Result<ResourceStorage<InformationText>, HrdfError> {
     log::info!("Parsing INFOTEXT_DE...");
     log::info!("Parsing INFOTEXT_EN...");
     log::info!("Parsing INFOTEXT_FR...");
@@ -24,7 +27,7 @@ pub fn parse(path: &str) -> Result<ResourceStorage<InformationText>, Box<dyn Err
             ColumnDefinition::new(1, 9, ExpectedType::Integer32),
         ]),
     ]);
-    let parser = FileParser::new(&format!("{path}/INFOTEXT_DE"), row_parser)?;
+    let parser = FileParser::new(source, "INFOTEXT_DE", row_parser, diagnostics)?;
 
     let data = parser
         .parse()
@@ -32,19 +35,20 @@ pub fn parse(path: &str) -> Result<ResourceStorage<InformationText>, Box<dyn Err
         .collect::<Result<Vec<_>, _>>()?;
     let mut data = InformationText::vec_to_map(data);
 
-    load_content(path, &mut data, Language::German)?;
-    load_content(path, &mut data, Language::English)?;
-    load_content(path, &mut data, Language::French)?;
-    load_content(path, &mut data, Language::Italian)?;
+    load_content(source, diagnostics, &mut data, Language::German)?;
+    load_content(source, diagnostics, &mut data, Language::English)?;
+    load_content(source, diagnostics, &mut data, Language::French)?;
+    load_content(source, diagnostics, &mut data, Language::Italian)?;
 
     Ok(ResourceStorage::new(data))
 }
 
 fn load_content(
-    path: &str,
+    source: &dyn HrdfSource,
+    diagnostics: &Diagnostics,
     data: &mut FxHashMap<i32, InformationText>,
     language: Language,
-) -> Result<(), Box<dyn Error>> {
+) -> Result<(), HrdfError> {
     #[rustfmt::skip]
     let row_parser = RowParser::new(vec![
         // This row contains the content in a specific language.
@@ -59,13 +63,11 @@ fn load_content(
         Language::French => "INFOTEXT_FR",
         Language::Italian => "INFOTEXT_IT",
     };
-    let parser = FileParser::new(&format!("{path}/{filename}"), row_parser)?;
+    let parser = FileParser::new(source, filename, row_parser, diagnostics)?;
 
-    parser.parse().try_for_each(|x| {
-        let (_, _, values) = x?;
-        set_content(values, data, language)?;
-        Ok(())
-    })
+    parser
+        .parse()
+        .try_for_each_row(|(_, _, values)| set_content(values, data, language))
 }
 
 // ------------------------------------------------------------------------------------------------
@@ -82,12 +84,12 @@ fn set_content(
     mut values: Vec<ParsedValue>,
     data: &mut FxHashMap<i32, InformationText>,
     language: Language,
-) -> Result<(), Box<dyn Error>> {
+) -> Result<(), RowError> {
     let id: i32 = values.remove(0).into();
     let description: String = values.remove(0).into();
 
     data.get_mut(&id)
-        .ok_or("Unknown ID")?
+        .ok_or_else(|| RowError::unknown_reference("information text", id))?
         .set_content(language, &description);
 
     Ok(())
@@ -1,29 +1,34 @@
 // 1 file(s).
 // File(s) read by the parser:
 // FPLAN
-use std::error::Error;
+use std::str::FromStr;
 
-use chrono::NaiveTime;
 use rustc_hash::FxHashMap;
 
 use crate::{
-    models::{Journey, JourneyMetadataEntry, JourneyMetadataType, JourneyRouteEntry, Model},
+    error::{Diagnostics, HrdfError, RowError},
+    models::{
+        DirectionType, Journey, JourneyMetadata, JourneyMetadataEntry, JourneyRouteEntry,
+        LineReference, Model,
+    },
     parsing::{
         ColumnDefinition, ExpectedType, FastRowMatcher, FileParser, ParsedValue, RowDefinition,
         RowParser,
     },
+    source::HrdfSource,
     storage::ResourceStorage,
-    utils::{create_time_from_value, AutoIncrement},
+    utils::{create_time_and_day_offset, AutoIncrement},
 };
 
 type JourneyAndTypeConverter = (ResourceStorage<Journey>, FxHashMap<(i32, String), i32>);
 
 pub fn parse(
-    path: &str,
+    source: &dyn HrdfSource,
+    diagnostics: &Diagnostics,
     transport_types_pk_type_converter: &FxHashMap<String, i32>,
     attributes_pk_type_converter: &FxHashMap<String, i32>,
     directions_pk_type_converter: &FxHashMap<String, i32>,
-) -> Result<JourneyAndTypeConverter, Box<dyn Error>> {
+) -> Result<JourneyAndTypeConverter, HrdfError> {
     log::info!("Parsing FPLAN...");
     const ROW_A: i32 = 1;
     const ROW_B: i32 = 2;
@@ -56,6 +61,9 @@ pub fn parse(
             ColumnDefinition::new(4, 5, ExpectedType::String),
             ColumnDefinition::new(7, 13, ExpectedType::OptionInteger32),
             ColumnDefinition::new(15, 21, ExpectedType::OptionInteger32),
+            ColumnDefinition::new(23, 28, ExpectedType::OptionInteger32),
+            ColumnDefinition::new(30, 35, ExpectedType::OptionInteger32),
+            ColumnDefinition::new(37, 42, ExpectedType::OptionInteger32),
         ]),
         RowDefinition::new(ROW_E, Box::new(FastRowMatcher::new(1, 2, "*I", true)), vec![
             ColumnDefinition::new(4, 5, ExpectedType::String),
@@ -84,14 +92,14 @@ pub fn parse(
         // *CI
         RowDefinition::new(ROW_H, Box::new(FastRowMatcher::new(1, 3, "*CI", true)), vec![
             ColumnDefinition::new(1, 3, ExpectedType::String),
-            ColumnDefinition::new(5, 8, ExpectedType::Integer32),
+            ColumnDefinition::new(5, 8, ExpectedType::Integer16),
             ColumnDefinition::new(10, 16, ExpectedType::OptionInteger32),
             ColumnDefinition::new(18, 24, ExpectedType::OptionInteger32),
         ]),
         // *CO
         RowDefinition::new(ROW_H, Box::new(FastRowMatcher::new(1, 3, "*CO", true)), vec![
             ColumnDefinition::new(1, 3, ExpectedType::String),
-            ColumnDefinition::new(5, 8, ExpectedType::Integer32),
+            ColumnDefinition::new(5, 8, ExpectedType::Integer16),
             ColumnDefinition::new(10, 16, ExpectedType::OptionInteger32),
             ColumnDefinition::new(18, 24, ExpectedType::OptionInteger32),
         ]),
@@ -101,39 +109,45 @@ pub fn parse(
             ColumnDefinition::new(37, 42, ExpectedType::OptionInteger32),
         ]),
     ]);
-    let parser = FileParser::new(&format!("{path}/FPLAN"), row_parser)?;
+    let parser = FileParser::new(source, "FPLAN", row_parser, diagnostics)?;
 
     let auto_increment = AutoIncrement::new();
     let mut data = Vec::new();
     let mut pk_type_converter = FxHashMap::default();
 
-    for x in parser.parse() {
-        let (id, _, values) = x?;
-        match id {
-            ROW_A => data.push(create_instance(
-                values,
-                &auto_increment,
-                &mut pk_type_converter,
-            )),
-            _ => {
-                let journey = data.last_mut().ok_or("Type A row missing.")?;
-
-                match id {
-                    ROW_B => {
-                        set_transport_type(values, journey, transport_types_pk_type_converter)?
+    // A journey spans from its type A row to the next one.
+    parser
+        .parse()
+        .with_record_start(ROW_A)
+        .try_for_each_row(|(id, _, values)| {
+            match id {
+                ROW_A => data.push(create_instance(
+                    values,
+                    &auto_increment,
+                    &mut pk_type_converter,
+                )),
+                _ => {
+                    let journey = data
+                        .last_mut()
+                        .ok_or_else(|| RowError::invalid("Type A row missing."))?;
+
+                    match id {
+                        ROW_B => {
+                            set_transport_type(values, journey, transport_types_pk_type_converter)?
+                        }
+                        ROW_C => set_bit_field(values, journey),
+                        ROW_D => add_attribute(values, journey, attributes_pk_type_converter)?,
+                        ROW_E => add_information_text(values, journey),
+                        ROW_F => set_line(values, journey)?,
+                        ROW_G => set_direction(values, journey, directions_pk_type_converter)?,
+                        ROW_H => set_boarding_or_disembarking_exchange_time(values, journey),
+                        ROW_I => add_route_entry(values, journey),
+                        _ => unreachable!(),
                     }
-                    ROW_C => set_bit_field(values, journey),
-                    ROW_D => add_attribute(values, journey, attributes_pk_type_converter)?,
-                    ROW_E => add_information_text(values, journey),
-                    ROW_F => set_line(values, journey)?,
-                    ROW_G => set_direction(values, journey, directions_pk_type_converter)?,
-                    ROW_H => set_boarding_or_disembarking_exchange_time(values, journey),
-                    ROW_I => add_route_entry(values, journey),
-                    _ => unreachable!(),
                 }
             }
-        }
-    }
+            Ok(())
+        })?;
 
     let data = Journey::vec_to_map(data);
 
@@ -162,28 +176,23 @@ fn set_transport_type(
     mut values: Vec<ParsedValue>,
     journey: &mut Journey,
     transport_types_pk_type_converter: &FxHashMap<String, i32>,
-) -> Result<(), Box<dyn Error>> {
+) -> Result<(), RowError> {
     let designation: String = values.remove(0).into();
     let from_stop_id: Option<i32> = values.remove(0).into();
     let until_stop_id: Option<i32> = values.remove(0).into();
 
     let transport_type_id = *transport_types_pk_type_converter
         .get(&designation)
-        .ok_or("Unknown legacy ID")?;
-
-    journey.add_metadata_entry(
-        JourneyMetadataType::TransportType,
-        JourneyMetadataEntry::new(
-            from_stop_id,
-            until_stop_id,
-            Some(transport_type_id),
-            None,
-            None,
-            None,
-            None,
-            None,
-        ),
-    );
+        .ok_or_else(|| RowError::unknown_reference("transport type", &designation))?;
+
+    journey.add_metadata_entry(JourneyMetadataEntry::new(
+        from_stop_id,
+        until_stop_id,
+        None,
+        None,
+        None,
+        JourneyMetadata::TransportType { transport_type_id },
+    ));
 
     Ok(())
 }
@@ -193,47 +202,40 @@ fn set_bit_field(mut values: Vec<ParsedValue>, journey: &mut Journey) {
     let until_stop_id: Option<i32> = values.remove(0).into();
     let bit_field_id: Option<i32> = values.remove(0).into();
 
-    journey.add_metadata_entry(
-        JourneyMetadataType::BitField,
-        JourneyMetadataEntry::new(
-            from_stop_id,
-            until_stop_id,
-            None,
-            bit_field_id,
-            None,
-            None,
-            None,
-            None,
-        ),
-    );
+    journey.add_metadata_entry(JourneyMetadataEntry::new(
+        from_stop_id,
+        until_stop_id,
+        bit_field_id,
+        None,
+        None,
+        JourneyMetadata::BitField,
+    ));
 }
 
 fn add_attribute(
     mut values: Vec<ParsedValue>,
     journey: &mut Journey,
     attributes_pk_type_converter: &FxHashMap<String, i32>,
-) -> Result<(), Box<dyn Error>> {
+) -> Result<(), RowError> {
     let designation: String = values.remove(0).into();
     let from_stop_id: Option<i32> = values.remove(0).into();
     let until_stop_id: Option<i32> = values.remove(0).into();
+    let bit_field_id: Option<i32> = values.remove(0).into();
+    let departure_time: Option<i32> = values.remove(0).into();
+    let arrival_time: Option<i32> = values.remove(0).into();
 
     let attribute_id = *attributes_pk_type_converter
         .get(&designation)
-        .ok_or("Unknown legacy ID")?;
-
-    journey.add_metadata_entry(
-        JourneyMetadataType::Attribute,
-        JourneyMetadataEntry::new(
-            from_stop_id,
-            until_stop_id,
-            Some(attribute_id),
-            None,
-            None,
-            None,
-            None,
-            None,
-        ),
-    );
+        .ok_or_else(|| RowError::unknown_reference("attribute", &designation))?;
+
+    journey.add_metadata_entry(JourneyMetadataEntry::new(
+        from_stop_id,
+        until_stop_id,
+        bit_field_id,
+        create_minutes_from_value(departure_time),
+        create_minutes_from_value(arrival_time),
+        JourneyMetadata::Attribute { attribute_id },
+    ));
 
     Ok(())
 }
@@ -247,57 +249,44 @@ fn add_information_text(mut values: Vec<ParsedValue>, journey: &mut Journey) {
     let departure_time: Option<i32> = values.remove(0).into();
     let arrival_time: Option<i32> = values.remove(0).into();
 
-    let arrival_time = create_time(arrival_time);
-    let departure_time = create_time(departure_time);
-
-    journey.add_metadata_entry(
-        JourneyMetadataType::InformationText,
-        JourneyMetadataEntry::new(
-            from_stop_id,
-            until_stop_id,
-            Some(information_text_id),
-            bit_field_id,
-            departure_time,
-            arrival_time,
-            Some(code),
-            None,
-        ),
-    );
+    journey.add_metadata_entry(JourneyMetadataEntry::new(
+        from_stop_id,
+        until_stop_id,
+        bit_field_id,
+        create_minutes_from_value(departure_time),
+        create_minutes_from_value(arrival_time),
+        JourneyMetadata::InformationText {
+            code,
+            information_text_id,
+        },
+    ));
 }
 
-fn set_line(mut values: Vec<ParsedValue>, journey: &mut Journey) -> Result<(), Box<dyn Error>> {
+fn set_line(mut values: Vec<ParsedValue>, journey: &mut Journey) -> Result<(), RowError> {
     let line_designation: String = values.remove(0).into();
     let from_stop_id: Option<i32> = values.remove(0).into();
     let until_stop_id: Option<i32> = values.remove(0).into();
     let departure_time: Option<i32> = values.remove(0).into();
     let arrival_time: Option<i32> = values.remove(0).into();
 
-    let arrival_time = create_time(arrival_time);
-    let departure_time = create_time(departure_time);
-
     let line_designation_first_char = line_designation
         .chars()
         .next()
-        .ok_or("Missing designation")?;
-    let (resource_id, extra_field_1) = if line_designation_first_char == '#' {
-        (Some(line_designation[1..].parse::<i32>()?), None)
+        .ok_or_else(|| RowError::invalid("Missing designation."))?;
+    let line = if line_designation_first_char == '#' {
+        LineReference::Id(line_designation[1..].parse::<i32>()?)
     } else {
-        (None, Some(line_designation))
+        LineReference::Designation(line_designation)
     };
 
-    journey.add_metadata_entry(
-        JourneyMetadataType::Line,
-        JourneyMetadataEntry::new(
-            from_stop_id,
-            until_stop_id,
-            resource_id,
-            None,
-            departure_time,
-            arrival_time,
-            extra_field_1,
-            None,
-        ),
-    );
+    journey.add_metadata_entry(JourneyMetadataEntry::new(
+        from_stop_id,
+        until_stop_id,
+        None,
+        create_minutes_from_value(departure_time),
+        create_minutes_from_value(arrival_time),
+        JourneyMetadata::Line(line),
+    ));
 
     Ok(())
 }
@@ -306,7 +295,7 @@ fn set_direction(
     mut values: Vec<ParsedValue>,
     journey: &mut Journey,
     directions_pk_type_converter: &FxHashMap<String, i32>,
-) -> Result<(), Box<dyn Error>> {
+) -> Result<(), RowError> {
     let direction_type: String = values.remove(0).into();
     let direction_id: String = values.remove(0).into();
     let from_stop_id: Option<i32> = values.remove(0).into();
@@ -314,60 +303,50 @@ fn set_direction(
     let departure_time: Option<i32> = values.remove(0).into();
     let arrival_time: Option<i32> = values.remove(0).into();
 
-    let arrival_time = create_time(arrival_time);
-    let departure_time = create_time(departure_time);
-
     let direction_id = if direction_id.is_empty() {
         None
     } else {
         let id = *directions_pk_type_converter
             .get(&direction_id)
-            .ok_or("Unknown legacy ID")?;
+            .ok_or_else(|| RowError::unknown_reference("direction", &direction_id))?;
         Some(id)
     };
 
-    journey.add_metadata_entry(
-        JourneyMetadataType::Direction,
-        JourneyMetadataEntry::new(
-            from_stop_id,
-            until_stop_id,
+    journey.add_metadata_entry(JourneyMetadataEntry::new(
+        from_stop_id,
+        until_stop_id,
+        None,
+        create_minutes_from_value(departure_time),
+        create_minutes_from_value(arrival_time),
+        JourneyMetadata::Direction {
+            direction_type: DirectionType::from_str(&direction_type)?,
             direction_id,
-            None,
-            departure_time,
-            arrival_time,
-            Some(direction_type),
-            None,
-        ),
-    );
+        },
+    ));
 
     Ok(())
 }
 
 fn set_boarding_or_disembarking_exchange_time(mut values: Vec<ParsedValue>, journey: &mut Journey) {
     let ci_co: String = values.remove(0).into();
-    let exchange_time: i32 = values.remove(0).into();
+    let exchange_time: i16 = values.remove(0).into();
     let from_stop_id: Option<i32> = values.remove(0).into();
     let until_stop_id: Option<i32> = values.remove(0).into();
 
-    let metadata_type = if ci_co == "*CI" {
-        JourneyMetadataType::ExchangeTimeBoarding
+    let value = if ci_co == "*CI" {
+        JourneyMetadata::ExchangeTimeBoarding { exchange_time }
     } else {
-        JourneyMetadataType::ExchangeTimeDisembarking
+        JourneyMetadata::ExchangeTimeDisembarking { exchange_time }
     };
 
-    journey.add_metadata_entry(
-        metadata_type,
-        JourneyMetadataEntry::new(
-            from_stop_id,
-            until_stop_id,
-            None,
-            None,
-            None,
-            None,
-            None,
-            Some(exchange_time),
-        ),
-    );
+    journey.add_metadata_entry(JourneyMetadataEntry::new(
+        from_stop_id,
+        until_stop_id,
+        None,
+        None,
+        None,
+        value,
+    ));
 }
 
 fn add_route_entry(mut values: Vec<ParsedValue>, journey: &mut Journey) {
@@ -375,25 +354,36 @@ fn add_route_entry(mut values: Vec<ParsedValue>, journey: &mut Journey) {
     let arrival_time: Option<i32> = values.remove(0).into();
     let departure_time: Option<i32> = values.remove(0).into();
 
-    let arrival_time = create_time(arrival_time);
-    let departure_time = create_time(departure_time);
+    // The times can exceed 24:00 (e.g. 2530 for 01:30 on the next day), the day offsets are kept.
+    let arrival = create_minutes_from_value(arrival_time).map(create_time_and_day_offset);
+    let departure = create_minutes_from_value(departure_time).map(create_time_and_day_offset);
 
-    journey.add_route_entry(JourneyRouteEntry::new(
+    let mut route_entry = JourneyRouteEntry::new(
         stop_id,
-        arrival_time,
-        departure_time,
-    ));
+        arrival.map(|(time, _)| time),
+        departure.map(|(time, _)| time),
+    );
+    if let Some((_, day_offset)) = arrival {
+        route_entry.set_arrival_day_offset(day_offset);
+    }
+    if let Some((_, day_offset)) = departure {
+        route_entry.set_departure_day_offset(day_offset);
+    }
+    // A negative time means that the passengers can't alight/board at this stop.
+    route_entry.set_alighting_allowed(arrival_time.is_none_or(|value| value >= 0));
+    route_entry.set_boarding_allowed(departure_time.is_none_or(|value| value >= 0));
+
+    journey.add_route_entry(route_entry);
 }
 
 // ------------------------------------------------------------------------------------------------
 // --- Helper Functions
 // ------------------------------------------------------------------------------------------------
 
-fn create_time(time: Option<i32>) -> Option<NaiveTime> {
+// The times are kept in minutes, as they can exceed 24:00 (e.g. 2530 for 01:30 on the next day).
+fn create_minutes_from_value(time: Option<i32>) -> Option<i32> {
     time.map(|value| {
-        create_time_from_value(match value.abs() {
-            val if val >= 2400 => val % 2400,
-            val => val,
-        } as u32)
+        let value = value.abs();
+        value / 100 * 60 + value % 100
     })
 }
@@ -1,18 +1,21 @@
 // 1 file(s).
 // File(s) read by the parser:
 // LINIE
-use std::error::Error;
-
 use crate::{
+    error::{Diagnostics, HrdfError, RowError},
     models::{Color, Line, Model},
     parsing::{
         ColumnDefinition, ExpectedType, FastRowMatcher, FileParser, ParsedValue, RowDefinition,
         RowParser,
     },
+    source::HrdfSource,
     storage::ResourceStorage,
 };
 
-pub fn parse(path: &str) -> Result<ResourceStorage<Line>, Box<dyn Error>> {
+pub fn parse(
+    source: &dyn HrdfSource,
+    diagnostics: &Diagnostics,
+) -> Result<ResourceStorage<Line>, HrdfError> {
     log::info!("Parsing LINIE...");
     const ROW_A: i32 = 1;
     const ROW_B: i32 = 2;
@@ -43,18 +46,19 @@ pub fn parse(path: &str) -> Result<ResourceStorage<Line>, Box<dyn Error>> {
             ColumnDefinition::new(19, 21, ExpectedType::Integer16),
         ]),
     ]);
-    let parser = FileParser::new(&format!("{path}/LINIE"), row_parser)?;
+    let parser = FileParser::new(source, "LINIE", row_parser, diagnostics)?;
 
     let mut data = Vec::new();
 
-    for x in parser.parse() {
-        let (id, _, values) = x?;
+    parser.parse().try_for_each_row(|(id, _, values)| {
         match id {
             ROW_A => {
                 data.push(create_instance(values));
             }
             _ => {
-                let line = data.last_mut().ok_or("Type A row missing.")?;
+                let line = data
+                    .last_mut()
+                    .ok_or_else(|| RowError::invalid("Type A row missing."))?;
 
                 match id {
                     ROW_B => set_short_name(values, line),
@@ -64,7 +68,8 @@ pub fn parse(path: &str) -> Result<ResourceStorage<Line>, Box<dyn Error>> {
                 }
             }
         }
-    }
+        Ok(())
+    })?;
 
     let data = Line::vec_to_map(data);
 
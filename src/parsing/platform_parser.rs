@@ -1,33 +1,37 @@
-// 3 file(s).
+// 5 file(s).
 // File(s) read by the parser:
-// GLEIS, GLEIS_LV95, GLEIS_WGS
+// GLEIS, GLEIS_LV95, GLEIS_WGS (V 2.0.4), GLEISE_LV95, GLEISE_WGS (V 2.0.5)
 // ---
 // Note: this parser collects both the Platform and JourneyPlatform resources.
-use std::error::Error;
-
+// The SLOIDs and the coordinates of the platforms are read from GLEIS_LV95 and GLEIS_WGS in the version 2.0.4,
+// and from GLEISE_LV95 and GLEISE_WGS in the version 2.0.5 (GLEIS_LV95 and GLEIS_WGS are then not read).
 use rustc_hash::FxHashMap;
 
 use crate::{
-    models::{CoordinateSystem, Coordinates, JourneyPlatform, Model, Platform},
+    error::{Diagnostics, HrdfError, RowError},
+    models::{CoordinateSystem, Coordinates, JourneyPlatform, Model, Platform, Version},
     parsing::{
         ColumnDefinition, ExpectedType, FastRowMatcher, FileParser, ParsedValue, RowDefinition,
         RowParser,
     },
+    source::HrdfSource,
     storage::ResourceStorage,
     utils::{create_time_from_value, AutoIncrement},
 };
 
 pub fn parse(
-    path: &str,
+    version: Version,
+    source: &dyn HrdfSource,
+    diagnostics: &Diagnostics,
     journeys_pk_type_converter: &FxHashMap<(i32, String), i32>,
-) -> Result<(ResourceStorage<JourneyPlatform>, ResourceStorage<Platform>), Box<dyn Error>> {
+) -> Result<(ResourceStorage<JourneyPlatform>, ResourceStorage<Platform>), HrdfError> {
     log::info!("Parsing GLEIS...");
     const ROW_A: i32 = 1;
     const ROW_B: i32 = 2;
 
     #[rustfmt::skip]
     let row_parser = RowParser::new(vec![
-        // This row is used to create a JourneyPlatform instance.
+        // This row is used to create a JourneyPlatform instance, once the platforms defined further down in the file are known.
         RowDefinition::new(ROW_A, Box::new(FastRowMatcher::new(9, 1, "#", false)), vec![
             ColumnDefinition::new(1, 7, ExpectedType::Integer32),
             ColumnDefinition::new(9, 14, ExpectedType::Integer32),
@@ -43,53 +47,60 @@ pub fn parse(
             ColumnDefinition::new(18, -1, ExpectedType::String),
         ]),
     ]);
-    let parser = FileParser::new(&format!("{path}/GLEIS"), row_parser)?;
+    let parser = FileParser::new(source, "GLEIS", row_parser, diagnostics)?;
 
     let auto_increment = AutoIncrement::new();
     let mut platforms = Vec::new();
     let mut platforms_pk_type_converter = FxHashMap::default();
-
-    let mut bytes_offset = 0;
-    let mut journey_platform = Vec::new();
-
-    for x in parser.parse() {
-        let (id, bytes_read, values) = x?;
-        match id {
-            ROW_A => {
-                bytes_offset += bytes_read;
-                journey_platform.push(values);
+    // The references to the platforms are resolved once the whole file has been parsed, as they refer to later rows.
+    let mut references = Vec::new();
+
+    // The type A rows are also at the beginning of GLEIS_LV95 and GLEIS_WGS, where they are skipped.
+    let (mut bytes_offset, mut skipped_lines) = (0, 0);
+
+    parser
+        .parse()
+        .try_for_each_located_row(|(id, bytes_read, values), line, row| {
+            match id {
+                ROW_A => {
+                    bytes_offset += bytes_read;
+                    skipped_lines += 1;
+                    references.push(create_reference(
+                        values,
+                        journeys_pk_type_converter,
+                        line,
+                        row,
+                    )?);
+                }
+                ROW_B => {
+                    platforms.push(create_platform(
+                        values,
+                        &auto_increment,
+                        &mut platforms_pk_type_converter,
+                    )?);
+                }
+                _ => unreachable!(),
             }
-            ROW_B => {
-                platforms.push(create_platform(
-                    values,
-                    &auto_increment,
-                    &mut platforms_pk_type_converter,
-                )?);
-            }
-            _ => unreachable!(),
-        }
-    }
+            Ok(())
+        })?;
 
     let mut platforms = Platform::vec_to_map(platforms);
 
-    let journey_platform = journey_platform
-        .into_iter()
-        .map(|values| {
-            create_journey_platform(
-                values,
-                journeys_pk_type_converter,
-                &platforms_pk_type_converter,
-            )
-        })
-        .collect::<Result<Vec<_>, _>>()?;
-    let journey_platform = JourneyPlatform::vec_to_map(journey_platform);
+    let journey_platform =
+        create_journey_platform(references, &platforms_pk_type_converter, diagnostics)?;
 
-    log::info!("Parsing GLEIS_LV95...");
-    #[rustfmt::skip]
-    load_coordinates_for_platforms(path, CoordinateSystem::LV95, bytes_offset, &platforms_pk_type_converter, &mut platforms)?;
-    log::info!("Parsing GLEIS_WGS84...");
-    #[rustfmt::skip]
-    load_coordinates_for_platforms(path, CoordinateSystem::WGS84, bytes_offset, &platforms_pk_type_converter, &mut platforms)?;
+    let (filenames, offset) = match version {
+        Version::V_5_40_41_2_0_4 => (["GLEIS_LV95", "GLEIS_WGS"], (bytes_offset, skipped_lines)),
+        // These files only contain the platforms, there are no rows to skip.
+        Version::V_5_40_41_2_0_5 => (["GLEISE_LV95", "GLEISE_WGS"], (0, 0)),
+    };
+    let coordinate_systems = [CoordinateSystem::LV95, CoordinateSystem::WGS84];
+
+    for (filename, coordinate_system) in filenames.into_iter().zip(coordinate_systems) {
+        log::info!("Parsing {filename}...");
+        #[rustfmt::skip]
+        load_coordinates_for_platforms(source, diagnostics, filename, coordinate_system, offset, &platforms_pk_type_converter, &mut platforms)?;
+    }
 
     Ok((
         ResourceStorage::new(journey_platform),
@@ -97,13 +108,27 @@ pub fn parse(
     ))
 }
 
+// A row assigning a platform to a journey.
+struct Reference {
+    journey_id: i32,
+    stop_id: i32,
+    index: i32,
+    time: Option<i32>,
+    bit_field_id: Option<i32>,
+    line: usize,
+    row: String,
+}
+
 fn load_coordinates_for_platforms(
-    path: &str,
+    source: &dyn HrdfSource,
+    diagnostics: &Diagnostics,
+    filename: &str,
     coordinate_system: CoordinateSystem,
-    bytes_offset: u64,
+    // The number of bytes and of lines to skip.
+    (bytes_offset, skipped_lines): (u64, usize),
     pk_type_converter: &FxHashMap<(i32, i32), i32>,
     data: &mut FxHashMap<i32, Platform>,
-) -> Result<(), Box<dyn Error>> {
+) -> Result<(), HrdfError> {
     const ROW_A: i32 = 1;
     const ROW_B: i32 = 2;
     const ROW_C: i32 = 3;
@@ -116,25 +141,25 @@ fn load_coordinates_for_platforms(
         RowDefinition::new(ROW_B, Box::new(FastRowMatcher::new(18, 3, "I A", true)), vec![
             ColumnDefinition::new(1, 7, ExpectedType::Integer32),
             ColumnDefinition::new(10, 16, ExpectedType::Integer32), // Should be 9-16, but here the # character is ignored.
-            ColumnDefinition::new(22, -1, ExpectedType::String),    // This column has not been explicitly defined in the SBB specification.
+            ColumnDefinition::new(22, -1, ExpectedType::String),
         ]),
-        // This row contains the LV95/WGS84 coordinates.
+        // This row contains the LV95/WGS84 coordinates, optionally followed by the altitude.
         RowDefinition::new(ROW_C, Box::new(FastRowMatcher::new(18, 1, "K", true)), vec![
             ColumnDefinition::new(1, 7, ExpectedType::Integer32),
             ColumnDefinition::new(10, 16, ExpectedType::Integer32), // Should be 9-16, but here the # character is ignored.
-            ColumnDefinition::new(20, 26, ExpectedType::Float),     // This column has not been explicitly defined in the SBB specification.
-            ColumnDefinition::new(28, 34, ExpectedType::Float),     // This column has not been explicitly defined in the SBB specification.
+            ColumnDefinition::new(20, -1, ExpectedType::String),    // The values are separated by spaces, their width varies.
         ]),
     ]);
-    let filename = match coordinate_system {
-        CoordinateSystem::LV95 => "GLEIS_LV95",
-        CoordinateSystem::WGS84 => "GLEIS_WGS",
-    };
-    let parser =
-        FileParser::new_with_bytes_offset(&format!("{path}/{filename}"), row_parser, bytes_offset)?;
-
-    parser.parse().try_for_each(|x| {
-        let (id, _, values) = x?;
+    let parser = FileParser::new_with_bytes_offset(
+        source,
+        filename,
+        row_parser,
+        diagnostics,
+        bytes_offset,
+        skipped_lines,
+    )?;
+
+    parser.parse().try_for_each_row(|(id, _, values)| {
         match id {
             ROW_A => {}
             ROW_B => platform_set_sloid(values, coordinate_system, pk_type_converter, data)?,
@@ -149,11 +174,12 @@ fn load_coordinates_for_platforms(
 // --- Helper Functions
 // ------------------------------------------------------------------------------------------------
 
-fn create_journey_platform(
+fn create_reference(
     mut values: Vec<ParsedValue>,
     journeys_pk_type_converter: &FxHashMap<(i32, String), i32>,
-    platforms_pk_type_converter: &FxHashMap<(i32, i32), i32>,
-) -> Result<JourneyPlatform, Box<dyn Error>> {
+    line: usize,
+    row: &str,
+) -> Result<Reference, RowError> {
     let stop_id: i32 = values.remove(0).into();
     let journey_id: i32 = values.remove(0).into();
     let administration: String = values.remove(0).into();
@@ -161,29 +187,58 @@ fn create_journey_platform(
     let time: Option<i32> = values.remove(0).into();
     let bit_field_id: Option<i32> = values.remove(0).into();
 
+    let journey = (journey_id, administration);
     let journey_id = *journeys_pk_type_converter
-        .get(&(journey_id, administration))
-        .ok_or("Unknown legacy ID")?;
+        .get(&journey)
+        .ok_or_else(|| RowError::unknown_reference("journey", &journey))?;
 
-    let platform_id = *platforms_pk_type_converter
-        .get(&(stop_id, index))
-        .ok_or("Unknown legacy ID")?;
-
-    let time = time.map(|x| create_time_from_value(x as u32));
-
-    Ok(JourneyPlatform::new(
+    Ok(Reference {
         journey_id,
-        platform_id,
+        stop_id,
+        index,
         time,
         bit_field_id,
-    ))
+        line,
+        row: row.to_string(),
+    })
+}
+
+fn create_journey_platform(
+    references: Vec<Reference>,
+    platforms_pk_type_converter: &FxHashMap<(i32, i32), i32>,
+    diagnostics: &Diagnostics,
+) -> Result<FxHashMap<(i32, i32), JourneyPlatform>, HrdfError> {
+    let mut data = Vec::with_capacity(references.len());
+
+    for reference in references {
+        let platform = (reference.stop_id, reference.index);
+        let Some(&platform_id) = platforms_pk_type_converter.get(&platform) else {
+            diagnostics.report(RowError::unknown_reference("platform", platform).locate(
+                "GLEIS",
+                reference.line,
+                &reference.row,
+            ))?;
+            continue;
+        };
+
+        let time = reference.time.map(|x| create_time_from_value(x as u32));
+
+        data.push(JourneyPlatform::new(
+            reference.journey_id,
+            platform_id,
+            time,
+            reference.bit_field_id,
+        ));
+    }
+
+    Ok(JourneyPlatform::vec_to_map(data))
 }
 
 fn create_platform(
     mut values: Vec<ParsedValue>,
     auto_increment: &AutoIncrement,
     platforms_pk_type_converter: &mut FxHashMap<(i32, i32), i32>,
-) -> Result<Platform, Box<dyn Error>> {
+) -> Result<Platform, RowError> {
     let stop_id: i32 = values.remove(0).into();
     let index: i32 = values.remove(0).into();
     let platform_data: String = values.remove(0).into();
@@ -200,7 +255,7 @@ fn platform_set_sloid(
     coordinate_system: CoordinateSystem,
     pk_type_converter: &FxHashMap<(i32, i32), i32>,
     data: &mut FxHashMap<i32, Platform>,
-) -> Result<(), Box<dyn Error>> {
+) -> Result<(), RowError> {
     // The SLOID is processed only when loading LV95 coordinates.
     if coordinate_system == CoordinateSystem::LV95 {
         let stop_id: i32 = values.remove(0).into();
@@ -209,9 +264,11 @@ fn platform_set_sloid(
 
         let id = pk_type_converter
             .get(&(stop_id, index))
-            .ok_or("Unknown legacy ID")?;
+            .ok_or_else(|| RowError::unknown_reference("platform", (stop_id, index)))?;
 
-        data.get_mut(id).ok_or("Unknown ID")?.set_sloid(sloid);
+        data.get_mut(id)
+            .ok_or_else(|| RowError::unknown_reference("platform", id))?
+            .set_sloid(sloid);
     }
 
     Ok(())
@@ -222,11 +279,12 @@ fn platform_set_coordinates(
     coordinate_system: CoordinateSystem,
     pk_type_converter: &FxHashMap<(i32, i32), i32>,
     data: &mut FxHashMap<i32, Platform>,
-) -> Result<(), Box<dyn Error>> {
+) -> Result<(), RowError> {
     let stop_id: i32 = values.remove(0).into();
     let index: i32 = values.remove(0).into();
-    let mut xy1: f64 = values.remove(0).into();
-    let mut xy2: f64 = values.remove(0).into();
+    let coordinates: String = values.remove(0).into();
+
+    let (mut xy1, mut xy2, altitude) = parse_coordinates(&coordinates)?;
 
     if coordinate_system == CoordinateSystem::WGS84 {
         // WGS84 coordinates are stored in reverse order for some unknown reason.
@@ -237,13 +295,19 @@ fn platform_set_coordinates(
 
     let id = &pk_type_converter
         .get(&(stop_id, index))
-        .ok_or("Unknown legacy ID")?;
-    let platform = data.get_mut(id).ok_or("Unknown ID")?;
+        .ok_or_else(|| RowError::unknown_reference("platform", (stop_id, index)))?;
+    let platform = data
+        .get_mut(id)
+        .ok_or_else(|| RowError::unknown_reference("platform", id))?;
 
     match coordinate_system {
         CoordinateSystem::LV95 => platform.set_lv95_coordinates(coordinate),
         CoordinateSystem::WGS84 => platform.set_wgs84_coordinates(coordinate),
     }
+    // The altitude is provided by both files, the one of the LV95 file (read first) is kept.
+    if platform.altitude().is_none() {
+        platform.set_altitude(altitude);
+    }
 
     Ok(())
 }
@@ -252,25 +316,151 @@ fn platform_set_coordinates(
 // --- Helper Functions
 // ------------------------------------------------------------------------------------------------
 
-fn parse_platform_data(
-    mut platform_data: String,
-) -> Result<(String, Option<String>), Box<dyn Error>> {
+fn parse_platform_data(mut platform_data: String) -> Result<(String, Option<String>), RowError> {
     platform_data = format!("{} ", platform_data);
-    let data = platform_data.split("' ").filter(|&s| !s.is_empty()).fold(
-        FxHashMap::default(),
-        |mut acc, item| {
-            let parts: Vec<&str> = item.split(" '").collect();
-            acc.insert(parts[0], parts[1]);
-            acc
-        },
-    );
+    let data = platform_data
+        .split("' ")
+        .filter(|&s| !s.is_empty())
+        .map(|item| {
+            item.split_once(" '")
+                .ok_or_else(|| RowError::invalid("Invalid platform data."))
+        })
+        .collect::<Result<FxHashMap<_, _>, _>>()?;
 
     // There should always be a G entry.
     let code = data
         .get("G")
-        .ok_or("Entry of type \"G\" missing.")?
+        .ok_or_else(|| RowError::invalid("Entry of type \"G\" missing."))?
         .to_string();
     let sectors = data.get("A").map(|s| s.to_string());
 
     Ok((code, sectors))
 }
+
+fn parse_coordinates(coordinates: &str) -> Result<(f64, f64, Option<f64>), RowError> {
+    let values = coordinates
+        .split_whitespace()
+        .map(|value| value.parse::<f64>())
+        .collect::<Result<Vec<_>, _>>()?;
+
+    match values[..] {
+        [xy1, xy2] => Ok((xy1, xy2, None)),
+        [xy1, xy2, altitude] => Ok((xy1, xy2, Some(altitude))),
+        _ => Err(RowError::invalid("Invalid platform coordinates.")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{error::ParsingMode, fixtures::MemorySource};
+
+    use super::*;
+
+    const GLEISE_LV95: &str = "\
+8500010 #0000001 G '1' A 'AB'
+8500010 #0000001 I A ch:1:sloid:10:1:5
+8500010 #0000001 K 2611363.5 1266310.5 260
+";
+
+    const GLEISE_WGS: &str = "\
+8500010 #0000001 G '1' A 'AB'
+8500010 #0000001 I A ch:1:sloid:10:1:5
+8500010 #0000001 K 7.589562 47.547414 261
+";
+
+    fn parse_platforms(version: Version, source: &MemorySource) -> ResourceStorage<Platform> {
+        let diagnostics = Diagnostics::new(ParsingMode::Strict, None);
+        let journeys_pk_type_converter = FxHashMap::from_iter([
+            ((1, "000011".to_string()), 1),
+            ((2, "000011".to_string()), 2),
+        ]);
+
+        let (journey_platform, platforms) =
+            parse(version, source, &diagnostics, &journeys_pk_type_converter).unwrap();
+        assert_eq!(journey_platform.entries().len(), 2);
+        platforms
+    }
+
+    fn platform_of(platforms: &ResourceStorage<Platform>, stop_id: i32) -> &Platform {
+        platforms
+            .entries()
+            .into_iter()
+            .find(|platform| platform.stop_id() == stop_id)
+            .unwrap()
+    }
+
+    #[test]
+    fn coordinates_of_version_2_0_4() {
+        let platforms = parse_platforms(Version::V_5_40_41_2_0_4, &MemorySource::new());
+        let platform = platform_of(&platforms, 8500010);
+
+        assert_eq!(platform.name(), "1");
+        assert_eq!(platform.sectors(), Some("AB"));
+        assert_eq!(platform.sloid(), "ch:1:sloid:10:1:1");
+        assert_eq!(platform.lv95_coordinates().easting(), 2611363.0);
+    }
+
+    #[test]
+    fn coordinates_of_version_2_0_5() {
+        // GLEIS_LV95 and GLEIS_WGS are not read.
+        let source = MemorySource::new()
+            .without("GLEIS_LV95")
+            .without("GLEIS_WGS")
+            .with("GLEISE_LV95", GLEISE_LV95)
+            .with("GLEISE_WGS", GLEISE_WGS);
+        let platforms = parse_platforms(Version::V_5_40_41_2_0_5, &source);
+        let platform = platform_of(&platforms, 8500010);
+
+        assert_eq!(platform.sloid(), "ch:1:sloid:10:1:5");
+        assert_eq!(platform.lv95_coordinates().easting(), 2611363.5);
+        assert_eq!(platform.lv95_coordinates().northing(), 1266310.5);
+        assert_eq!(platform.wgs84_coordinates().latitude(), 47.547414);
+        assert_eq!(platform.wgs84_coordinates().longitude(), 7.589562);
+        // The altitude of GLEISE_LV95 is kept.
+        assert_eq!(platform.altitude(), Some(260.0));
+    }
+
+    #[test]
+    fn unknown_journey_platform_is_located() {
+        let gleis = MemorySource::new().file("GLEIS").replace(
+            "8500020 000001 000011 #0000001",
+            "8500020 000001 000011 #0000009",
+        );
+        let source = MemorySource::new().with("GLEIS", &gleis);
+        let diagnostics = Diagnostics::new(ParsingMode::Strict, None);
+
+        let error = parse(
+            Version::V_5_40_41_2_0_4,
+            &source,
+            &diagnostics,
+            &FxHashMap::from_iter([((1, "000011".to_string()), 1)]),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            HrdfError::UnknownReference { ref file, line: 2, ref row, .. }
+                if file == "GLEIS" && row.starts_with("8500020 000001 000011 #0000009")
+        ));
+    }
+
+    #[test]
+    fn unknown_platform_is_located() {
+        let gleise_lv95 = format!("{GLEISE_LV95}8500030 #0000009 K 2600037 1199749\n");
+        let source = MemorySource::new()
+            .with("GLEISE_LV95", &gleise_lv95)
+            .with("GLEISE_WGS", GLEISE_WGS);
+        let diagnostics = Diagnostics::new(ParsingMode::Strict, None);
+
+        let error = parse(
+            Version::V_5_40_41_2_0_5,
+            &source,
+            &diagnostics,
+            &FxHashMap::from_iter([((1, "000011".to_string()), 1)]),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            HrdfError::UnknownReference { ref file, line: 4, .. } if file == "GLEISE_LV95"
+        ));
+    }
+}
@@ -1,24 +1,25 @@
 // 1 file(s).
 // File(s) read by the parser:
 // METABHF
-use std::error::Error;
-
 use rustc_hash::FxHashMap;
 
 use crate::{
+    error::{Diagnostics, HrdfError, RowError},
     models::{Model, StopConnection},
     parsing::{
         AdvancedRowMatcher, ColumnDefinition, ExpectedType, FastRowMatcher, FileParser,
         ParsedValue, RowDefinition, RowParser,
     },
+    source::HrdfSource,
     storage::ResourceStorage,
     utils::AutoIncrement,
 };
 
 pub fn parse(
-    path: &str,
+    source: &dyn HrdfSource,
+    diagnostics: &Diagnostics,
     attributes_pk_type_converter: &FxHashMap<String, i32>,
-) -> Result<ResourceStorage<StopConnection>, Box<dyn Error>> {
+) -> Result<ResourceStorage<StopConnection>, HrdfError> {
     log::info!("Parsing METABHF...");
     const ROW_A: i32 = 1;
     const ROW_B: i32 = 2;
@@ -27,7 +28,7 @@ pub fn parse(
     #[rustfmt::skip]
     let row_parser = RowParser::new(vec![
         // This row is used to create a StopConnection instance.
-        RowDefinition::new(ROW_A, Box::new(AdvancedRowMatcher::new(r"[0-9]{7} [0-9]{7} [0-9]{3}")?), vec![
+        RowDefinition::new(ROW_A, Box::new(AdvancedRowMatcher::new(r"[0-9]{7} [0-9]{7} [0-9]{3}")), vec![
             ColumnDefinition::new(1, 7, ExpectedType::Integer32),
             ColumnDefinition::new(9, 15, ExpectedType::Integer32),
             ColumnDefinition::new(17, 19, ExpectedType::Integer16),
@@ -39,17 +40,18 @@ pub fn parse(
         // This row is ignored.
         RowDefinition::new(ROW_C, Box::new(FastRowMatcher::new(8, 1, ":", true)), Vec::new()),
     ]);
-    let parser = FileParser::new(&format!("{path}/METABHF"), row_parser)?;
+    let parser = FileParser::new(source, "METABHF", row_parser, diagnostics)?;
 
     let auto_increment = AutoIncrement::new();
     let mut data = Vec::new();
 
-    for x in parser.parse() {
-        let (id, _, values) = x?;
+    parser.parse().try_for_each_row(|(id, _, values)| {
         match id {
             ROW_A => data.push(create_instance(values, &auto_increment)),
             _ => {
-                let stop_connection = data.last_mut().ok_or("Type A row missing.")?;
+                let stop_connection = data
+                    .last_mut()
+                    .ok_or_else(|| RowError::invalid("Type A row missing."))?;
 
                 match id {
                     ROW_B => set_attribute(values, stop_connection, attributes_pk_type_converter)?,
@@ -58,7 +60,8 @@ pub fn parse(
                 }
             }
         }
-    }
+        Ok(())
+    })?;
 
     let data = StopConnection::vec_to_map(data);
 
@@ -81,11 +84,11 @@ fn set_attribute(
     mut values: Vec<ParsedValue>,
     current_instance: &mut StopConnection,
     attributes_pk_type_converter: &FxHashMap<String, i32>,
-) -> Result<(), Box<dyn Error>> {
+) -> Result<(), RowError> {
     let attribute_designation: String = values.remove(0).into();
     let attribute_id = *attributes_pk_type_converter
         .get(&attribute_designation)
-        .ok_or("Unknown legacy ID")?;
+        .ok_or_else(|| RowError::unknown_reference("attribute", &attribute_designation))?;
     current_instance.set_attribute(attribute_id);
     Ok(())
 }
@@ -1,25 +1,32 @@
 // 8 file(s).
 // File(s) read by the parser:
-// BAHNHOF, BFKOORD_LV95, BFKOORD_WGS, BFPRIOS, KMINFO, UMSTEIGB, BHFART_60
+// BAHNHOF, BFKOORD_LV95, BFKOORD_WGS, BFPRIOS, KMINFO, UMSTEIGB, BHFART, BHFART_60
 // ---
-// Files not used by the parser:
-// BHFART
-use std::{error::Error, vec};
+// BHFART and BHFART_60 share the same layout, at least one of them must be provided.
+// When both are provided, BHFART_60 takes precedence: for each stop, the restrictions, the SLOID
+// and the boarding areas it contains replace the ones read from BHFART.
+use std::vec;
 
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHashSet};
 
 use crate::{
+    error::{Diagnostics, HrdfError, RowError},
     models::{CoordinateSystem, Coordinates, Model, Stop, Version},
     parsing::{
         ColumnDefinition, ExpectedType, FastRowMatcher, FileParser, ParsedValue, RowDefinition,
         RowParser,
     },
+    source::HrdfSource,
     storage::ResourceStorage,
 };
 
 type StopStorageAndExchangeTimes = (ResourceStorage<Stop>, (i16, i16));
 
-pub fn parse(version: Version, path: &str) -> Result<StopStorageAndExchangeTimes, Box<dyn Error>> {
+pub fn parse(
+    version: Version,
+    source: &dyn HrdfSource,
+    diagnostics: &Diagnostics,
+) -> Result<StopStorageAndExchangeTimes, HrdfError> {
     log::info!("Parsing BAHNHOF...");
     #[rustfmt::skip]
     let row_parser = RowParser::new(vec![
@@ -29,36 +36,56 @@ pub fn parse(version: Version, path: &str) -> Result<StopStorageAndExchangeTimes
             ColumnDefinition::new(13, -1, ExpectedType::String), // Should be 13-62, but some entries go beyond column 62.
         ]),
     ]);
-    let parser = FileParser::new(&format!("{path}/BAHNHOF"), row_parser)?;
+    let parser = FileParser::new(source, "BAHNHOF", row_parser, diagnostics)?;
 
     let data = parser
         .parse()
-        .map(|x| x.map(|(_, _, values)| create_instance(values))?)
+        .map_rows(|(_, _, values)| create_instance(values))
         .collect::<Result<Vec<_>, _>>()?;
     let mut data = Stop::vec_to_map(data);
 
     log::info!("Parsing BFKOORD_LV95...");
-    load_coordinates(version, path, CoordinateSystem::LV95, &mut data)?;
+    load_coordinates(
+        version,
+        source,
+        diagnostics,
+        CoordinateSystem::LV95,
+        &mut data,
+    )?;
     log::info!("Parsing BFKOORD_WGS...");
-    load_coordinates(version, path, CoordinateSystem::WGS84, &mut data)?;
+    load_coordinates(
+        version,
+        source,
+        diagnostics,
+        CoordinateSystem::WGS84,
+        &mut data,
+    )?;
     log::info!("Parsing BFPRIOS...");
-    load_exchange_priorities(path, &mut data)?;
+    load_exchange_priorities(source, diagnostics, &mut data)?;
     log::info!("Parsing KMINFO...");
-    load_exchange_flags(path, &mut data)?;
+    load_exchange_flags(source, diagnostics, &mut data)?;
     log::info!("Parsing UMSTEIGB...");
-    let default_exchange_time = load_exchange_times(path, &mut data)?;
-    log::info!("Parsing BHFART_60...");
-    load_descriptions(path, &mut data)?;
+    let default_exchange_time = load_exchange_times(source, diagnostics, &mut data)?;
+    // BHFART is parsed first, so that the data of BHFART_60 overrides it.
+    let mut has_descriptions = false;
+    for filename in ["BHFART", "BHFART_60"] {
+        has_descriptions |= load_descriptions(source, diagnostics, filename, &mut data)?;
+    }
+    if !has_descriptions {
+        // Either file would do, so both are named.
+        return Err(HrdfError::MissingFile("BHFART or BHFART_60".to_string()));
+    }
 
     Ok((ResourceStorage::new(data), default_exchange_time))
 }
 
 fn load_coordinates(
     version: Version,
-    path: &str,
+    source: &dyn HrdfSource,
+    diagnostics: &Diagnostics,
     coordinate_system: CoordinateSystem,
     data: &mut FxHashMap<i32, Stop>,
-) -> Result<(), Box<dyn Error>> {
+) -> Result<(), HrdfError> {
     #[rustfmt::skip]
     let row_parser = RowParser::new(vec![
         // This row contains the LV95/WGS84 coordinates.
@@ -83,19 +110,18 @@ fn load_coordinates(
         CoordinateSystem::LV95 => "BFKOORD_LV95",
         CoordinateSystem::WGS84 => "BFKOORD_WGS",
     };
-    let parser = FileParser::new(&format!("{path}/{filename}"), row_parser)?;
+    let parser = FileParser::new(source, filename, row_parser, diagnostics)?;
 
-    parser.parse().try_for_each(|x| {
-        let (_, _, values) = x?;
-        set_coordinates(values, coordinate_system, data)?;
-        Ok(())
-    })
+    parser
+        .parse()
+        .try_for_each_row(|(_, _, values)| set_coordinates(values, coordinate_system, data))
 }
 
 fn load_exchange_priorities(
-    path: &str,
+    source: &dyn HrdfSource,
+    diagnostics: &Diagnostics,
     data: &mut FxHashMap<i32, Stop>,
-) -> Result<(), Box<dyn Error>> {
+) -> Result<(), HrdfError> {
     #[rustfmt::skip]
     let row_parser = RowParser::new(vec![
         // This row contains the changing priority.
@@ -104,16 +130,18 @@ fn load_exchange_priorities(
             ColumnDefinition::new(9, 10, ExpectedType::Integer16),
         ]),
     ]);
-    let parser = FileParser::new(&format!("{path}/BFPRIOS"), row_parser)?;
+    let parser = FileParser::new(source, "BFPRIOS", row_parser, diagnostics)?;
 
-    parser.parse().try_for_each(|x| {
-        let (_, _, values) = x?;
-        set_exchange_priority(values, data)?;
-        Ok(())
-    })
+    parser
+        .parse()
+        .try_for_each_row(|(_, _, values)| set_exchange_priority(values, data))
 }
 
-fn load_exchange_flags(path: &str, data: &mut FxHashMap<i32, Stop>) -> Result<(), Box<dyn Error>> {
+fn load_exchange_flags(
+    source: &dyn HrdfSource,
+    diagnostics: &Diagnostics,
+    data: &mut FxHashMap<i32, Stop>,
+) -> Result<(), HrdfError> {
     #[rustfmt::skip]
     let row_parser = RowParser::new(vec![
         // This row contains the changing flag.
@@ -122,19 +150,18 @@ fn load_exchange_flags(path: &str, data: &mut FxHashMap<i32, Stop>) -> Result<()
             ColumnDefinition::new(9, 13, ExpectedType::Integer16),
         ]),
     ]);
-    let parser = FileParser::new(&format!("{path}/KMINFO"), row_parser)?;
+    let parser = FileParser::new(source, "KMINFO", row_parser, diagnostics)?;
 
-    parser.parse().try_for_each(|x| {
-        let (_, _, values) = x?;
-        set_exchange_flag(values, data)?;
-        Ok(())
-    })
+    parser
+        .parse()
+        .try_for_each_row(|(_, _, values)| set_exchange_flag(values, data))
 }
 
 fn load_exchange_times(
-    path: &str,
+    source: &dyn HrdfSource,
+    diagnostics: &Diagnostics,
     data: &mut FxHashMap<i32, Stop>,
-) -> Result<(i16, i16), Box<dyn Error>> {
+) -> Result<(i16, i16), HrdfError> {
     #[rustfmt::skip]
     let row_parser = RowParser::new(vec![
         // This row contains the changing time.
@@ -144,22 +171,27 @@ fn load_exchange_times(
             ColumnDefinition::new(12, 13, ExpectedType::Integer16),
         ]),
     ]);
-    let parser = FileParser::new(&format!("{path}/UMSTEIGB"), row_parser)?;
+    let parser = FileParser::new(source, "UMSTEIGB", row_parser, diagnostics)?;
 
     let mut default_exchange_time = (0, 0);
 
-    parser.parse().try_for_each(|x| {
-        let (_, _, values) = x?;
+    parser.parse().try_for_each_row(|(_, _, values)| {
         if let Some(x) = set_exchange_time(values, data)? {
             default_exchange_time = x;
         }
-        Ok::<(), Box<dyn Error>>(())
+        Ok(())
     })?;
 
     Ok(default_exchange_time)
 }
 
-fn load_descriptions(path: &str, data: &mut FxHashMap<i32, Stop>) -> Result<(), Box<dyn Error>> {
+/// Returns false if the file is missing.
+fn load_descriptions(
+    source: &dyn HrdfSource,
+    diagnostics: &Diagnostics,
+    filename: &str,
+    data: &mut FxHashMap<i32, Stop>,
+) -> Result<bool, HrdfError> {
     const ROW_A: i32 = 1;
     const ROW_B: i32 = 2;
     const ROW_C: i32 = 3;
@@ -185,26 +217,35 @@ fn load_descriptions(path: &str, data: &mut FxHashMap<i32, Stop>) -> Result<(),
             ColumnDefinition::new(13, -1, ExpectedType::String),
         ]),
     ]);
-    let parser = FileParser::new(&format!("{path}/BHFART_60"), row_parser)?;
+    let parser = match FileParser::new(source, filename, row_parser, diagnostics) {
+        Ok(parser) => parser,
+        Err(HrdfError::MissingFile(_)) => return Ok(false),
+        Err(e) => return Err(e),
+    };
+    log::info!("Parsing {filename}...");
+
+    // The stops whose boarding areas have been provided by this file.
+    let mut stops_with_boarding_areas = FxHashSet::default();
 
-    parser.parse().try_for_each(|x| {
-        let (id, _, values) = x?;
+    parser.parse().try_for_each_row(|(id, _, values)| {
         match id {
             ROW_A => {}
             ROW_B => set_restrictions(values, data)?,
             ROW_C => set_sloid(values, data)?,
-            ROW_D => add_boarding_area(values, data)?,
+            ROW_D => add_boarding_area(values, data, &mut stops_with_boarding_areas)?,
             _ => unreachable!(),
         }
         Ok(())
-    })
+    })?;
+
+    Ok(true)
 }
 
 // ------------------------------------------------------------------------------------------------
 // --- Data Processing Functions
 // ------------------------------------------------------------------------------------------------
 
-fn create_instance(mut values: Vec<ParsedValue>) -> Result<Stop, Box<dyn Error>> {
+fn create_instance(mut values: Vec<ParsedValue>) -> Result<Stop, RowError> {
     let id: i32 = values.remove(0).into();
     let designations: String = values.remove(0).into();
 
@@ -217,7 +258,7 @@ fn set_coordinates(
     mut values: Vec<ParsedValue>,
     coordinate_system: CoordinateSystem,
     data: &mut FxHashMap<i32, Stop>,
-) -> Result<(), Box<dyn Error>> {
+) -> Result<(), RowError> {
     let stop_id: i32 = values.remove(0).into();
     let mut xy1: f64 = values.remove(0).into();
     let mut xy2: f64 = values.remove(0).into();
@@ -229,7 +270,9 @@ fn set_coordinates(
         (xy1, xy2) = (xy2, xy1);
     }
 
-    let stop = data.get_mut(&stop_id).ok_or("Unknown ID")?;
+    let stop = data
+        .get_mut(&stop_id)
+        .ok_or_else(|| RowError::unknown_reference("stop", stop_id))?;
     let coordinate = Coordinates::new(coordinate_system, xy1, xy2);
 
     match coordinate_system {
@@ -243,11 +286,13 @@ fn set_coordinates(
 fn set_exchange_priority(
     mut values: Vec<ParsedValue>,
     data: &mut FxHashMap<i32, Stop>,
-) -> Result<(), Box<dyn Error>> {
+) -> Result<(), RowError> {
     let stop_id: i32 = values.remove(0).into();
     let exchange_priority: i16 = values.remove(0).into();
 
-    let stop = data.get_mut(&stop_id).ok_or("Unknown ID")?;
+    let stop = data
+        .get_mut(&stop_id)
+        .ok_or_else(|| RowError::unknown_reference("stop", stop_id))?;
     stop.set_exchange_priority(exchange_priority);
 
     Ok(())
@@ -256,11 +301,13 @@ fn set_exchange_priority(
 fn set_exchange_flag(
     mut values: Vec<ParsedValue>,
     data: &mut FxHashMap<i32, Stop>,
-) -> Result<(), Box<dyn Error>> {
+) -> Result<(), RowError> {
     let stop_id: i32 = values.remove(0).into();
     let exchange_flag: i16 = values.remove(0).into();
 
-    let stop = data.get_mut(&stop_id).ok_or("Unknown ID")?;
+    let stop = data
+        .get_mut(&stop_id)
+        .ok_or_else(|| RowError::unknown_reference("stop", stop_id))?;
     stop.set_exchange_flag(exchange_flag);
 
     Ok(())
@@ -269,7 +316,7 @@ fn set_exchange_flag(
 fn set_exchange_time(
     mut values: Vec<ParsedValue>,
     data: &mut FxHashMap<i32, Stop>,
-) -> Result<Option<(i16, i16)>, Box<dyn Error>> {
+) -> Result<Option<(i16, i16)>, RowError> {
     let stop_id: i32 = values.remove(0).into();
     let exchange_time_inter_city: i16 = values.remove(0).into();
     let exchange_time_other: i16 = values.remove(0).into();
@@ -281,7 +328,9 @@ fn set_exchange_time(
         // It contains default exchange times to be used when a stop has no specific exchange time.
         Ok(exchange_time)
     } else {
-        let stop = data.get_mut(&stop_id).ok_or("Unknown ID")?;
+        let stop = data
+            .get_mut(&stop_id)
+            .ok_or_else(|| RowError::unknown_reference("stop", stop_id))?;
         stop.set_exchange_time(exchange_time);
         Ok(None)
     }
@@ -290,11 +339,13 @@ fn set_exchange_time(
 fn set_restrictions(
     mut values: Vec<ParsedValue>,
     data: &mut FxHashMap<i32, Stop>,
-) -> Result<(), Box<dyn Error>> {
+) -> Result<(), RowError> {
     let stop_id: i32 = values.remove(0).into();
     let restrictions: i16 = values.remove(0).into();
 
-    let stop = data.get_mut(&stop_id).ok_or("Unknown ID")?;
+    let stop = data
+        .get_mut(&stop_id)
+        .ok_or_else(|| RowError::unknown_reference("stop", stop_id))?;
     stop.set_restrictions(restrictions);
 
     Ok(())
@@ -303,11 +354,13 @@ fn set_restrictions(
 fn set_sloid(
     mut values: Vec<ParsedValue>,
     data: &mut FxHashMap<i32, Stop>,
-) -> Result<(), Box<dyn Error>> {
+) -> Result<(), RowError> {
     let stop_id: i32 = values.remove(0).into();
     let sloid: String = values.remove(0).into();
 
-    let stop = data.get_mut(&stop_id).ok_or("Unknown ID")?;
+    let stop = data
+        .get_mut(&stop_id)
+        .ok_or_else(|| RowError::unknown_reference("stop", stop_id))?;
     stop.set_sloid(sloid);
 
     Ok(())
@@ -316,11 +369,18 @@ fn set_sloid(
 fn add_boarding_area(
     mut values: Vec<ParsedValue>,
     data: &mut FxHashMap<i32, Stop>,
-) -> Result<(), Box<dyn Error>> {
+    stops_with_boarding_areas: &mut FxHashSet<i32>,
+) -> Result<(), RowError> {
     let stop_id: i32 = values.remove(0).into();
     let sloid: String = values.remove(0).into();
 
-    let stop = data.get_mut(&stop_id).ok_or("Unknown ID")?;
+    let stop = data
+        .get_mut(&stop_id)
+        .ok_or_else(|| RowError::unknown_reference("stop", stop_id))?;
+    if stops_with_boarding_areas.insert(stop_id) {
+        // The boarding areas read from a previous file are replaced, not merged.
+        stop.clear_boarding_areas();
+    }
     stop.add_boarding_area(sloid);
 
     Ok(())
@@ -332,16 +392,22 @@ fn add_boarding_area(
 
 type NameAndAlternatives = (String, Option<String>, Option<String>, Option<Vec<String>>);
 
-fn parse_designations(designations: String) -> Result<NameAndAlternatives, Box<dyn Error>> {
+fn parse_designations(designations: String) -> Result<NameAndAlternatives, RowError> {
     let designations = designations
         .split('>')
         .filter(|&s| !s.is_empty())
-        .map(|s| -> Result<(i32, String), Box<dyn Error>> {
+        .map(|s| -> Result<(i32, String), RowError> {
             let s = s.replace('$', "");
             let mut parts = s.split('<');
 
-            let v = parts.next().ok_or("Missing value part")?.to_string();
-            let k = parts.next().ok_or("Missing value part")?.parse::<i32>()?;
+            let v = parts
+                .next()
+                .ok_or_else(|| RowError::invalid("Missing value part."))?
+                .to_string();
+            let k = parts
+                .next()
+                .ok_or_else(|| RowError::invalid("Missing value part."))?
+                .parse::<i32>()?;
 
             Ok((k, v))
         })
@@ -350,14 +416,73 @@ fn parse_designations(designations: String) -> Result<NameAndAlternatives, Box<d
             |mut acc: std::collections::HashMap<i32, Vec<String>, _>, item| {
                 let (k, v) = item?;
                 acc.entry(k).or_default().push(v);
-                Ok::<_, Box<dyn Error>>(acc)
+                Ok::<_, RowError>(acc)
             },
         )?;
 
-    let name = designations.get(&1).ok_or("Missing stop name")?[0].clone();
+    let name = designations
+        .get(&1)
+        .ok_or_else(|| RowError::invalid("Missing stop name."))?[0]
+        .clone();
     let long_name = designations.get(&2).map(|x| x[0].clone());
     let abbreviation = designations.get(&3).map(|x| x[0].clone());
     let synonyms = designations.get(&4).cloned();
 
     Ok((name, long_name, abbreviation, synonyms))
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{error::ParsingMode, fixtures::MemorySource};
+
+    use super::*;
+
+    #[test]
+    fn missing_descriptions_name_both_files() {
+        let diagnostics = Diagnostics::new(ParsingMode::Strict, None);
+        let source = MemorySource::new().without("BHFART").without("BHFART_60");
+
+        let error = parse(Version::V_5_40_41_2_0_4, &source, &diagnostics).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "The file BHFART or BHFART_60 is missing."
+        );
+    }
+
+    #[test]
+    fn either_descriptions_file_is_enough() {
+        for file in ["BHFART", "BHFART_60"] {
+            let diagnostics = Diagnostics::new(ParsingMode::Strict, None);
+            let source = MemorySource::new().without(file);
+
+            let (stops, _) = parse(Version::V_5_40_41_2_0_4, &source, &diagnostics).unwrap();
+            assert_eq!(stops.entries().len(), 3);
+        }
+    }
+
+    #[test]
+    fn descriptions_of_bhfart_60_take_precedence() {
+        let diagnostics = Diagnostics::new(ParsingMode::Strict, None);
+        let bhfart = "\
+8500010 B 01
+8500010 G A ch:1:sloid:99
+8500010 G a ch:1:sloid:99:1
+8500010 G a ch:1:sloid:99:2
+8500020 B 02
+8500020 G A ch:1:sloid:20
+";
+        let source = MemorySource::new().with("BHFART", bhfart);
+
+        let (stops, _) = parse(Version::V_5_40_41_2_0_4, &source, &diagnostics).unwrap();
+        let stop = stops.find(8500010).unwrap();
+        assert_eq!(stop.restrictions(), 3);
+        assert_eq!(stop.sloid(), "ch:1:sloid:10");
+        // The boarding areas of BHFART are replaced, not merged.
+        assert_eq!(stop.boarding_areas(), &vec!["ch:1:sloid:10:1".to_string()]);
+
+        // The stops missing from BHFART_60 keep the data of BHFART.
+        let stop = stops.find(8500020).unwrap();
+        assert_eq!(stop.restrictions(), 2);
+        assert_eq!(stop.sloid(), "ch:1:sloid:20");
+    }
+}
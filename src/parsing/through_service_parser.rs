@@ -1,21 +1,22 @@
 // 1 file(s).
 // File(s) read by the parser:
 // DURCHBI
-use std::error::Error;
-
 use rustc_hash::FxHashMap;
 
 use crate::{
+    error::{Diagnostics, HrdfError, RowError},
     models::{Model, ThroughService},
     parsing::{ColumnDefinition, ExpectedType, FileParser, ParsedValue, RowDefinition, RowParser},
+    source::HrdfSource,
     storage::ResourceStorage,
     utils::AutoIncrement,
 };
 
 pub fn parse(
-    path: &str,
+    source: &dyn HrdfSource,
+    diagnostics: &Diagnostics,
     journeys_pk_type_converter: &FxHashMap<(i32, String), i32>,
-) -> Result<ResourceStorage<ThroughService>, Box<dyn Error>> {
+) -> Result<ResourceStorage<ThroughService>, HrdfError> {
     log::info!("Parsing DURCHBI...");
     #[rustfmt::skip]
     let row_parser = RowParser::new(vec![
@@ -30,16 +31,14 @@ pub fn parse(
             ColumnDefinition::new(44, 50, ExpectedType::OptionInteger32),
         ]),
     ]);
-    let parser = FileParser::new(&format!("{path}/DURCHBI"), row_parser)?;
+    let parser = FileParser::new(source, "DURCHBI", row_parser, diagnostics)?;
 
     let auto_increment = AutoIncrement::new();
 
     let data = parser
         .parse()
-        .map(|x| {
-            x.and_then(|(_, _, values)| {
-                create_instance(values, &auto_increment, journeys_pk_type_converter)
-            })
+        .map_rows(|(_, _, values)| {
+            create_instance(values, &auto_increment, journeys_pk_type_converter)
         })
         .collect::<Result<Vec<_>, _>>()?;
     let data = ThroughService::vec_to_map(data);
@@ -55,7 +54,7 @@ fn create_instance(
     mut values: Vec<ParsedValue>,
     auto_increment: &AutoIncrement,
     journeys_pk_type_converter: &FxHashMap<(i32, String), i32>,
-) -> Result<ThroughService, Box<dyn Error>> {
+) -> Result<ThroughService, RowError> {
     let journey_1_id: i32 = values.remove(0).into();
     let journey_1_administration: String = values.remove(0).into();
     let journey_1_stop_id: i32 = values.remove(0).into();
@@ -64,13 +63,15 @@ fn create_instance(
     let bit_field_id: i32 = values.remove(0).into();
     let journey_2_stop_id: Option<i32> = values.remove(0).into();
 
+    let journey_1 = (journey_1_id, journey_1_administration);
     let journey_1_id = *journeys_pk_type_converter
-        .get(&(journey_1_id, journey_1_administration))
-        .ok_or("Unknown legacy ID")?;
+        .get(&journey_1)
+        .ok_or_else(|| RowError::unknown_reference("journey", &journey_1))?;
 
+    let journey_2 = (journey_2_id, journey_2_administration);
     let journey_2_id = *journeys_pk_type_converter
-        .get(&(journey_2_id, journey_2_administration))
-        .ok_or("Unknown legacy ID")?;
+        .get(&journey_2)
+        .ok_or_else(|| RowError::unknown_reference("journey", &journey_2))?;
 
     Ok(ThroughService::new(
         auto_increment.next(),
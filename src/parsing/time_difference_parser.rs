@@ -0,0 +1,316 @@
+// 1 file(s).
+// File(s) read by the parser:
+// ZEITVS
+use std::iter;
+
+use chrono::{NaiveDate, NaiveDateTime};
+use rustc_hash::FxHashMap;
+
+use crate::{
+    error::{Diagnostics, HrdfError, RowError},
+    models::{DaylightSavingPeriod, Model, TimeDifference},
+    parsing::{
+        AdvancedRowMatcher, ColumnDefinition, ExpectedType, FastRowMatcher, FileParser,
+        ParsedValue, RowDefinition, RowParser,
+    },
+    source::HrdfSource,
+    storage::ResourceStorage,
+    utils::{create_offset, create_time_from_value},
+};
+
+// In seconds, the standard time of Switzerland (CET).
+const DEFAULT_UTC_OFFSET: i32 = 3600;
+
+pub fn parse(
+    source: &dyn HrdfSource,
+    diagnostics: &Diagnostics,
+) -> Result<ResourceStorage<TimeDifference>, HrdfError> {
+    log::info!("Parsing ZEITVS...");
+    const ROW_A: i32 = 1;
+    const ROW_B: i32 = 2;
+    const ROW_C: i32 = 3;
+    const ROW_D: i32 = 4;
+
+    #[rustfmt::skip]
+    let row_parser = RowParser::new(vec![
+        // This row contains the time difference and a daylight saving period.
+        RowDefinition::new(ROW_A, Box::new(AdvancedRowMatcher::new(r"^[0-9]{7} [+-][0-9]{4} [+-][0-9]{4} [0-9]{8} [0-9]{4} [0-9]{8} [0-9]{4}")), vec![
+            ColumnDefinition::new(1, 7, ExpectedType::Integer32),
+            ColumnDefinition::new(9, 13, ExpectedType::String),
+            ColumnDefinition::new(15, 19, ExpectedType::String),
+            ColumnDefinition::new(21, 28, ExpectedType::String),
+            ColumnDefinition::new(30, 33, ExpectedType::Integer32),
+            ColumnDefinition::new(35, 42, ExpectedType::String),
+            ColumnDefinition::new(44, 47, ExpectedType::Integer32),
+        ]),
+        // This row contains the time difference, without daylight saving time.
+        RowDefinition::new(ROW_B, Box::new(AdvancedRowMatcher::new(r"^[0-9]{7} [+-][0-9]{4}")), vec![
+            ColumnDefinition::new(1, 7, ExpectedType::Integer32),
+            ColumnDefinition::new(9, 13, ExpectedType::String),
+        ]),
+        // This row refers to the time difference of another stop or country.
+        RowDefinition::new(ROW_C, Box::new(AdvancedRowMatcher::new(r"^[0-9]{7} [0-9]{7}")), vec![
+            ColumnDefinition::new(1, 7, ExpectedType::Integer32),
+            ColumnDefinition::new(9, 15, ExpectedType::Integer32),
+        ]),
+        // This row is ignored.
+        RowDefinition::new(ROW_D, Box::new(FastRowMatcher::new(1, 1, "%", true)), Vec::new()),
+    ]);
+    let parser = match FileParser::new(source, "ZEITVS", row_parser, diagnostics) {
+        Ok(parser) => parser,
+        Err(HrdfError::MissingFile(_)) => return Ok(default_time_differences(diagnostics)),
+        Err(e) => return Err(e),
+    };
+
+    let mut data = FxHashMap::default();
+    // The references are resolved once the whole file has been parsed, as they may refer to a later row.
+    let mut references = Vec::new();
+
+    parser
+        .parse()
+        .try_for_each_located_row(|(id, _, values), line, row| {
+            match id {
+                ROW_A => add_daylight_saving_period(values, &mut data)?,
+                ROW_B => set_utc_offset(values, &mut data)?,
+                ROW_C => references.push(create_reference(values, line, row)),
+                ROW_D => {}
+                _ => unreachable!(),
+            }
+            Ok(())
+        })?;
+
+    copy_time_differences(references, &mut data, diagnostics)?;
+
+    Ok(ResourceStorage::new(data))
+}
+
+// A row referring to the time difference of another stop or country.
+struct Reference {
+    id: i32,
+    reference_id: i32,
+    line: usize,
+    row: String,
+}
+
+// ------------------------------------------------------------------------------------------------
+// --- Data Processing Functions
+// ------------------------------------------------------------------------------------------------
+
+fn add_daylight_saving_period(
+    mut values: Vec<ParsedValue>,
+    data: &mut FxHashMap<i32, TimeDifference>,
+) -> Result<(), RowError> {
+    let id: i32 = values.remove(0).into();
+    let utc_offset: String = values.remove(0).into();
+    let daylight_saving_utc_offset: String = values.remove(0).into();
+    let start_date: String = values.remove(0).into();
+    let start_time: i32 = values.remove(0).into();
+    let end_date: String = values.remove(0).into();
+    let end_time: i32 = values.remove(0).into();
+
+    let utc_offset = parse_utc_offset(&utc_offset)?;
+    let daylight_saving_utc_offset = parse_utc_offset(&daylight_saving_utc_offset)?;
+
+    // The daylight saving time starts at a standard time and ends at a daylight saving time.
+    let start = parse_date_time(&start_date, start_time)? - create_offset(utc_offset);
+    let end = parse_date_time(&end_date, end_time)? - create_offset(daylight_saving_utc_offset);
+
+    data.entry(id)
+        .or_insert_with(|| TimeDifference::new(id, utc_offset))
+        .add_daylight_saving_period(DaylightSavingPeriod::new(
+            daylight_saving_utc_offset,
+            start,
+            end,
+        ));
+
+    Ok(())
+}
+
+fn set_utc_offset(
+    mut values: Vec<ParsedValue>,
+    data: &mut FxHashMap<i32, TimeDifference>,
+) -> Result<(), RowError> {
+    let id: i32 = values.remove(0).into();
+    let utc_offset: String = values.remove(0).into();
+
+    let utc_offset = parse_utc_offset(&utc_offset)?;
+
+    data.entry(id)
+        .or_insert_with(|| TimeDifference::new(id, utc_offset));
+
+    Ok(())
+}
+
+fn create_reference(mut values: Vec<ParsedValue>, line: usize, row: &str) -> Reference {
+    let id: i32 = values.remove(0).into();
+    let reference_id: i32 = values.remove(0).into();
+
+    Reference {
+        id,
+        reference_id,
+        line,
+        row: row.to_string(),
+    }
+}
+
+/// A reference may point to another reference, which is followed until a time difference defined by the file is found.
+fn copy_time_differences(
+    references: Vec<Reference>,
+    data: &mut FxHashMap<i32, TimeDifference>,
+    diagnostics: &Diagnostics,
+) -> Result<(), HrdfError> {
+    let reference_ids: FxHashMap<i32, i32> = references
+        .iter()
+        .map(|reference| (reference.id, reference.reference_id))
+        .collect();
+
+    let mut time_differences = Vec::new();
+    for reference in &references {
+        // The number of steps is bounded, so that a cycle of references ends.
+        let time_difference = iter::successors(Some(reference.reference_id), |id| {
+            reference_ids.get(id).copied()
+        })
+        .take(references.len() + 1)
+        .find_map(|id| data.get(&id));
+
+        match time_difference {
+            Some(time_difference) => {
+                let mut time_difference = time_difference.clone();
+                time_difference.set_id(reference.id);
+                time_differences.push(time_difference);
+            }
+            None => diagnostics.report(
+                RowError::unknown_reference("time difference", reference.reference_id).locate(
+                    "ZEITVS",
+                    reference.line,
+                    &reference.row,
+                ),
+            )?,
+        }
+    }
+
+    for time_difference in time_differences {
+        data.insert(time_difference.id(), time_difference);
+    }
+    Ok(())
+}
+
+// ------------------------------------------------------------------------------------------------
+// --- Helper Functions
+// ------------------------------------------------------------------------------------------------
+
+/// Used if ZEITVS is missing: all the stops are given the standard time of Switzerland, without daylight saving time.
+fn default_time_differences(diagnostics: &Diagnostics) -> ResourceStorage<TimeDifference> {
+    log::warn!("ZEITVS is missing, the time difference of all the stops is +0100.");
+    diagnostics.warn(
+        "ZEITVS",
+        0,
+        "The file is missing, the time difference of all the stops is +0100.",
+    );
+
+    let time_difference = TimeDifference::new(0, DEFAULT_UTC_OFFSET);
+    ResourceStorage::new(FxHashMap::from_iter([(
+        time_difference.id(),
+        time_difference,
+    )]))
+}
+
+/// Returns the offset in seconds, e.g. +0130 becomes 5400.
+fn parse_utc_offset(value: &str) -> Result<i32, RowError> {
+    let (sign, value) = value.split_at(1);
+    let value = value.parse::<i32>()?;
+    if value >= 2400 || value % 100 >= 60 {
+        return Err(RowError::invalid("Invalid time difference."));
+    }
+    let seconds = (value / 100 * 60 + value % 100) * 60;
+
+    Ok(if sign == "-" { -seconds } else { seconds })
+}
+
+fn parse_date_time(date: &str, time: i32) -> Result<NaiveDateTime, RowError> {
+    let date = NaiveDate::parse_from_str(date, "%d%m%Y")?;
+    if time > 2400 || time % 100 >= 60 {
+        return Err(RowError::invalid("Invalid time."));
+    }
+    // The daylight saving time may end at 24:00.
+    let (date, time) = if time == 2400 {
+        (
+            date.succ_opt()
+                .ok_or_else(|| RowError::invalid("Invalid date."))?,
+            0,
+        )
+    } else {
+        (date, time)
+    };
+
+    Ok(NaiveDateTime::new(
+        date,
+        create_time_from_value(time as u32),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        error::{ParsingMode, Severity},
+        fixtures::MemorySource,
+    };
+
+    use super::*;
+
+    const ZEITVS: &str = "\
+8500030 8500000
+8500040 8500030
+8500000 +0100 +0200 31032024 0200 27102024 0300
+";
+
+    #[test]
+    fn references_are_resolved_after_the_whole_file() {
+        let diagnostics = Diagnostics::new(ParsingMode::Strict, None);
+        let source = MemorySource::new().with("ZEITVS", ZEITVS);
+
+        let time_differences = parse(&source, &diagnostics).unwrap();
+        for id in [8500000, 8500030, 8500040] {
+            let time_difference = time_differences.find(id).unwrap();
+            assert_eq!(time_difference.id(), id);
+            assert_eq!(time_difference.utc_offset().local_minus_utc(), 3600);
+            assert_eq!(time_difference.daylight_saving_periods().len(), 1);
+        }
+    }
+
+    #[test]
+    fn missing_file_uses_default_time_difference() {
+        let diagnostics = Diagnostics::new(ParsingMode::Strict, None);
+        let source = MemorySource::new().without("ZEITVS");
+
+        let time_differences = parse(&source, &diagnostics).unwrap();
+        assert_eq!(time_differences.entries().len(), 1);
+        let time_difference = time_differences.find(0).unwrap();
+        assert_eq!(time_difference.utc_offset().local_minus_utc(), 3600);
+        assert!(time_difference.daylight_saving_periods().is_empty());
+
+        let diagnostics = diagnostics.into_entries();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity(), Severity::Warning);
+        assert_eq!(diagnostics[0].file(), "ZEITVS");
+    }
+
+    #[test]
+    fn unknown_reference_is_located() {
+        let source = MemorySource::new().with("ZEITVS", &format!("{ZEITVS}8500050 8700000\n"));
+
+        let error = parse(&source, &Diagnostics::new(ParsingMode::Strict, None)).unwrap_err();
+        assert!(matches!(
+            error,
+            HrdfError::UnknownReference { line: 4, ref row, .. } if row == "8500050 8700000"
+        ));
+
+        let diagnostics = Diagnostics::new(ParsingMode::Lenient, None);
+        let time_differences = parse(&source, &diagnostics).unwrap();
+        assert!(time_differences.find(8500050).is_none());
+        let diagnostics = diagnostics.into_entries();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity(), Severity::Error);
+        assert_eq!(diagnostics[0].line(), 4);
+    }
+}
@@ -1,21 +1,24 @@
 // 1 file(s).
 // File(s) read by the parser:
 // ECKDATEN
-use std::error::Error;
-
 use chrono::NaiveDate;
 
 use crate::{
+    error::{Diagnostics, HrdfError},
     models::{Model, TimetableMetadataEntry},
     parsing::{
         AdvancedRowMatcher, ColumnDefinition, ExpectedType, FastRowMatcher, FileParser,
-        ParsedValue, RowDefinition, RowParser,
+        RowDefinition, RowParser,
     },
+    source::HrdfSource,
     storage::ResourceStorage,
     utils::AutoIncrement,
 };
 
-pub fn parse(path: &str) -> Result<ResourceStorage<TimetableMetadataEntry>, Box<dyn Error>> {
+pub fn parse(
+    source: &dyn HrdfSource,
+    diagnostics: &Diagnostics,
+) -> Result<ResourceStorage<TimetableMetadataEntry>, HrdfError> {
     log::info!("Parsing ECKDATEN...");
     const ROW_A: i32 = 1;
     const ROW_B: i32 = 2;
@@ -23,7 +26,7 @@ pub fn parse(path: &str) -> Result<ResourceStorage<TimetableMetadataEntry>, Box<
     #[rustfmt::skip]
     let row_parser = RowParser::new(vec![
         // This row contains the period start/end date in which timetables are effective.
-        RowDefinition::new(ROW_A, Box::new(AdvancedRowMatcher::new(r"^[0-9]{2}.[0-9]{2}.[0-9]{4}$")?), vec![
+        RowDefinition::new(ROW_A, Box::new(AdvancedRowMatcher::new(r"^[0-9]{2}.[0-9]{2}.[0-9]{4}$")), vec![
             ColumnDefinition::new(1, 10, ExpectedType::String),
         ]),
         // This row contains the name, the creation date, the version and the provider of the timetable.
@@ -31,24 +34,36 @@ pub fn parse(path: &str) -> Result<ResourceStorage<TimetableMetadataEntry>, Box<
             ColumnDefinition::new(1, -1, ExpectedType::String),
         ]),
     ]);
-    let parser = FileParser::new(&format!("{path}/ECKDATEN"), row_parser)?;
+    let parser = FileParser::new(source, "ECKDATEN", row_parser, diagnostics)?;
 
-    let mut data: Vec<ParsedValue> = parser
+    let mut data: Vec<String> = parser
         .parse()
-        .map(|x| x.map(|(_, _, mut values)| values.remove(0)))
+        .map_rows(|(id, _, mut values)| {
+            let value: String = values.remove(0).into();
+            match id {
+                ROW_A => Ok(NaiveDate::parse_from_str(&value, "%d.%m.%Y")?.to_string()),
+                ROW_B => Ok(value),
+                _ => unreachable!(),
+            }
+        })
         .collect::<Result<Vec<_>, _>>()?;
 
-    let start_date: String = data.remove(0).into();
-    let end_date: String = data.remove(0).into();
-    let other_data: String = data.remove(0).into();
+    if data.len() < 3 {
+        return Err(HrdfError::InvalidData(
+            "ECKDATEN must contain the start date, the end date and the timetable description."
+                .to_string(),
+        ));
+    }
+
+    let start_date = data.remove(0);
+    let end_date = data.remove(0);
+    let other_data = data.remove(0);
 
-    let start_date = NaiveDate::parse_from_str(&start_date, "%d.%m.%Y")?;
-    let end_date = NaiveDate::parse_from_str(&end_date, "%d.%m.%Y")?;
     let other_data: Vec<String> = other_data.split('$').map(String::from).collect();
 
-    let rows = vec![
-        ("start_date", start_date.to_string()),
-        ("end_date", end_date.to_string()),
+    let rows = [
+        ("start_date", start_date),
+        ("end_date", end_date),
         ("name", other_data[0].to_owned()),
         ("created_at", other_data[1].to_owned()),
         ("version", other_data[2].to_owned()),
@@ -1,21 +1,24 @@
 // 4 file(s).
 // File(s) read by the parser:
 // BETRIEB_DE, BETRIEB_EN, BETRIEB_FR, BETRIEB_IT
-use std::error::Error;
-
 use regex::Regex;
 use rustc_hash::FxHashMap;
 
 use crate::{
+    error::{Diagnostics, HrdfError, RowError},
     models::{Language, Model, TransportCompany},
     parsing::{
         ColumnDefinition, ExpectedType, FastRowMatcher, FileParser, ParsedValue, RowDefinition,
         RowParser,
     },
+    source::HrdfSource,
     storage::ResourceStorage,
 };
 
-pub fn parse(path: &str) -> Result<ResourceStorage<TransportCompany>, Box<dyn Error>> {
+pub fn parse(
+    source: &dyn HrdfSource,
+    diagnostics: &Diagnostics,
+) -> Result<ResourceStorage<TransportCompany>, HrdfError> {
     log::info!("Parsing BETRIEB_DE...");
     log::info!("Parsing BETRIEB_EN...");
     log::info!("Parsing BETRIEB_FR...");
@@ -33,7 +36,7 @@ pub fn parse(path: &str) -> Result<ResourceStorage<TransportCompany>, Box<dyn Er
             ColumnDefinition::new(9, -1, ExpectedType::String),
         ]),
     ]);
-    let parser = FileParser::new(&format!("{path}/BETRIEB_DE"), row_parser)?;
+    let parser = FileParser::new(source, "BETRIEB_DE", row_parser, diagnostics)?;
 
     let data = parser
         .parse()
@@ -52,19 +55,20 @@ pub fn parse(path: &str) -> Result<ResourceStorage<TransportCompany>, Box<dyn Er
     let data = data.into_iter().flatten().collect();
     let mut data = TransportCompany::vec_to_map(data);
 
-    load_designations(path, &mut data, Language::German)?;
-    load_designations(path, &mut data, Language::English)?;
-    load_designations(path, &mut data, Language::French)?;
-    load_designations(path, &mut data, Language::Italian)?;
+    load_designations(source, diagnostics, &mut data, Language::German)?;
+    load_designations(source, diagnostics, &mut data, Language::English)?;
+    load_designations(source, diagnostics, &mut data, Language::French)?;
+    load_designations(source, diagnostics, &mut data, Language::Italian)?;
 
     Ok(ResourceStorage::new(data))
 }
 
 fn load_designations(
-    path: &str,
+    source: &dyn HrdfSource,
+    diagnostics: &Diagnostics,
     data: &mut FxHashMap<i32, TransportCompany>,
     language: Language,
-) -> Result<(), Box<dyn Error>> {
+) -> Result<(), HrdfError> {
     const ROW_A: i32 = 1;
     const ROW_B: i32 = 2;
 
@@ -84,10 +88,9 @@ fn load_designations(
         Language::French => "BETRIEB_FR",
         Language::Italian => "BETRIEB_IT",
     };
-    let parser = FileParser::new(&format!("{path}/{filename}"), row_parser)?;
+    let parser = FileParser::new(source, filename, row_parser, diagnostics)?;
 
-    parser.parse().try_for_each(|x| {
-        let (id, _, values) = x?;
+    parser.parse().try_for_each_row(|(id, _, values)| {
         if id == ROW_A {
             set_designations(values, data, language)?
         }
@@ -112,13 +115,15 @@ fn set_designations(
     mut values: Vec<ParsedValue>,
     data: &mut FxHashMap<i32, TransportCompany>,
     language: Language,
-) -> Result<(), Box<dyn Error>> {
+) -> Result<(), RowError> {
     let id: i32 = values.remove(0).into();
     let designations = values.remove(0).into();
 
     let (short_name, long_name, full_name) = parse_designations(designations);
 
-    let transport_company = data.get_mut(&id).ok_or("Unknown ID")?;
+    let transport_company = data
+        .get_mut(&id)
+        .ok_or_else(|| RowError::unknown_reference("transport company", id))?;
     transport_company.set_short_name(language, &short_name);
     transport_company.set_long_name(language, &long_name);
     transport_company.set_full_name(language, &full_name);
@@ -1,23 +1,26 @@
 // 1 file(s).
 // File(s) read by the parser:
 // ZUGART
-use std::error::Error;
-
 use rustc_hash::FxHashMap;
 
 use crate::{
+    error::{Diagnostics, HrdfError, RowError},
     models::{Language, Model, TransportType},
     parsing::{
         AdvancedRowMatcher, ColumnDefinition, ExpectedType, FastRowMatcher, FileParser,
         ParsedValue, RowDefinition, RowParser,
     },
+    source::HrdfSource,
     storage::ResourceStorage,
     utils::AutoIncrement,
 };
 
 type TransportTypeAndTypeConverter = (ResourceStorage<TransportType>, FxHashMap<String, i32>);
 
-pub fn parse(path: &str) -> Result<TransportTypeAndTypeConverter, Box<dyn Error>> {
+pub fn parse(
+    source: &dyn HrdfSource,
+    diagnostics: &Diagnostics,
+) -> Result<TransportTypeAndTypeConverter, HrdfError> {
     log::info!("Parsing ZUGART...");
     const ROW_A: i32 = 1;
     const ROW_B: i32 = 2;
@@ -29,7 +32,7 @@ pub fn parse(path: &str) -> Result<TransportTypeAndTypeConverter, Box<dyn Error>
     let row_parser = RowParser::new(vec![
         // This row is used to create a TransportType instance.
         RowDefinition::new(ROW_A, Box::new(
-            AdvancedRowMatcher::new(r"^.{3} [ 0-9]{2}")?
+            AdvancedRowMatcher::new(r"^.{3} [ 0-9]{2}")
         ), vec![
             ColumnDefinition::new(1, 3, ExpectedType::String),
             ColumnDefinition::new(5, 6, ExpectedType::Integer16),
@@ -45,22 +48,22 @@ pub fn parse(path: &str) -> Result<TransportTypeAndTypeConverter, Box<dyn Error>
         ]),
         // This row contains the product class name in a specific language.
         RowDefinition::new(ROW_C, Box::new(
-            AdvancedRowMatcher::new(r"^class.+$")?
+            AdvancedRowMatcher::new(r"^class.+$")
         ), vec![
             ColumnDefinition::new(6, 7, ExpectedType::Integer16),
             ColumnDefinition::new(9, -1, ExpectedType::String),
         ]),
         // This row is ignored.
-        RowDefinition::new(ROW_D, Box::new(AdvancedRowMatcher::new(r"^option.+$")?), Vec::new()),
+        RowDefinition::new(ROW_D, Box::new(AdvancedRowMatcher::new(r"^option.+$")), Vec::new()),
         // This row contains the category name in a specific language.
         RowDefinition::new(ROW_E, Box::new(
-            AdvancedRowMatcher::new(r"^category.+$")?
+            AdvancedRowMatcher::new(r"^category.+$")
         ), vec![
             ColumnDefinition::new(10, 12, ExpectedType::Integer32),
             ColumnDefinition::new(14, -1, ExpectedType::String),
         ]),
     ]);
-    let parser = FileParser::new(&format!("{path}/ZUGART"), row_parser)?;
+    let parser = FileParser::new(source, "ZUGART", row_parser, diagnostics)?;
 
     let auto_increment = AutoIncrement::new();
     let mut data = Vec::new();
@@ -68,29 +71,35 @@ pub fn parse(path: &str) -> Result<TransportTypeAndTypeConverter, Box<dyn Error>
 
     let mut current_language = Language::default();
 
-    for x in parser.parse() {
-        let (id, _, values) = x?;
-        match id {
-            ROW_A => {
-                let transport_type =
-                    create_instance(values, &auto_increment, &mut pk_type_converter);
-                data.push(transport_type);
-            }
-            _ => {
-                let transport_type = data.last_mut().ok_or("Type A row missing.")?;
-
-                match id {
-                    ROW_B => update_current_language(values, &mut current_language),
-                    ROW_C => {
-                        set_product_class_name(values, &mut data, current_language);
+    // The translations belong to the language set by the type B row preceding them.
+    parser
+        .parse()
+        .with_record_start(ROW_B)
+        .try_for_each_row(|(id, _, values)| {
+            match id {
+                ROW_A => {
+                    let transport_type =
+                        create_instance(values, &auto_increment, &mut pk_type_converter);
+                    data.push(transport_type);
+                }
+                _ => {
+                    let transport_type = data
+                        .last_mut()
+                        .ok_or_else(|| RowError::invalid("Type A row missing."))?;
+
+                    match id {
+                        ROW_B => update_current_language(values, &mut current_language),
+                        ROW_C => {
+                            set_product_class_name(values, &mut data, current_language);
+                        }
+                        ROW_D => {}
+                        ROW_E => set_category_name(values, transport_type, current_language),
+                        _ => unreachable!(),
                     }
-                    ROW_D => {}
-                    ROW_E => set_category_name(values, transport_type, current_language),
-                    _ => unreachable!(),
                 }
             }
-        }
-    }
+            Ok(())
+        })?;
 
     let data = TransportType::vec_to_map(data);
 
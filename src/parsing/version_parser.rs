@@ -0,0 +1,167 @@
+// 4 file(s).
+// File(s) read by the parser:
+// BFKOORD_WGS, ECKDATEN, GLEISE_LV95, GLEISE_WGS
+use std::io::BufRead;
+
+use crate::{error::HrdfError, models::Version, source::HrdfSource};
+
+// The only version of the HRDF format supported (see ECKDATEN).
+const SUPPORTED_FORMAT: &str = "5.40.41";
+
+/// Detects the version of the HRDF data.<br>
+/// Returns an error if the data doesn't match the layout of any supported version.
+pub fn parse(source: &dyn HrdfSource) -> Result<Version, HrdfError> {
+    log::info!("Detecting HRDF version...");
+
+    let format = load_format(source)?;
+    if format != SUPPORTED_FORMAT {
+        return Err(HrdfError::InvalidData(format!(
+            "The HRDF format {format} is not supported (ECKDATEN), only {SUPPORTED_FORMAT} is."
+        )));
+    }
+
+    let coordinates_version = load_coordinates_version(source)?;
+    let has_platform_coordinates = exists(source, "GLEISE_LV95")? || exists(source, "GLEISE_WGS")?;
+
+    // The coordinates of the platforms are only provided by V 2.0.5.
+    match (coordinates_version, has_platform_coordinates) {
+        (Some(Version::V_5_40_41_2_0_4), false) => Ok(Version::V_5_40_41_2_0_4),
+        (Some(Version::V_5_40_41_2_0_5), true) => Ok(Version::V_5_40_41_2_0_5),
+        (Some(version), _) => Err(HrdfError::InvalidData(format!(
+            "The layout of BFKOORD_WGS matches {version}, but GLEISE_LV95/GLEISE_WGS are {}.",
+            if has_platform_coordinates {
+                "present"
+            } else {
+                "missing"
+            }
+        ))),
+        (None, _) => Err(HrdfError::InvalidData(
+            "The layout of BFKOORD_WGS doesn't match any supported version.".to_string(),
+        )),
+    }
+}
+
+/// Returns the version of the HRDF format, which is the third field of the description of the timetable.
+fn load_format(source: &dyn HrdfSource) -> Result<String, HrdfError> {
+    let description = non_empty_lines(source, "ECKDATEN")?.nth(2).transpose()?;
+
+    description
+        .as_deref()
+        .and_then(|description| description.split('$').nth(2))
+        .map(|format| format.trim().to_string())
+        .ok_or_else(|| {
+            HrdfError::InvalidData(
+                "ECKDATEN must contain the version of the HRDF format.".to_string(),
+            )
+        })
+}
+
+/// The columns of BFKOORD_WGS are wider in V 2.0.5 than in V 2.0.4.
+fn load_coordinates_version(source: &dyn HrdfSource) -> Result<Option<Version>, HrdfError> {
+    let Some(row) = non_empty_lines(source, "BFKOORD_WGS")?.next().transpose()? else {
+        return Ok(None);
+    };
+
+    // The stop name may be given as a comment after the coordinates.
+    let row = row
+        .split('%')
+        .next()
+        .unwrap_or_default()
+        .trim_end()
+        .as_bytes();
+
+    // Length of the row and 1-based indices of the separators between the columns.
+    let layouts = [
+        (Version::V_5_40_41_2_0_4, 36, [8, 19, 30]),
+        (Version::V_5_40_41_2_0_5, 39, [8, 20, 32]),
+    ];
+
+    Ok(layouts
+        .into_iter()
+        .find(|(_, length, separators)| {
+            row.len() == *length && separators.iter().all(|&i| row[i - 1] == b' ')
+        })
+        .map(|(version, _, _)| version))
+}
+
+fn exists(source: &dyn HrdfSource, file: &str) -> Result<bool, HrdfError> {
+    match source.open(file) {
+        Ok(_) => Ok(true),
+        Err(HrdfError::MissingFile(_)) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+fn non_empty_lines<'a>(
+    source: &'a dyn HrdfSource,
+    file: &str,
+) -> Result<impl Iterator<Item = Result<String, HrdfError>> + 'a, HrdfError> {
+    let lines = source
+        .open(file)?
+        .split(b'\n')
+        .filter_map(|line| match line {
+            Ok(line) => {
+                // The files may not be encoded in UTF-8, but only ASCII characters are relevant here.
+                let line = String::from_utf8_lossy(&line).trim_end().to_string();
+                (!line.is_empty()).then_some(Ok(line))
+            }
+            Err(e) => Some(Err(HrdfError::from(e))),
+        });
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::MemorySource;
+
+    use super::*;
+
+    // The columns of V 2.0.5 are wider.
+    const BFKOORD_WGS: &str = "\
+8500010    7.589562   47.547414     277
+8500020    8.540192   47.378177     408
+";
+
+    #[test]
+    fn version_2_0_4_is_detected() {
+        let source = MemorySource::new();
+        assert_eq!(parse(&source).unwrap(), Version::V_5_40_41_2_0_4);
+    }
+
+    #[test]
+    fn version_2_0_5_is_detected() {
+        for file in ["GLEISE_LV95", "GLEISE_WGS"] {
+            let source = MemorySource::new()
+                .with("BFKOORD_WGS", BFKOORD_WGS)
+                .with(file, "");
+            assert_eq!(parse(&source).unwrap(), Version::V_5_40_41_2_0_5);
+        }
+    }
+
+    #[test]
+    fn inconsistent_layout_is_rejected() {
+        let source = MemorySource::new().with("BFKOORD_WGS", BFKOORD_WGS);
+        assert!(matches!(parse(&source), Err(HrdfError::InvalidData(_))));
+
+        let source = MemorySource::new().with("GLEISE_LV95", "");
+        assert!(matches!(parse(&source), Err(HrdfError::InvalidData(_))));
+
+        let source = MemorySource::new().with("BFKOORD_WGS", "8500010 7.589562 47.547414 277\n");
+        assert!(matches!(parse(&source), Err(HrdfError::InvalidData(_))));
+    }
+
+    #[test]
+    fn unsupported_format_is_rejected() {
+        let source = MemorySource::new().with(
+            "ECKDATEN",
+            "10.12.2023\n14.12.2024\nFahrplan 2024$28.03.2024 10:04:00$5.20.39$SBB\n",
+        );
+        assert!(matches!(
+            parse(&source),
+            Err(HrdfError::InvalidData(reason)) if reason.contains("5.20.39")
+        ));
+
+        let source = MemorySource::new().with("ECKDATEN", "10.12.2023\n14.12.2024\n");
+        assert!(matches!(parse(&source), Err(HrdfError::InvalidData(_))));
+    }
+}
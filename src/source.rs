@@ -0,0 +1,344 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use flate2::{read::DeflateDecoder, Crc};
+use zip::{result::ZipError, CompressionMethod, ZipArchive};
+
+use crate::error::HrdfError;
+
+// ------------------------------------------------------------------------------------------------
+// --- HrdfSource
+// ------------------------------------------------------------------------------------------------
+
+/// Provides the content of the HRDF files to the parsers.
+/// The files may be opened concurrently (see the "parallel" feature).
+pub trait HrdfSource: Send + Sync {
+    /// Opens the HRDF file with the given name, e.g. FPLAN.
+    fn open(&self, file: &str) -> Result<Box<dyn BufRead + Send + '_>, HrdfError>;
+
+    /// Same as open, but the file is read from the given position.<br>
+    /// By default, the bytes before the position are read and discarded, the sources able to seek should override it.
+    fn open_at(
+        &self,
+        file: &str,
+        bytes_offset: u64,
+    ) -> Result<Box<dyn BufRead + Send + '_>, HrdfError> {
+        let mut reader = self.open(file)?;
+        io::copy(&mut (&mut reader).take(bytes_offset), &mut io::sink())?;
+        Ok(reader)
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// --- DirectorySource
+// ------------------------------------------------------------------------------------------------
+
+/// The HRDF files are read from a directory.
+pub struct DirectorySource {
+    path: PathBuf,
+}
+
+impl DirectorySource {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl HrdfSource for DirectorySource {
+    fn open(&self, file: &str) -> Result<Box<dyn BufRead + Send + '_>, HrdfError> {
+        self.open_at(file, 0)
+    }
+
+    fn open_at(
+        &self,
+        file: &str,
+        bytes_offset: u64,
+    ) -> Result<Box<dyn BufRead + Send + '_>, HrdfError> {
+        let mut reader = File::open(self.path.join(file)).map_err(|e| {
+            if e.kind() == io::ErrorKind::NotFound {
+                HrdfError::MissingFile(file.to_string())
+            } else {
+                HrdfError::Io(e)
+            }
+        })?;
+        reader.seek(SeekFrom::Start(bytes_offset))?;
+        Ok(Box::new(BufReader::new(reader)))
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// --- ZipSource
+// ------------------------------------------------------------------------------------------------
+
+/// The HRDF files are read directly from a ZIP archive, without extracting it.
+pub struct ZipSource<R> {
+    // Only used to look up the location of the files in the archive.
+    archive: Mutex<ZipArchive<SharedReader<R>>>,
+    reader: SharedReader<R>,
+}
+
+impl<R: Read + Seek> ZipSource<R> {
+    pub fn new(reader: R) -> Result<Self, HrdfError> {
+        let reader = SharedReader::new(reader);
+        let archive = ZipArchive::new(reader.clone())?;
+        Ok(Self {
+            archive: Mutex::new(archive),
+            reader,
+        })
+    }
+}
+
+impl<R: Read + Seek + Send> HrdfSource for ZipSource<R> {
+    fn open(&self, file: &str) -> Result<Box<dyn BufRead + Send + '_>, HrdfError> {
+        self.open_at(file, 0)
+    }
+
+    /// The stored files are read from the position directly, the compressed ones are decompressed from their start.
+    fn open_at(
+        &self,
+        file: &str,
+        bytes_offset: u64,
+    ) -> Result<Box<dyn BufRead + Send + '_>, HrdfError> {
+        // unwrap: The Mutex is only poisoned if a thread panicked while holding it, in which case the panic has already been propagated.
+        let mut archive = self.archive.lock().unwrap();
+
+        // The files may be located in a subdirectory of the archive.
+        let index = archive
+            .index_for_name(file)
+            .or_else(|| {
+                archive
+                    .file_names()
+                    .position(|name| name.ends_with(&format!("/{file}")))
+            })
+            .ok_or_else(|| HrdfError::MissingFile(file.to_string()))?;
+
+        let entry = archive.by_index_raw(index)?;
+        let (data_start, compressed_size, compression, crc32) = (
+            entry.data_start(),
+            entry.compressed_size(),
+            entry.compression(),
+            entry.crc32(),
+        );
+
+        // The file is decompressed on the fly from its own reader, so that several files can be read at the same time.
+        let mut reader = self.reader.clone();
+
+        let mut reader: Box<dyn BufRead + Send> = match compression {
+            // The checksum can only be verified if the whole file is read.
+            CompressionMethod::Stored if bytes_offset > 0 => {
+                let bytes_offset = bytes_offset.min(compressed_size);
+                reader.seek(SeekFrom::Start(data_start + bytes_offset))?;
+                return Ok(Box::new(BufReader::new(
+                    reader.take(compressed_size - bytes_offset),
+                )));
+            }
+            CompressionMethod::Stored => {
+                reader.seek(SeekFrom::Start(data_start))?;
+                Box::new(BufReader::new(ChecksumReader::new(
+                    reader.take(compressed_size),
+                    crc32,
+                )))
+            }
+            CompressionMethod::Deflated => {
+                reader.seek(SeekFrom::Start(data_start))?;
+                Box::new(BufReader::new(ChecksumReader::new(
+                    DeflateDecoder::new(reader.take(compressed_size)),
+                    crc32,
+                )))
+            }
+            _ => {
+                return Err(HrdfError::Unzip(ZipError::UnsupportedArchive(
+                    "Compression method not supported.",
+                )))
+            }
+        };
+
+        // The compressed files can't be read from a position, the bytes before it are decompressed and discarded.
+        io::copy(&mut (&mut reader).take(bytes_offset), &mut io::sink())?;
+        Ok(reader)
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// --- ChecksumReader
+// ------------------------------------------------------------------------------------------------
+
+/// Checks the CRC-32 of a file of the archive once it has been read entirely,
+/// as the file is decompressed without the zip crate, which would otherwise check it.
+struct ChecksumReader<R> {
+    inner: R,
+    crc: Crc,
+    expected_crc32: u32,
+}
+
+impl<R> ChecksumReader<R> {
+    fn new(inner: R, expected_crc32: u32) -> Self {
+        Self {
+            inner,
+            crc: Crc::new(),
+            expected_crc32,
+        }
+    }
+}
+
+impl<R: Read> Read for ChecksumReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let bytes_read = self.inner.read(buf)?;
+        if bytes_read == 0 && !buf.is_empty() && self.crc.sum() != self.expected_crc32 {
+            return Err(ZipError::InvalidArchive("Invalid checksum").into());
+        }
+        self.crc.update(&buf[..bytes_read]);
+        Ok(bytes_read)
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// --- SharedReader
+// ------------------------------------------------------------------------------------------------
+
+/// Reader sharing its underlying reader with its clones, each clone having its own position.
+struct SharedReader<R> {
+    inner: Arc<Mutex<R>>,
+    position: u64,
+}
+
+impl<R> SharedReader<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(inner)),
+            position: 0,
+        }
+    }
+}
+
+impl<R> Clone for SharedReader<R> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+            position: self.position,
+        }
+    }
+}
+
+impl<R: Read + Seek> Read for SharedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // unwrap: The Mutex is only poisoned if a thread panicked while holding it, in which case the panic has already been propagated.
+        let mut inner = self.inner.lock().unwrap();
+        inner.seek(SeekFrom::Start(self.position))?;
+        let bytes_read = inner.read(buf)?;
+        self.position += bytes_read as u64;
+        Ok(bytes_read)
+    }
+}
+
+impl<R: Read + Seek> Seek for SharedReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.position = match pos {
+            SeekFrom::Start(offset) => offset,
+            _ => {
+                // unwrap: The Mutex is only poisoned if a thread panicked while holding it, in which case the panic has already been propagated.
+                let mut inner = self.inner.lock().unwrap();
+                // The underlying reader is moved to the current position so that relative seeks are correct.
+                inner.seek(SeekFrom::Start(self.position))?;
+                inner.seek(pos)?
+            }
+        };
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    use crate::fixtures::MemorySource;
+
+    use super::*;
+
+    const CONTENT: &str = "8500010 Basel SBB\n";
+
+    fn read(zip: Vec<u8>) -> Result<String, HrdfError> {
+        let source = ZipSource::new(Cursor::new(zip))?;
+        let mut content = String::new();
+        source.open("BAHNHOF")?.read_to_string(&mut content)?;
+        Ok(content)
+    }
+
+    #[test]
+    fn files_are_read() {
+        for compression_method in [CompressionMethod::Stored, CompressionMethod::Deflated] {
+            let source = MemorySource::new().with("BAHNHOF", CONTENT);
+            let content = read(source.to_zip(compression_method)).unwrap();
+            assert_eq!(content, "8500010 Basel SBB\r\n");
+        }
+    }
+
+    #[test]
+    fn files_are_read_from_offset() {
+        let source = MemorySource::new().with("BAHNHOF", CONTENT);
+        let read_at = |source: &dyn HrdfSource| {
+            let mut content = String::new();
+            source
+                .open_at("BAHNHOF", "8500010 ".len() as u64)
+                .unwrap()
+                .read_to_string(&mut content)
+                .unwrap();
+            content
+        };
+
+        for compression_method in [CompressionMethod::Stored, CompressionMethod::Deflated] {
+            let zip = source.to_zip(compression_method);
+            let zip_source = ZipSource::new(Cursor::new(zip)).unwrap();
+            assert_eq!(read_at(&zip_source), "Basel SBB\r\n");
+        }
+
+        let directory = tempfile::tempdir().unwrap();
+        source.write_to(directory.path()).unwrap();
+        assert_eq!(
+            read_at(&DirectorySource::new(directory.path())),
+            "Basel SBB\r\n"
+        );
+        assert_eq!(read_at(&source), "Basel SBB\r\n");
+    }
+
+    #[test]
+    fn missing_file_is_reported() {
+        let source = MemorySource::new().without("BAHNHOF");
+        let zip = source.to_zip(CompressionMethod::Deflated);
+        assert!(matches!(read(zip), Err(HrdfError::MissingFile(file)) if file == "BAHNHOF"));
+    }
+
+    #[test]
+    fn checksum_mismatch_is_reported() {
+        let source = MemorySource::new().with("BAHNHOF", CONTENT);
+        let mut zip = source.to_zip(CompressionMethod::Stored);
+        let start = zip
+            .windows(CONTENT.len() - 1)
+            .position(|window| window == &CONTENT.as_bytes()[..CONTENT.len() - 1])
+            .unwrap();
+        zip[start] = b'9';
+
+        assert!(matches!(
+            read(zip),
+            Err(HrdfError::Unzip(ZipError::InvalidArchive(_)))
+        ));
+    }
+
+    #[test]
+    fn files_are_found_in_subdirectory() {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        writer
+            .start_file("data/BAHNHOF", SimpleFileOptions::default())
+            .unwrap();
+        writer.write_all(CONTENT.as_bytes()).unwrap();
+        let zip = writer.finish().unwrap().into_inner();
+
+        assert_eq!(read(zip).unwrap(), CONTENT);
+    }
+}
@@ -1,18 +1,24 @@
-use std::error::Error;
+use std::{ops::RangeInclusive, sync::Arc, time::Instant};
 
 use chrono::{Days, NaiveDate};
+#[cfg(feature = "mmap-cache")]
+use rkyv::Archived;
 use rustc_hash::{FxHashMap, FxHashSet};
 use serde::{Deserialize, Serialize};
 
 use crate::{
+    error::{Diagnostic, Diagnostics, HrdfError, ParsingMode},
+    filtering::{Area, DateFilter, RegionFilter},
     models::{
-        Attribute, BitField, Direction, ExchangeTimeAdministration, ExchangeTimeJourney,
-        ExchangeTimeLine, Holiday, InformationText, Journey, JourneyPlatform, Line, Model,
-        Platform, Stop, StopConnection, ThroughService, TimetableMetadataEntry, TransportCompany,
-        TransportType, Version,
+        Attribute, BitField, BorderPoint, Direction, ExchangeTimeAdministration,
+        ExchangeTimeJourney, ExchangeTimeLine, Holiday, InformationText, Journey, JourneyPlatform,
+        Line, Model, Platform, Stop, StopConnection, ThroughService, TimeDifference,
+        TimetableMetadataEntry, TransportCompany, TransportType, Version,
     },
+    observer::{LoadEvent, LoadObserver},
     parsing,
-    utils::{count_days_between_two_dates, timetable_end_date, timetable_start_date},
+    source::{DirectorySource, HrdfSource},
+    utils::{count_days_between_two_dates, join_tasks, timetable_end_date, timetable_start_date},
 };
 
 // ------------------------------------------------------------------------------------------------
@@ -20,11 +26,17 @@ use crate::{
 // ------------------------------------------------------------------------------------------------
 
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "mmap-cache",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
 pub struct DataStorage {
     // Time-relevant data.
     bit_fields: ResourceStorage<BitField>,
     holidays: ResourceStorage<Holiday>,
     timetable_metadata: ResourceStorage<TimetableMetadataEntry>,
+    time_differences: ResourceStorage<TimeDifference>,
 
     // Basic data.
     attributes: ResourceStorage<Attribute>,
@@ -36,6 +48,7 @@ pub struct DataStorage {
 
     // Stop data
     stops: ResourceStorage<Stop>,
+    border_points: ResourceStorage<BorderPoint>,
     stop_connections: ResourceStorage<StopConnection>,
 
     // Timetable data
@@ -59,67 +72,298 @@ pub struct DataStorage {
 
     // Additional global data
     default_exchange_time: (i16, i16), // (InterCity exchange time, Exchange time for all other journey types)
+    load_options: LoadOptions,
 }
 
 #[allow(unused)]
 impl DataStorage {
-    pub fn new(version: Version, path: &str) -> Result<Self, Box<dyn Error>> {
-        // Time-relevant data
-        let bit_fields = parsing::load_bit_fields(path)?;
-        let holidays = parsing::load_holidays(path)?;
-        let timetable_metadata = parsing::load_timetable_metadata(path)?;
-
-        // Basic data
-        let (attributes, attributes_pk_type_converter) = parsing::load_attributes(path)?;
-        let (directions, directions_pk_type_converter) = parsing::load_directions(path)?;
-        let information_texts = parsing::load_information_texts(path)?;
-        let lines = parsing::load_lines(path)?;
-        let transport_companies = parsing::load_transport_companies(path)?;
-        let (transport_types, transport_types_pk_type_converter) =
-            parsing::load_transport_types(path)?;
-
-        // Stop data
-        let stop_connections = parsing::load_stop_connections(path, &attributes_pk_type_converter)?;
-        let (stops, default_exchange_time) = parsing::load_stops(version, path)?;
-
-        // Timetable data
-        let (journeys, journeys_pk_type_converter) = parsing::load_journeys(
-            path,
-            &transport_types_pk_type_converter,
-            &attributes_pk_type_converter,
-            &directions_pk_type_converter,
+    /// Parses the HRDF files of the directory in strict mode.<br>
+    /// The parsing mode, the load options and the observer are set with HrdfLoader.
+    pub fn new(version: Version, path: &str) -> Result<Self, HrdfError> {
+        let source = DirectorySource::new(path);
+        let (data_storage, _) = Self::from_source(
+            version,
+            &source,
+            ParsingMode::Strict,
+            LoadOptions::default(),
+            None,
         )?;
-        let (journey_platform, platforms) =
-            parsing::load_platforms(path, &journeys_pk_type_converter)?;
-        let through_service = parsing::load_through_service(path, &journeys_pk_type_converter)?;
-
-        // Exchange times
-        let exchange_times_administration = parsing::load_exchange_times_administration(path)?;
-        let exchange_times_journey =
-            parsing::load_exchange_times_journey(path, &journeys_pk_type_converter)?;
-        let exchange_times_line =
-            parsing::load_exchange_times_line(path, &transport_types_pk_type_converter)?;
-
-        log::info!("Building bit_fields_by_day...");
-        let bit_fields_by_day = create_bit_fields_by_day(&bit_fields, &timetable_metadata)?;
-        log::info!("Building bit_fields_by_stop_id...");
-        let bit_fields_by_stop_id = create_bit_fields_by_stop_id(&journeys);
-        log::info!("Building journeys_by_stop_id_and_bit_field_id...");
-        let journeys_by_stop_id_and_bit_field_id =
-            create_journeys_by_stop_id_and_bit_field_id(&journeys);
-        log::info!("Building stop_connections_by_stop_id...");
-        let stop_connections_by_stop_id = create_stop_connections_by_stop_id(&stop_connections);
-        log::info!("Building exchange_times_administration_map...");
-        let exchange_times_administration_map =
-            create_exchange_times_administration_map(&exchange_times_administration);
-        log::info!("Building exchange_times_journey_map...");
-        let exchange_times_journey_map = create_exchange_times_journey_map(&exchange_times_journey);
+        Ok(data_storage)
+    }
+
+    pub(crate) fn from_source(
+        version: Version,
+        source: &dyn HrdfSource,
+        parsing_mode: ParsingMode,
+        load_options: LoadOptions,
+        observer: Option<Arc<dyn LoadObserver>>,
+    ) -> Result<(Self, Vec<Diagnostic>), HrdfError> {
+        let diagnostics = Diagnostics::new(parsing_mode, observer);
+
+        // The files are loaded in stages, the files of a stage only depend on the files of the previous stages.
+        // If the "parallel" feature is enabled, the files of a stage are loaded concurrently.
+        join_tasks!(
+            // Time-relevant data
+            bit_fields = parsing::load_bit_fields(source, &diagnostics),
+            holidays = parsing::load_holidays(source, &diagnostics),
+            timetable_metadata = parsing::load_timetable_metadata(source, &diagnostics),
+            time_differences = parsing::load_time_differences(source, &diagnostics),
+            // Basic data
+            attributes = parsing::load_attributes(source, &diagnostics),
+            directions = parsing::load_directions(source, &diagnostics),
+            information_texts = load_if(load_options.information_texts, || {
+                parsing::load_information_texts(source, &diagnostics)
+            }),
+            lines = load_if(load_options.lines, || parsing::load_lines(
+                source,
+                &diagnostics
+            )),
+            transport_companies = load_if(load_options.transport_companies, || {
+                parsing::load_transport_companies(source, &diagnostics)
+            }),
+            transport_types = parsing::load_transport_types(source, &diagnostics),
+            // Stop data
+            stops = parsing::load_stops(version, source, &diagnostics),
+            border_points = parsing::load_border_points(source, &diagnostics),
+            // Exchange times
+            exchange_times_administration = load_if(load_options.exchange_times, || {
+                parsing::load_exchange_times_administration(source, &diagnostics)
+            }),
+        );
+        let mut bit_fields = bit_fields?;
+        let holidays = holidays?;
+        let timetable_metadata = timetable_metadata?;
+        let time_differences = time_differences?;
+        let (attributes, attributes_pk_type_converter) = attributes?;
+        let (mut directions, directions_pk_type_converter) = directions?;
+        let information_texts = information_texts?;
+        let mut lines = lines?;
+        let transport_companies = transport_companies?;
+        let (transport_types, transport_types_pk_type_converter) = transport_types?;
+        let (mut stops, default_exchange_time) = stops?;
+        let mut border_points = border_points?;
+        let mut exchange_times_administration = exchange_times_administration?;
+
+        join_tasks!(
+            // Stop data
+            stop_connections = load_if(load_options.stop_connections, || {
+                parsing::load_stop_connections(source, &diagnostics, &attributes_pk_type_converter)
+            }),
+            // Timetable data
+            journeys = load_if(load_options.journeys, || {
+                parsing::load_journeys(
+                    source,
+                    &diagnostics,
+                    &transport_types_pk_type_converter,
+                    &attributes_pk_type_converter,
+                    &directions_pk_type_converter,
+                )
+            }),
+            // Exchange times
+            exchange_times_line = load_if(load_options.exchange_times, || {
+                parsing::load_exchange_times_line(
+                    source,
+                    &diagnostics,
+                    &transport_types_pk_type_converter,
+                )
+            }),
+            // Maps
+            bit_fields_by_day = build_map(&diagnostics, "bit_fields_by_day", || {
+                create_bit_fields_by_day(
+                    &bit_fields,
+                    &timetable_metadata,
+                    load_options.date_range(),
+                )
+            }),
+        );
+        let mut stop_connections = stop_connections?;
+        let (mut journeys, journeys_pk_type_converter) = journeys?;
+        let mut exchange_times_line = exchange_times_line?;
+        let mut bit_fields_by_day = bit_fields_by_day?;
+
+        let date_filter = load_options
+            .date_range()
+            .map(|date_range| {
+                let max_day_offset = journeys
+                    .entries()
+                    .into_iter()
+                    .filter_map(|journey| journey.route().last()?.arrival_time_and_day_offset())
+                    .map(|(_, day_offset)| day_offset)
+                    .max()
+                    .unwrap_or(0);
+                DateFilter::new(
+                    &date_range,
+                    max_day_offset,
+                    &bit_fields,
+                    &timetable_metadata,
+                )
+            })
+            .transpose()?;
+        if let Some(date_filter) = &date_filter {
+            journeys.retain(|journey| date_filter.is_active(journey.bit_field_id()));
+        }
+
+        let region_filter = load_options.has_region_filter().then(|| {
+            RegionFilter::new(load_options.area(), load_options.administrations(), &stops)
+        });
+        if let Some(region_filter) = &region_filter {
+            if load_options.journeys {
+                journeys.retain(|journey| region_filter.is_journey_kept(journey));
+
+                // Only the data referenced by the remaining journeys is kept.
+                let mut stop_ids = FxHashSet::default();
+                let mut line_ids = FxHashSet::default();
+                let mut direction_ids = FxHashSet::default();
+                for journey in journeys.entries() {
+                    stop_ids.extend(journey.route().iter().map(|entry| entry.stop_id()));
+                    line_ids.extend(journey.line_ids());
+                    direction_ids.extend(journey.direction_ids());
+                }
+
+                stops.retain(|stop| stop_ids.contains(&stop.id()));
+                lines.retain(|line| line_ids.contains(&line.id()));
+                directions.retain(|direction| direction_ids.contains(&direction.id()));
+            } else {
+                stops.retain(|stop| region_filter.is_stop_in_area(stop.id()));
+            }
+
+            let is_loaded = |stop_id| stops.data().contains_key(&stop_id);
+            let is_loaded_or_none = |stop_id: Option<i32>| stop_id.is_none_or(is_loaded);
+
+            border_points.retain(|border_point| is_loaded(border_point.stop_id()));
+            stop_connections.retain(|stop_connection| {
+                is_loaded(stop_connection.stop_id_1()) && is_loaded(stop_connection.stop_id_2())
+            });
+            exchange_times_administration
+                .retain(|exchange_time| is_loaded_or_none(exchange_time.stop_id()));
+            exchange_times_line.retain(|exchange_time| is_loaded_or_none(exchange_time.stop_id()));
+        }
+
+        join_tasks!(
+            // Timetable data
+            // These subsystems reference the journeys.
+            platforms = load_if(load_options.journeys && load_options.platforms, || {
+                parsing::load_platforms(version, source, &diagnostics, &journeys_pk_type_converter)
+            }),
+            through_service = load_if(
+                load_options.journeys && load_options.through_services,
+                || {
+                    parsing::load_through_service(source, &diagnostics, &journeys_pk_type_converter)
+                }
+            ),
+            // Exchange times
+            exchange_times_journey =
+                load_if(load_options.journeys && load_options.exchange_times, || {
+                    parsing::load_exchange_times_journey(
+                        source,
+                        &diagnostics,
+                        &journeys_pk_type_converter,
+                    )
+                }),
+            // Maps
+            bit_fields_by_stop_id = build_map(&diagnostics, "bit_fields_by_stop_id", || {
+                create_bit_fields_by_stop_id(&journeys)
+            }),
+            journeys_by_stop_id_and_bit_field_id =
+                build_map(&diagnostics, "journeys_by_stop_id_and_bit_field_id", || {
+                    create_journeys_by_stop_id_and_bit_field_id(&journeys)
+                }),
+            stop_connections_by_stop_id =
+                build_map(&diagnostics, "stop_connections_by_stop_id", || {
+                    create_stop_connections_by_stop_id(&stop_connections)
+                }),
+            exchange_times_administration_map =
+                build_map(&diagnostics, "exchange_times_administration_map", || {
+                    create_exchange_times_administration_map(&exchange_times_administration)
+                }),
+        );
+        let (mut journey_platform, mut platforms) = platforms?;
+        let mut through_service = through_service?;
+        let mut exchange_times_journey = exchange_times_journey?;
+
+        if date_filter.is_some() || region_filter.is_some() {
+            let is_loaded = |journey_id| journeys.data().contains_key(&journey_id);
+
+            let is_active = |bit_field_id| {
+                date_filter
+                    .as_ref()
+                    .is_none_or(|date_filter| date_filter.is_active(bit_field_id))
+            };
+
+            journey_platform.retain(|journey_platform| {
+                is_loaded(journey_platform.journey_id())
+                    && is_active(journey_platform.bit_field_id())
+            });
+            through_service.retain(|through_service| {
+                is_loaded(through_service.journey_1_id())
+                    && is_loaded(through_service.journey_2_id())
+                    && is_active(Some(through_service.bit_field_id()))
+            });
+            exchange_times_journey.retain(|exchange_time| {
+                is_loaded(exchange_time.journey_id_1())
+                    && is_loaded(exchange_time.journey_id_2())
+                    && is_active(exchange_time.bit_field_id())
+            });
+        }
+
+        if date_filter.is_some() {
+            // The bit fields are kept as long as they're referenced, even if they're not active in the date range
+            // (e.g. the bit field of an information text of a journey that is active).
+            let mut bit_field_ids = FxHashSet::default();
+            for journey in journeys.entries() {
+                bit_field_ids.extend(
+                    journey
+                        .metadata()
+                        .values()
+                        .flatten()
+                        .filter_map(|entry| entry.bit_field_id()),
+                );
+            }
+            bit_field_ids.extend(
+                journey_platform
+                    .entries()
+                    .into_iter()
+                    .filter_map(|journey_platform| journey_platform.bit_field_id()),
+            );
+            bit_field_ids.extend(
+                through_service
+                    .entries()
+                    .into_iter()
+                    .map(|through_service| through_service.bit_field_id()),
+            );
+            bit_field_ids.extend(
+                exchange_times_journey
+                    .entries()
+                    .into_iter()
+                    .filter_map(|exchange_time| exchange_time.bit_field_id()),
+            );
+
+            bit_fields.retain(|bit_field| bit_field_ids.contains(&bit_field.id()));
+            for day_bit_field_ids in bit_fields_by_day.values_mut() {
+                day_bit_field_ids.retain(|id| *id == 0 || bit_field_ids.contains(id));
+            }
+        }
+
+        if region_filter.is_some() {
+            let platform_ids: FxHashSet<i32> = journey_platform
+                .entries()
+                .into_iter()
+                .map(|journey_platform| journey_platform.platform_id())
+                .collect();
+            platforms.retain(|platform| platform_ids.contains(&platform.id()));
+        }
+
+        let exchange_times_journey_map =
+            build_map(&diagnostics, "exchange_times_journey_map", || {
+                create_exchange_times_journey_map(&exchange_times_journey)
+            });
 
         let mut data_storage = Self {
             // Time-relevant data
             bit_fields,
             holidays,
             timetable_metadata,
+            time_differences,
             // Basic data
             attributes,
             information_texts,
@@ -130,6 +374,7 @@ impl DataStorage {
             // Stop data
             stop_connections,
             stops,
+            border_points,
             // Timetable data
             journeys,
             journey_platform,
@@ -148,29 +393,47 @@ impl DataStorage {
             exchange_times_journey_map,
             // Additional global data
             default_exchange_time,
+            load_options,
         };
 
-        Ok(data_storage)
+        Ok((data_storage, diagnostics.into_entries()))
     }
 
     // Getters/Setters
 
+    /// The subsystems that have been loaded, the data of the others is empty.
+    pub fn load_options(&self) -> &LoadOptions {
+        &self.load_options
+    }
+
+    pub fn attributes(&self) -> &ResourceStorage<Attribute> {
+        &self.attributes
+    }
+
     pub fn bit_fields(&self) -> &ResourceStorage<BitField> {
         &self.bit_fields
     }
 
+    pub fn border_points(&self) -> &ResourceStorage<BorderPoint> {
+        &self.border_points
+    }
+
+    /// Empty if the journeys are disabled (see LoadOptions).
     pub fn journeys(&self) -> &ResourceStorage<Journey> {
         &self.journeys
     }
 
+    /// Empty if the lines are disabled (see LoadOptions).
     pub fn lines(&self) -> &ResourceStorage<Line> {
         &self.lines
     }
 
+    /// Empty if the platforms or the journeys are disabled (see LoadOptions).
     pub fn platforms(&self) -> &ResourceStorage<Platform> {
         &self.platforms
     }
 
+    /// Empty if the stop connections are disabled (see LoadOptions).
     pub fn stop_connections(&self) -> &ResourceStorage<StopConnection> {
         &self.stop_connections
     }
@@ -187,14 +450,26 @@ impl DataStorage {
         &self.timetable_metadata
     }
 
+    pub fn time_differences(&self) -> &ResourceStorage<TimeDifference> {
+        &self.time_differences
+    }
+
+    /// The time difference of the stop, otherwise the one of its country (e.g. 8500000), otherwise the default one (0).
+    pub fn time_difference_of(&self, stop_id: i32) -> Option<&TimeDifference> {
+        time_difference_ids(stop_id).find_map(|id| self.time_differences.find(id))
+    }
+
+    /// Empty if the exchange times are disabled (see LoadOptions).
     pub fn exchange_times_administration(&self) -> &ResourceStorage<ExchangeTimeAdministration> {
         &self.exchange_times_administration
     }
 
+    /// Empty if the exchange times or the journeys are disabled (see LoadOptions).
     pub fn exchange_times_journey(&self) -> &ResourceStorage<ExchangeTimeJourney> {
         &self.exchange_times_journey
     }
 
+    /// Empty if the exchange times are disabled (see LoadOptions).
     pub fn exchange_times_line(&self) -> &ResourceStorage<ExchangeTimeLine> {
         &self.exchange_times_line
     }
@@ -203,24 +478,29 @@ impl DataStorage {
         &self.bit_fields_by_day
     }
 
+    /// Empty if the journeys are disabled (see LoadOptions).
     pub fn bit_fields_by_stop_id(&self) -> &FxHashMap<i32, FxHashSet<i32>> {
         &self.bit_fields_by_stop_id
     }
 
+    /// Empty if the journeys are disabled (see LoadOptions).
     pub fn journeys_by_stop_id_and_bit_field_id(&self) -> &FxHashMap<(i32, i32), Vec<i32>> {
         &self.journeys_by_stop_id_and_bit_field_id
     }
 
+    /// Empty if the stop connections are disabled (see LoadOptions).
     pub fn stop_connections_by_stop_id(&self) -> &FxHashMap<i32, FxHashSet<i32>> {
         &self.stop_connections_by_stop_id
     }
 
+    /// Empty if the exchange times are disabled (see LoadOptions).
     pub fn exchange_times_administration_map(
         &self,
     ) -> &FxHashMap<(Option<i32>, String, String), i32> {
         &self.exchange_times_administration_map
     }
 
+    /// Empty if the exchange times or the journeys are disabled (see LoadOptions).
     pub fn exchange_times_journey_map(&self) -> &FxHashMap<(i32, i32, i32), FxHashSet<i32>> {
         &self.exchange_times_journey_map
     }
@@ -230,15 +510,323 @@ impl DataStorage {
     }
 }
 
+/// The same getters as DataStorage, but the data is read in place (see MappedHrdf).
+#[cfg(feature = "mmap-cache")]
+impl ArchivedDataStorage {
+    // Getters/Setters
+
+    pub fn load_options(&self) -> LoadOptions {
+        // unwrap: Deserializing a LoadOptions will never fail.
+        rkyv::Deserialize::deserialize(&self.load_options, &mut rkyv::Infallible).unwrap()
+    }
+
+    pub fn attributes(&self) -> &ArchivedResourceStorage<Attribute> {
+        &self.attributes
+    }
+
+    pub fn bit_fields(&self) -> &ArchivedResourceStorage<BitField> {
+        &self.bit_fields
+    }
+
+    pub fn border_points(&self) -> &ArchivedResourceStorage<BorderPoint> {
+        &self.border_points
+    }
+
+    pub fn journeys(&self) -> &ArchivedResourceStorage<Journey> {
+        &self.journeys
+    }
+
+    pub fn lines(&self) -> &ArchivedResourceStorage<Line> {
+        &self.lines
+    }
+
+    pub fn platforms(&self) -> &ArchivedResourceStorage<Platform> {
+        &self.platforms
+    }
+
+    pub fn stop_connections(&self) -> &ArchivedResourceStorage<StopConnection> {
+        &self.stop_connections
+    }
+
+    pub fn stops(&self) -> &ArchivedResourceStorage<Stop> {
+        &self.stops
+    }
+
+    pub fn transport_types(&self) -> &ArchivedResourceStorage<TransportType> {
+        &self.transport_types
+    }
+
+    pub fn timetable_metadata(&self) -> &ArchivedResourceStorage<TimetableMetadataEntry> {
+        &self.timetable_metadata
+    }
+
+    pub fn time_differences(&self) -> &ArchivedResourceStorage<TimeDifference> {
+        &self.time_differences
+    }
+
+    pub fn time_difference_of(&self, stop_id: i32) -> Option<&Archived<TimeDifference>> {
+        time_difference_ids(stop_id).find_map(|id| self.time_differences.find(id))
+    }
+
+    pub fn exchange_times_administration(
+        &self,
+    ) -> &ArchivedResourceStorage<ExchangeTimeAdministration> {
+        &self.exchange_times_administration
+    }
+
+    pub fn exchange_times_journey(&self) -> &ArchivedResourceStorage<ExchangeTimeJourney> {
+        &self.exchange_times_journey
+    }
+
+    pub fn exchange_times_line(&self) -> &ArchivedResourceStorage<ExchangeTimeLine> {
+        &self.exchange_times_line
+    }
+
+    pub fn bit_fields_by_day(&self) -> &Archived<FxHashMap<NaiveDate, FxHashSet<i32>>> {
+        &self.bit_fields_by_day
+    }
+
+    pub fn bit_fields_by_stop_id(&self) -> &Archived<FxHashMap<i32, FxHashSet<i32>>> {
+        &self.bit_fields_by_stop_id
+    }
+
+    pub fn journeys_by_stop_id_and_bit_field_id(
+        &self,
+    ) -> &Archived<FxHashMap<(i32, i32), Vec<i32>>> {
+        &self.journeys_by_stop_id_and_bit_field_id
+    }
+
+    pub fn stop_connections_by_stop_id(&self) -> &Archived<FxHashMap<i32, FxHashSet<i32>>> {
+        &self.stop_connections_by_stop_id
+    }
+
+    #[allow(clippy::type_complexity)]
+    pub fn exchange_times_administration_map(
+        &self,
+    ) -> &Archived<FxHashMap<(Option<i32>, String, String), i32>> {
+        &self.exchange_times_administration_map
+    }
+
+    #[allow(clippy::type_complexity)]
+    pub fn exchange_times_journey_map(
+        &self,
+    ) -> &Archived<FxHashMap<(i32, i32, i32), FxHashSet<i32>>> {
+        &self.exchange_times_journey_map
+    }
+
+    pub fn default_exchange_time(&self) -> (i16, i16) {
+        (self.default_exchange_time.0, self.default_exchange_time.1)
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// --- LoadOptions
+// ------------------------------------------------------------------------------------------------
+
+/// Selects the subsystems to load, the data of the disabled subsystems is left empty.<br>
+/// The stops, the border points, the time differences, the bit fields, the holidays, the timetable metadata, the attributes, the directions and the transport types are always loaded.
+/// By default, everything is loaded.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "mmap-cache",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
+pub struct LoadOptions {
+    journeys: bool,
+    platforms: bool,
+    through_services: bool,
+    exchange_times: bool,
+    stop_connections: bool,
+    information_texts: bool,
+    transport_companies: bool,
+    lines: bool,
+    // Both ends included.
+    date_range: Option<(NaiveDate, NaiveDate)>,
+    area: Option<Area>,
+    // Sorted and without duplicates.
+    administrations: Option<Vec<String>>,
+}
+
+impl Default for LoadOptions {
+    fn default() -> Self {
+        Self {
+            journeys: true,
+            platforms: true,
+            through_services: true,
+            exchange_times: true,
+            stop_connections: true,
+            information_texts: true,
+            transport_companies: true,
+            lines: true,
+            date_range: None,
+            area: None,
+            administrations: None,
+        }
+    }
+}
+
+impl LoadOptions {
+    /// Only the subsystems that are always loaded, e.g. for tools that only need the stops and their coordinates.
+    pub fn minimal() -> Self {
+        Self {
+            journeys: false,
+            platforms: false,
+            through_services: false,
+            exchange_times: false,
+            stop_connections: false,
+            information_texts: false,
+            transport_companies: false,
+            lines: false,
+            date_range: None,
+            area: None,
+            administrations: None,
+        }
+    }
+
+    // Getters/Setters
+
+    pub fn journeys(&self) -> bool {
+        self.journeys
+    }
+
+    pub fn platforms(&self) -> bool {
+        self.platforms
+    }
+
+    pub fn through_services(&self) -> bool {
+        self.through_services
+    }
+
+    pub fn exchange_times(&self) -> bool {
+        self.exchange_times
+    }
+
+    pub fn stop_connections(&self) -> bool {
+        self.stop_connections
+    }
+
+    pub fn information_texts(&self) -> bool {
+        self.information_texts
+    }
+
+    pub fn transport_companies(&self) -> bool {
+        self.transport_companies
+    }
+
+    pub fn lines(&self) -> bool {
+        self.lines
+    }
+
+    pub fn date_range(&self) -> Option<RangeInclusive<NaiveDate>> {
+        self.date_range.map(|(start, end)| start..=end)
+    }
+
+    pub fn area(&self) -> Option<&Area> {
+        self.area.as_ref()
+    }
+
+    pub fn administrations(&self) -> Option<&[String]> {
+        self.administrations.as_deref()
+    }
+
+    /// Parses FPLAN.
+    pub fn with_journeys(mut self, value: bool) -> Self {
+        self.journeys = value;
+        self
+    }
+
+    /// Parses GLEIS, GLEIS_LV95 and GLEIS_WGS. Ignored if the journeys are disabled.
+    pub fn with_platforms(mut self, value: bool) -> Self {
+        self.platforms = value;
+        self
+    }
+
+    /// Parses DURCHBI. Ignored if the journeys are disabled.
+    pub fn with_through_services(mut self, value: bool) -> Self {
+        self.through_services = value;
+        self
+    }
+
+    /// Parses UMSTEIGV, UMSTEIGL and UMSTEIGZ. UMSTEIGZ is ignored if the journeys are disabled.
+    pub fn with_exchange_times(mut self, value: bool) -> Self {
+        self.exchange_times = value;
+        self
+    }
+
+    /// Parses METABHF.
+    pub fn with_stop_connections(mut self, value: bool) -> Self {
+        self.stop_connections = value;
+        self
+    }
+
+    /// Parses INFOTEXT_DE, INFOTEXT_EN, INFOTEXT_FR and INFOTEXT_IT.
+    pub fn with_information_texts(mut self, value: bool) -> Self {
+        self.information_texts = value;
+        self
+    }
+
+    /// Parses BETRIEB_DE, BETRIEB_EN, BETRIEB_FR and BETRIEB_IT.
+    pub fn with_transport_companies(mut self, value: bool) -> Self {
+        self.transport_companies = value;
+        self
+    }
+
+    /// Parses LINIE.
+    pub fn with_lines(mut self, value: bool) -> Self {
+        self.lines = value;
+        self
+    }
+
+    /// Only the data active on at least one day of the date range is kept: the journeys, the bit fields, the platforms of the journeys, the through services and the exchange times between journeys.
+    /// The journeys of the previous operating days that still run in the date range (e.g. after midnight) are kept as well.
+    /// bit_fields_by_day only contains the days of the date range.
+    pub fn with_date_range(mut self, value: RangeInclusive<NaiveDate>) -> Self {
+        self.date_range = Some((*value.start(), *value.end()));
+        self
+    }
+
+    /// Only the journeys touching at least one stop of the area are kept, with the stops, the platforms, the stop connections, the lines, the directions and the exchange times they reference.<br>
+    /// If the journeys are disabled, only the stops of the area are kept.
+    pub fn with_area(mut self, value: Area) -> Self {
+        self.area = Some(value);
+        self
+    }
+
+    /// Only the journeys operated by one of the administrations (see Journey::administration) are kept, with the data they reference.<br>
+    /// Combined with an area, the journeys must match both. Ignored if the journeys are disabled.
+    pub fn with_administrations(mut self, mut value: Vec<String>) -> Self {
+        value.sort();
+        value.dedup();
+        self.administrations = Some(value);
+        self
+    }
+
+    fn has_region_filter(&self) -> bool {
+        self.area.is_some() || self.administrations.is_some()
+    }
+}
+
 // ------------------------------------------------------------------------------------------------
 // --- ResourceStorage
 // ------------------------------------------------------------------------------------------------
 
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "mmap-cache",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
 pub struct ResourceStorage<M: Model<M>> {
     data: FxHashMap<M::K, M>,
 }
 
+impl<M: Model<M>> Default for ResourceStorage<M> {
+    fn default() -> Self {
+        Self::new(FxHashMap::default())
+    }
+}
+
 impl<M: Model<M>> ResourceStorage<M> {
     pub fn new(data: FxHashMap<M::K, M>) -> Self {
         Self { data }
@@ -257,22 +845,75 @@ impl<M: Model<M>> ResourceStorage<M> {
         self.data.values().collect()
     }
 
+    pub(crate) fn retain(&mut self, mut f: impl FnMut(&M) -> bool) {
+        self.data.retain(|_, value| f(value));
+    }
+
     pub fn resolve_ids(&self, ids: &FxHashSet<M::K>) -> Option<Vec<&M>> {
         ids.iter().map(|&id| self.find(id)).collect()
     }
 }
 
+/// The same getters as ResourceStorage, but the data is read in place (see MappedHrdf).
+#[cfg(feature = "mmap-cache")]
+impl<M: Model<M, K = i32> + rkyv::Archive> ArchivedResourceStorage<M> {
+    pub fn data(&self) -> &Archived<FxHashMap<i32, M>> {
+        &self.data
+    }
+
+    pub fn find(&self, k: i32) -> Option<&M::Archived> {
+        self.data.get(&k)
+    }
+
+    pub fn entries(&self) -> Vec<&M::Archived> {
+        self.data.values().collect()
+    }
+}
+
 // ------------------------------------------------------------------------------------------------
 // --- Maps
 // ------------------------------------------------------------------------------------------------
 
+/// Returns empty data if the subsystem is disabled.
+fn load_if<T: Default>(
+    enabled: bool,
+    f: impl FnOnce() -> Result<T, HrdfError>,
+) -> Result<T, HrdfError> {
+    if enabled {
+        f()
+    } else {
+        Ok(T::default())
+    }
+}
+
+/// Reports the building of the map to the observer.
+fn build_map<T>(diagnostics: &Diagnostics, name: &str, f: impl FnOnce() -> T) -> T {
+    log::info!("Building {name}...");
+    diagnostics.notify(LoadEvent::IndexStarted {
+        name: name.to_string(),
+    });
+
+    let now = Instant::now();
+    let map = f();
+
+    diagnostics.notify(LoadEvent::IndexFinished {
+        name: name.to_string(),
+        duration: now.elapsed(),
+    });
+    map
+}
+
+/// If a date range is provided, only its days are kept.
 fn create_bit_fields_by_day(
     bit_fields: &ResourceStorage<BitField>,
     timetable_metadata: &ResourceStorage<TimetableMetadataEntry>,
-) -> Result<FxHashMap<NaiveDate, FxHashSet<i32>>, Box<dyn Error>> {
-    let start_date = timetable_start_date(timetable_metadata)?;
-    let num_days =
-        count_days_between_two_dates(start_date, timetable_end_date(timetable_metadata)?);
+    date_range: Option<RangeInclusive<NaiveDate>>,
+) -> Result<FxHashMap<NaiveDate, FxHashSet<i32>>, HrdfError> {
+    let start_date = timetable_start_date(timetable_metadata)
+        .map_err(|e| HrdfError::InvalidData(e.to_string()))?;
+    let end_date = timetable_end_date(timetable_metadata)
+        .map_err(|e| HrdfError::InvalidData(e.to_string()))?;
+    let num_days = count_days_between_two_dates(start_date, end_date);
 
     let dates: Vec<NaiveDate> = (0..num_days)
         .map(|i| {
@@ -284,10 +925,19 @@ fn create_bit_fields_by_day(
         })
         .collect();
 
+    let is_in_date_range = |date: &NaiveDate| {
+        date_range
+            .as_ref()
+            .is_none_or(|date_range| date_range.contains(date))
+    };
+
     let mut map = FxHashMap::default();
-    dates.iter().for_each(|date| {
-        map.entry(*date).or_insert(FxHashSet::default()).insert(0);
-    });
+    dates
+        .iter()
+        .filter(|date| is_in_date_range(date))
+        .for_each(|date| {
+            map.entry(*date).or_insert(FxHashSet::default()).insert(0);
+        });
 
     let result = bit_fields.data().keys().fold(map, |mut acc, bit_field_id| {
         let bit_field = bit_fields
@@ -303,9 +953,12 @@ fn create_bit_fields_by_day(
             .map(|(i, _)| i)
             .collect();
 
-        indexes.iter().for_each(|&i| {
-            acc.entry(dates[i]).or_default().insert(bit_field.id());
-        });
+        indexes
+            .iter()
+            .filter(|&&i| is_in_date_range(&dates[i]))
+            .for_each(|&i| {
+                acc.entry(dates[i]).or_default().insert(bit_field.id());
+            });
 
         acc
     });
@@ -395,3 +1048,151 @@ fn create_exchange_times_administration_map(
         },
     )
 }
+
+// ------------------------------------------------------------------------------------------------
+// --- Helper Functions
+// ------------------------------------------------------------------------------------------------
+
+/// The IDs of the time differences that may apply to the stop, by order of precedence.
+fn time_difference_ids(stop_id: i32) -> impl Iterator<Item = i32> {
+    // The first 2 digits of a stop ID are the UIC code of its country.
+    [stop_id, stop_id / 100000 * 100000, 0].into_iter()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{error::Severity, fixtures::MemorySource, models::JourneyMetadataType};
+
+    use super::*;
+
+    fn load(source: &MemorySource, load_options: LoadOptions) -> DataStorage {
+        let (data_storage, diagnostics) = DataStorage::from_source(
+            Version::V_5_40_41_2_0_4,
+            source,
+            ParsingMode::Strict,
+            load_options,
+            None,
+        )
+        .unwrap();
+        assert!(diagnostics.is_empty());
+        data_storage
+    }
+
+    #[test]
+    fn minimal_options_only_load_stops() {
+        // The files of the disabled subsystems are not read.
+        let source = MemorySource::new()
+            .without("FPLAN")
+            .without("GLEIS")
+            .without("LINIE");
+        let data_storage = load(&source, LoadOptions::minimal());
+
+        assert_eq!(data_storage.stops().entries().len(), 3);
+        assert_eq!(data_storage.load_options(), &LoadOptions::minimal());
+        assert!(data_storage.journeys().entries().is_empty());
+        assert!(data_storage.platforms().entries().is_empty());
+        assert!(data_storage.lines().entries().is_empty());
+        assert!(data_storage.stop_connections().entries().is_empty());
+        assert!(data_storage.exchange_times_journey().entries().is_empty());
+        assert!(data_storage.bit_fields_by_stop_id().is_empty());
+    }
+
+    #[test]
+    fn disabled_subsystem_is_empty() {
+        let source = MemorySource::new();
+        let data_storage = load(&source, LoadOptions::default());
+        assert!(!data_storage.platforms().entries().is_empty());
+        assert!(!data_storage.lines().entries().is_empty());
+
+        let data_storage = load(
+            &source,
+            LoadOptions::default()
+                .with_platforms(false)
+                .with_lines(false),
+        );
+        assert_eq!(data_storage.journeys().entries().len(), 2);
+        assert!(data_storage.platforms().entries().is_empty());
+        assert!(data_storage.lines().entries().is_empty());
+    }
+
+    #[test]
+    fn invalid_journey_is_skipped_in_lenient_mode() {
+        let fplan = "\
+*Z 00000x 000011   101
+*G IC  8500010 8500030
+*A VE 8500010 8500030 000001
+8500010 Basel SBB                    02330
+8500030 Bern                 -02500
+*Z 000002 000011   101
+*G IR  8500030 8500010
+*A VE 8500030 8500010 000001
+8500030 Bern                         01000
+8500010 Basel SBB             01100
+";
+        let source = MemorySource::new().with("FPLAN", fplan);
+        let load = |parsing_mode| {
+            DataStorage::from_source(
+                Version::V_5_40_41_2_0_4,
+                &source,
+                parsing_mode,
+                LoadOptions::default(),
+                None,
+            )
+        };
+
+        assert!(matches!(
+            load(ParsingMode::Strict),
+            Err(HrdfError::Parse { ref file, line: 1, .. }) if file == "FPLAN"
+        ));
+
+        // The whole journey is skipped, its other rows are reported as warnings.
+        let (data_storage, diagnostics) = load(ParsingMode::Lenient).unwrap();
+        let journeys = data_storage.journeys().entries();
+        assert_eq!(journeys.len(), 1);
+        assert_eq!(journeys[0].first_stop_id(), 8500030);
+        // The rows of the other files referring to the journey are skipped as well.
+        let severities: Vec<_> = diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.file() == "FPLAN")
+            .map(|diagnostic| (diagnostic.line(), diagnostic.severity()))
+            .collect();
+        assert_eq!(
+            severities,
+            vec![
+                (1, Severity::Error),
+                (2, Severity::Warning),
+                (3, Severity::Warning),
+                (4, Severity::Warning),
+                (5, Severity::Warning),
+            ]
+        );
+    }
+
+    #[test]
+    fn date_range_keeps_referenced_bit_fields() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let (data_storage, diagnostics) = DataStorage::from_source(
+            Version::V_5_40_41_2_0_4,
+            &MemorySource::new(),
+            ParsingMode::Strict,
+            LoadOptions::default().with_date_range(date..=date),
+            None,
+        )
+        .unwrap();
+        assert!(diagnostics.is_empty());
+
+        let journey = data_storage.journeys().find(1).unwrap();
+        assert!(data_storage.journeys().find(2).is_none());
+
+        // The information text is only active on the first 2 days of the timetable.
+        let information_texts = journey.metadata_entries(JourneyMetadataType::InformationText);
+        assert_eq!(information_texts.len(), 2);
+        for entry in information_texts {
+            if let Some(bit_field_id) = entry.bit_field_id() {
+                assert!(data_storage.bit_fields().find(bit_field_id).is_some());
+            }
+        }
+        assert!(data_storage.bit_fields().find(3).is_some());
+        assert!(data_storage.bit_fields().find(2).is_none());
+    }
+}
@@ -2,9 +2,10 @@
 // --- AutoIncrement
 // ------------------------------------------------------------------------------------------------
 
-use std::cell::RefCell;
+use std::{cell::RefCell, io, path::Path};
 
-use chrono::{Days, NaiveDate, NaiveTime};
+use chrono::{FixedOffset, NaiveDate, NaiveTime, TimeDelta, Timelike};
+use tempfile::NamedTempFile;
 
 use crate::{models::TimetableMetadataEntry, storage::ResourceStorage};
 
@@ -25,14 +26,9 @@ impl AutoIncrement {
     }
 }
 
-pub fn add_1_day(date: NaiveDate) -> NaiveDate {
-    date.checked_add_days(Days::new(1))
-        .expect("Error adding 1 day to the date.")
-}
-
-pub fn sub_1_day(date: NaiveDate) -> NaiveDate {
-    date.checked_sub_days(Days::new(1))
-        .expect("Error substracting 1 day to the date.")
+pub fn add_days(date: NaiveDate, days: i32) -> NaiveDate {
+    date.checked_add_signed(TimeDelta::days(days.into()))
+        .expect("Error adding days to the date.")
 }
 
 pub fn count_days_between_two_dates(date_1: NaiveDate, date_2: NaiveDate) -> usize {
@@ -49,6 +45,41 @@ pub fn create_time_from_value(value: u32) -> NaiveTime {
     create_time(value / 100, value % 100)
 }
 
+const MINUTES_PER_DAY: i32 = 24 * 60;
+
+/// Returns the time of day and the number of days, e.g. 1530 minutes (25:30) become 01:30 and 1 day.
+pub fn create_time_and_day_offset(minutes: i32) -> (NaiveTime, i32) {
+    let time = minutes.rem_euclid(MINUTES_PER_DAY) as u32;
+    (
+        create_time(time / 60, time % 60),
+        minutes.div_euclid(MINUTES_PER_DAY),
+    )
+}
+
+/// Inverse of create_time_and_day_offset, e.g. 01:30 and 1 day become 1530 minutes (25:30).
+pub fn create_minutes(time: NaiveTime, day_offset: i32) -> i32 {
+    (time.hour() * 60 + time.minute()) as i32 + day_offset * MINUTES_PER_DAY
+}
+
+pub fn create_offset(seconds: i32) -> FixedOffset {
+    FixedOffset::east_opt(seconds).expect("Impossible to create a FixedOffset from seconds.")
+}
+
+/// Creates a temporary file next to the given path and named after it, e.g. data.cache.Ab12Cd.part.<br>
+/// Each call creates another file, so that concurrent writers never write into the same file.
+/// The file is removed when dropped, unless it's persisted (i.e. renamed to the given path).
+pub fn create_temporary_file(path: &Path) -> io::Result<NamedTempFile> {
+    let directory = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    tempfile::Builder::new()
+        .prefix(&format!("{file_name}."))
+        .suffix(".part")
+        .tempfile_in(directory)
+}
+
 pub fn timetable_start_date(
     timetable_metadata: &ResourceStorage<TimetableMetadataEntry>,
 ) -> Result<NaiveDate, &str> {
@@ -72,3 +103,88 @@ pub fn timetable_end_date(
         .value_as_NaiveDate();
     Ok(result)
 }
+
+// ------------------------------------------------------------------------------------------------
+// --- Tasks
+// ------------------------------------------------------------------------------------------------
+
+/// Runs the tasks spawned on the TaskScope, concurrently on the rayon thread pool if the "parallel" feature is enabled, one after another otherwise.<br>
+/// All tasks are completed when this function returns.
+pub fn run_tasks<'scope, F>(f: F)
+where
+    F: for<'a> FnOnce(&TaskScope<'a, 'scope>) + Send,
+{
+    #[cfg(feature = "parallel")]
+    rayon::scope(|scope| f(&TaskScope { scope }));
+    #[cfg(not(feature = "parallel"))]
+    f(&TaskScope {
+        scope: std::marker::PhantomData,
+    });
+}
+
+pub struct TaskScope<'a, 'scope> {
+    #[cfg(feature = "parallel")]
+    scope: &'a rayon::Scope<'scope>,
+    #[cfg(not(feature = "parallel"))]
+    scope: std::marker::PhantomData<&'a &'scope ()>,
+}
+
+impl<'scope> TaskScope<'_, 'scope> {
+    pub fn spawn<F>(&self, task: F)
+    where
+        F: FnOnce() + Send + 'scope,
+    {
+        #[cfg(feature = "parallel")]
+        self.scope.spawn(|_| task());
+        #[cfg(not(feature = "parallel"))]
+        task();
+    }
+}
+
+/// Evaluates the expressions as tasks (see run_tasks) and binds their results to the given names.
+macro_rules! join_tasks {
+    ($($name:ident = $task:expr),+ $(,)?) => {
+        $(let mut $name = None;)+
+        $crate::utils::run_tasks(|scope| {
+            $(scope.spawn(|| $name = Some($task));)+
+        });
+        // unwrap: All tasks have been completed by run_tasks.
+        $(let $name = $name.unwrap();)+
+    };
+}
+
+pub(crate) use join_tasks;
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[test]
+    fn time_and_day_offset_round_trip() {
+        assert_eq!(create_time_and_day_offset(1530), (create_time(1, 30), 1));
+        assert_eq!(create_time_and_day_offset(1440), (create_time(0, 0), 1));
+        assert_eq!(create_time_and_day_offset(1439), (create_time(23, 59), 0));
+        // A negative number of minutes is before the start of the operating day.
+        assert_eq!(create_time_and_day_offset(-30), (create_time(23, 30), -1));
+
+        for minutes in [-2881, -1440, -1, 0, 1, 59, 60, 1439, 1440, 2885, 4320] {
+            let (time, day_offset) = create_time_and_day_offset(minutes);
+            assert_eq!(create_minutes(time, day_offset), minutes);
+        }
+    }
+
+    #[test]
+    fn join_tasks_binds_results() {
+        let counter = AtomicUsize::new(0);
+        let task = |value: i32| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            value
+        };
+
+        join_tasks!(a = task(1), b = task(2), c = Ok::<_, String>(task(3)));
+        assert_eq!((a, b, c), (1, 2, Ok(3)));
+        assert_eq!(counter.load(Ordering::SeqCst), 3);
+    }
+}